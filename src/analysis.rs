@@ -0,0 +1,90 @@
+pub mod behaviour;
+pub mod coverability;
+pub mod structural;
+
+use crate::input_graph::dpnet::DPetriNet;
+use crate::input_graph::pnet::PetriNet;
+
+/// Analyses of the net which can be run instead of checking a formula
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum AnalysisType {
+    Boundedness,
+    /// P-/T-invariants, minimal siphons and traps
+    Structure,
+    /// Deadlocks, liveness of every transition, home markings and reversibility
+    Behaviour,
+}
+
+/// Structure of a (decision) petri net independent of its probabilistic semantics
+pub struct NetStructure {
+    pub places: Vec<String>,
+    pub transitions: Vec<NetTransition>,
+    pub initial_marking: Vec<usize>,
+}
+
+impl NetStructure {
+    /// Matrix C with C[p][t] being the change of tokens in place p when firing transition t
+    pub fn incidence_matrix(&self) -> Vec<Vec<i64>> {
+        let mut matrix = vec![vec![0; self.transitions.len()]; self.places.len()];
+        for (t_index, transition) in self.transitions.iter().enumerate() {
+            for (place, tokens) in &transition.pre {
+                matrix[*place][t_index] -= *tokens as i64;
+            }
+            for (place, tokens) in &transition.succ {
+                matrix[*place][t_index] += *tokens as i64;
+            }
+        }
+        matrix
+    }
+}
+
+pub struct NetTransition {
+    pub name: String,
+    /// Tokens consumed per place as (place index, tokens)
+    pub pre: Vec<(usize, usize)>,
+    /// Tokens produced per place as (place index, tokens)
+    pub succ: Vec<(usize, usize)>,
+    pub fire_rate: f64,
+    /// Whether the transition can be deactivated by a scheduler
+    pub controllable: bool,
+}
+
+impl From<&PetriNet> for NetStructure {
+    fn from(net: &PetriNet) -> Self {
+        NetStructure {
+            places: net.places.iter().map(|p| p.name.clone()).collect(),
+            transitions: net
+                .transitions
+                .iter()
+                .map(|t| NetTransition {
+                    name: t.name.clone(),
+                    pre: t.pre.clone(),
+                    succ: t.succ.clone(),
+                    fire_rate: t.fire_rate,
+                    controllable: false,
+                })
+                .collect(),
+            initial_marking: net.initial_marking.clone(),
+        }
+    }
+}
+
+impl From<&DPetriNet> for NetStructure {
+    fn from(net: &DPetriNet) -> Self {
+        NetStructure {
+            places: net.places.iter().map(|p| p.name.clone()).collect(),
+            transitions: net
+                .transitions
+                .iter()
+                .map(|t| NetTransition {
+                    name: t.name.clone(),
+                    pre: t.pre.clone(),
+                    succ: t.succ.clone(),
+                    fire_rate: t.fire_rate,
+                    controllable: net.c_transitions.contains(t),
+                })
+                .collect(),
+            initial_marking: net.initial_marking.clone(),
+        }
+    }
+}
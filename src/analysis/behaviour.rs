@@ -0,0 +1,352 @@
+use super::NetStructure;
+use crate::input_graph::{Node, TransitionLabels, MDP};
+use petgraph::algo::kosaraju_scc;
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+use petgraph::Direction::{Incoming, Outgoing};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Firing sequence leading from the initial marking to `marking`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FiringPath<S> {
+    pub transitions: Vec<String>,
+    pub marking: S,
+}
+
+/// Liveness levels of a transition, each implying the lower ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Liveness {
+    /// The transition can never fire
+    L0,
+    /// The transition can fire at least once
+    L1,
+    /// The transition can fire arbitrarily often
+    L2,
+    /// The transition can fire infinitely often in one firing sequence
+    L3,
+    /// The transition can eventually fire from every reachable marking
+    L4,
+}
+
+#[derive(Debug)]
+pub struct TransitionLiveness<S> {
+    pub transition: String,
+    pub level: Liveness,
+    /// Path to a marking in which the transition is enabled. None if the transition is L0
+    pub enabled: Option<FiringPath<S>>,
+    /// Path to a marking from which the transition can never fire again. None if it is L4
+    pub dead: Option<FiringPath<S>>,
+}
+
+#[derive(Debug)]
+pub struct BehaviourResult<S> {
+    /// Reachable markings in which no transition is enabled
+    pub dead_markings: Vec<FiringPath<S>>,
+    pub liveness: Vec<TransitionLiveness<S>>,
+    /// Markings which can be reached from every reachable marking
+    pub home_markings: Vec<S>,
+    /// One path into each terminal strongly connected component if there is more than one.
+    /// Markings of different components cannot reach each other, so there is no home marking
+    pub home_witnesses: Vec<FiringPath<S>>,
+    /// Path to a marking from which the initial marking cannot be reached again. None if the
+    /// net is reversible
+    pub irreversible: Option<FiringPath<S>>,
+}
+
+impl<S> BehaviourResult<S> {
+    pub fn is_deadlock_free(&self) -> bool {
+        self.dead_markings.is_empty()
+    }
+
+    pub fn is_reversible(&self) -> bool {
+        self.irreversible.is_none()
+    }
+}
+
+/// Graph of the reachable markings with one edge per firing transition. Schedulers of decision
+/// petri nets are ignored, i.e. every enabled transition may fire
+struct MarkingGraph<'a, S> {
+    graph: DiGraph<&'a S, usize>,
+    initial: NodeIndex,
+    /// BFS tree from the initial marking as (parent, transition)
+    parents: HashMap<NodeIndex, (NodeIndex, usize)>,
+    /// Number of transitions on the BFS tree path from the initial marking to every node
+    distances: HashMap<NodeIndex, usize>,
+    transition_names: Vec<String>,
+}
+
+impl<'a, S: Clone> MarkingGraph<'a, S> {
+    fn new(
+        reach_graph: &'a MDP<S>,
+        initial_marking: &S,
+        labels: &TransitionLabels,
+        net: &NetStructure,
+    ) -> Self
+    where
+        S: PartialEq,
+    {
+        let mut graph = DiGraph::new();
+        let mut indices = HashMap::new();
+        let mut initial = None;
+        for state in reach_graph.node_indices() {
+            if let Node::State(marking) = &reach_graph[state] {
+                let index = graph.add_node(marking);
+                indices.insert(state, index);
+                if marking == initial_marking {
+                    initial = Some(index);
+                }
+            }
+        }
+        // Edges of different actions firing the same transition are only added once
+        let mut added = HashSet::new();
+        for edge in reach_graph.edge_references() {
+            if let Some(transition) = labels.get(&edge.id()) {
+                let state = reach_graph
+                    .neighbors_directed(edge.source(), Incoming)
+                    .next()
+                    .unwrap();
+                let (source, target) = (indices[&state], indices[&edge.target()]);
+                if added.insert((source, target, *transition)) {
+                    graph.add_edge(source, target, *transition);
+                }
+            }
+        }
+        let mut marking_graph = MarkingGraph {
+            graph,
+            initial: initial.unwrap(),
+            parents: HashMap::new(),
+            distances: HashMap::new(),
+            transition_names: net.transitions.iter().map(|t| t.name.clone()).collect(),
+        };
+        marking_graph.build_bfs_tree();
+        marking_graph
+    }
+
+    fn build_bfs_tree(&mut self) {
+        self.distances.insert(self.initial, 0);
+        let mut upcoming = VecDeque::from([self.initial]);
+        while let Some(node) = upcoming.pop_front() {
+            let mut edges: Vec<_> = self.graph.edges_directed(node, Outgoing).collect();
+            edges.sort_by_key(|e| (*e.weight(), e.target()));
+            for edge in edges {
+                if !self.distances.contains_key(&edge.target()) {
+                    self.parents.insert(edge.target(), (node, *edge.weight()));
+                    self.distances
+                        .insert(edge.target(), self.distances[&node] + 1);
+                    upcoming.push_back(edge.target());
+                }
+            }
+        }
+    }
+
+    /// Shortest firing sequence from the initial marking to the node
+    fn path_to(&self, node: NodeIndex) -> FiringPath<S> {
+        let mut transitions = Vec::new();
+        let mut current = node;
+        while let Some((parent, transition)) = self.parents.get(&current) {
+            transitions.push(self.transition_names[*transition].clone());
+            current = *parent;
+        }
+        transitions.reverse();
+        FiringPath {
+            transitions,
+            marking: self.graph[node].clone(),
+        }
+    }
+
+    /// Path to the node closest to the initial marking out of the given ones
+    fn shortest_path_to(&self, nodes: &HashSet<NodeIndex>) -> Option<FiringPath<S>> {
+        nodes
+            .iter()
+            .min_by_key(|n| (self.distances[n], **n))
+            .map(|n| self.path_to(*n))
+    }
+
+    /// Nodes from which one of the targets can be reached
+    fn backward_reachable(&self, targets: impl Iterator<Item = NodeIndex>) -> HashSet<NodeIndex> {
+        let mut reached: HashSet<NodeIndex> = targets.collect();
+        let mut upcoming: Vec<NodeIndex> = reached.iter().copied().collect();
+        while let Some(node) = upcoming.pop() {
+            for pre in self.graph.neighbors_directed(node, Incoming) {
+                if reached.insert(pre) {
+                    upcoming.push(pre);
+                }
+            }
+        }
+        reached
+    }
+}
+
+/// Checks deadlocks, liveness of every transition, home markings and reversibility on the
+/// reachability graph built by `to_labelled_mdp`
+pub fn analyze_behaviour<S>(
+    reach_graph: &MDP<S>,
+    initial_marking: &S,
+    labels: &TransitionLabels,
+    net: &NetStructure,
+) -> BehaviourResult<S>
+where
+    S: Clone + PartialEq,
+{
+    let graph = MarkingGraph::new(reach_graph, initial_marking, labels, net);
+    let nodes: Vec<NodeIndex> = graph.graph.node_indices().collect();
+
+    let mut dead_nodes: Vec<NodeIndex> = nodes
+        .iter()
+        .filter(|n| graph.graph.edges_directed(**n, Outgoing).next().is_none())
+        .copied()
+        .collect();
+    dead_nodes.sort_by_key(|n| (graph.distances[n], *n));
+    let dead_markings = dead_nodes.iter().map(|n| graph.path_to(*n)).collect();
+
+    let sccs = kosaraju_scc(&graph.graph);
+    let mut component = HashMap::new();
+    for (i, scc) in sccs.iter().enumerate() {
+        for node in scc {
+            component.insert(*node, i);
+        }
+    }
+    let bottom: Vec<usize> = (0..sccs.len())
+        .filter(|i| {
+            sccs[*i].iter().all(|n| {
+                graph
+                    .graph
+                    .neighbors_directed(*n, Outgoing)
+                    .all(|succ| component[&succ] == *i)
+            })
+        })
+        .collect();
+
+    let liveness = (0..net.transitions.len())
+        .map(|t| {
+            let firing: HashSet<NodeIndex> = graph
+                .graph
+                .edge_references()
+                .filter(|e| *e.weight() == t)
+                .map(|e| e.source())
+                .collect();
+            // Transition occurs on a cycle, so it can be fired infinitely often
+            let on_cycle = graph
+                .graph
+                .edge_references()
+                .any(|e| *e.weight() == t && component[&e.source()] == component[&e.target()]);
+            let can_fire = graph.backward_reachable(firing.iter().copied());
+            let never_again: HashSet<NodeIndex> = nodes
+                .iter()
+                .filter(|n| !can_fire.contains(n))
+                .copied()
+                .collect();
+            // L2 and L3 coincide on finite reachability graphs
+            let level = match (firing.is_empty(), on_cycle, never_again.is_empty()) {
+                (true, _, _) => Liveness::L0,
+                (false, false, _) => Liveness::L1,
+                (false, true, false) => Liveness::L3,
+                (false, true, true) => Liveness::L4,
+            };
+            TransitionLiveness {
+                transition: net.transitions[t].name.clone(),
+                level,
+                enabled: graph.shortest_path_to(&firing),
+                dead: graph.shortest_path_to(&never_again),
+            }
+        })
+        .collect();
+
+    let (home_markings, home_witnesses) = match bottom.len() {
+        1 => {
+            let mut home_nodes = sccs[bottom[0]].clone();
+            home_nodes.sort();
+            let markings = home_nodes.iter().map(|n| graph.graph[*n].clone()).collect();
+            (markings, Vec::new())
+        }
+        _ => {
+            let mut witnesses: Vec<(FiringPath<S>, NodeIndex)> = bottom
+                .iter()
+                .map(|i| {
+                    let path = graph
+                        .shortest_path_to(&sccs[*i].iter().copied().collect())
+                        .unwrap();
+                    (path, *sccs[*i].iter().min().unwrap())
+                })
+                .collect();
+            witnesses.sort_by_key(|(path, n)| (path.transitions.len(), *n));
+            (
+                Vec::new(),
+                witnesses.into_iter().map(|(path, _)| path).collect(),
+            )
+        }
+    };
+
+    let reaching_initial = graph.backward_reachable([graph.initial].into_iter());
+    let not_reaching_initial: HashSet<NodeIndex> = nodes
+        .iter()
+        .filter(|n| !reaching_initial.contains(n))
+        .copied()
+        .collect();
+
+    BehaviourResult {
+        dead_markings,
+        liveness,
+        home_markings,
+        home_witnesses,
+        irreversible: graph.shortest_path_to(&not_reaching_initial),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_graph::dpnet::Marking;
+    use crate::input_graph::{InputGraph, ParseImpl};
+    use crate::parser::petri_net_parser::PetriNetParser;
+
+    /// A and B alternate until t3 moves the token to the dead marking C. t4 never fires
+    const NET: &str = "
+P = {A, B, C, D}
+G = {
+        {A} -> t1 -> {B},
+        {B} -> t2 -> {A},
+        {B} -> t3 -> {C},
+        {D} -> t4 -> {A}
+}
+M = (1, 0, 0, 0)
+L = (1.0, 1.0, 1.0, 1.0)
+AP = {
+    (a, {(1,0,0,0)})
+}
+";
+
+    fn path(transitions: &[&str], marking: Marking) -> FiringPath<Marking> {
+        FiringPath {
+            transitions: transitions.iter().map(|t| t.to_string()).collect(),
+            marking,
+        }
+    }
+
+    #[test]
+    fn finds_the_shortest_paths_to_dead_and_irreversible_markings() {
+        let net = PetriNetParser::parse(NET).unwrap();
+        let (reach_graph, initial_marking, labels) = net.to_labelled_mdp(2).unwrap();
+        let result = analyze_behaviour(
+            &reach_graph,
+            &initial_marking,
+            &labels,
+            &net.net_structure(),
+        );
+        let dead = path(&["t1", "t3"], vec![0, 0, 1, 0]);
+        assert_eq!(result.dead_markings, vec![dead.clone()]);
+        assert_eq!(result.home_markings, vec![vec![0, 0, 1, 0]]);
+        assert!(result.home_witnesses.is_empty());
+        assert_eq!(result.irreversible, Some(dead.clone()));
+
+        let levels: Vec<Liveness> = result.liveness.iter().map(|l| l.level).collect();
+        assert_eq!(
+            levels,
+            vec![Liveness::L3, Liveness::L3, Liveness::L1, Liveness::L0]
+        );
+        let t3 = &result.liveness[2];
+        assert_eq!(t3.enabled, Some(path(&["t1"], vec![0, 1, 0, 0])));
+        assert_eq!(t3.dead, Some(dead));
+        assert_eq!(result.liveness[3].enabled, None);
+    }
+}
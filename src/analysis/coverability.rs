@@ -0,0 +1,334 @@
+use super::{NetStructure, NetTransition};
+use std::collections::{HashSet, VecDeque};
+use std::fmt::{Debug, Display};
+
+/// Number of tokens of a place in a marking of the coverability tree
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tokens {
+    Finite(usize),
+    /// Arbitrarily many tokens
+    Omega,
+}
+
+impl Tokens {
+    fn le(&self, other: &Tokens) -> bool {
+        match (self, other) {
+            (_, Tokens::Omega) => true,
+            (Tokens::Omega, Tokens::Finite(_)) => false,
+            (Tokens::Finite(a), Tokens::Finite(b)) => a <= b,
+        }
+    }
+
+    fn max(self, other: Tokens) -> Tokens {
+        match self.le(&other) {
+            true => other,
+            false => self,
+        }
+    }
+}
+
+impl Display for Tokens {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tokens::Finite(tokens) => write!(f, "{}", tokens),
+            Tokens::Omega => write!(f, "ω"),
+        }
+    }
+}
+
+impl Debug for Tokens {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+pub type OmegaMarking = Vec<Tokens>;
+
+/// Firing sequence showing that a place is unbounded: after firing `prefix` from the initial
+/// marking, every repetition of `pump` leaves at least as many tokens in every place and strictly
+/// more in the unbounded one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Witness {
+    pub prefix: Vec<String>,
+    pub pump: Vec<String>,
+    /// False if a marking on the way through `pump` already has ω in a place, so `pump` may need
+    /// tokens of other unbounded places, i.e. their pumps have to be repeated first to enable
+    /// `pump` often enough
+    pub repeatable: bool,
+}
+
+/// Maximal number of tokens of a place over all reachable markings
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaceBound {
+    pub place: String,
+    pub bound: Tokens,
+    /// Set if the place is unbounded
+    pub witness: Option<Witness>,
+}
+
+#[derive(Debug)]
+pub struct BoundednessResult {
+    pub places: Vec<PlaceBound>,
+    /// Number of nodes of the coverability tree
+    pub tree_size: usize,
+}
+
+impl BoundednessResult {
+    pub fn is_bounded(&self) -> bool {
+        self.places.iter().all(|p| p.witness.is_none())
+    }
+
+    pub fn unbounded_places(&self) -> impl Iterator<Item = &PlaceBound> {
+        self.places.iter().filter(|p| p.witness.is_some())
+    }
+}
+
+struct TreeNode {
+    marking: OmegaMarking,
+    parent: Option<usize>,
+    /// Transition fired in the parent to reach this node
+    transition: Option<usize>,
+}
+
+/// Karp–Miller coverability tree. A node is not expanded further if its marking already occurs
+/// in the tree
+pub struct CoverabilityTree<'a> {
+    net: &'a NetStructure,
+    nodes: Vec<TreeNode>,
+    /// Witness of the acceleration which introduced ω for a place
+    witnesses: Vec<Option<Witness>>,
+}
+
+impl<'a> CoverabilityTree<'a> {
+    pub fn new(net: &'a NetStructure) -> Self {
+        let root = TreeNode {
+            marking: net
+                .initial_marking
+                .iter()
+                .map(|t| Tokens::Finite(*t))
+                .collect(),
+            parent: None,
+            transition: None,
+        };
+        let mut tree = CoverabilityTree {
+            net,
+            nodes: vec![root],
+            witnesses: vec![None; net.places.len()],
+        };
+        tree.build();
+        tree
+    }
+
+    fn build(&mut self) {
+        let mut seen: HashSet<OmegaMarking> = HashSet::new();
+        seen.insert(self.nodes[0].marking.clone());
+        let mut upcoming = VecDeque::from([0]);
+        while let Some(node) = upcoming.pop_front() {
+            for (t_index, transition) in self.net.transitions.iter().enumerate() {
+                if !is_enabled(transition, &self.nodes[node].marking) {
+                    continue;
+                }
+                let mut succ = fire(transition, &self.nodes[node].marking);
+                self.accelerate(node, t_index, &mut succ);
+                if seen.insert(succ.clone()) {
+                    self.nodes.push(TreeNode {
+                        marking: succ,
+                        parent: Some(node),
+                        transition: Some(t_index),
+                    });
+                    upcoming.push_back(self.nodes.len() - 1);
+                }
+            }
+        }
+    }
+
+    /// Sets every place to ω in which `succ` strictly exceeds a smaller ancestor
+    fn accelerate(&mut self, parent: usize, transition: usize, succ: &mut OmegaMarking) {
+        let mut ancestor = Some(parent);
+        // Whether a marking on the path from the ancestor down to the parent has ω
+        let mut omega_on_path = false;
+        while let Some(index) = ancestor {
+            let marking = &self.nodes[index].marking;
+            omega_on_path |= marking.contains(&Tokens::Omega);
+            let smaller = marking.iter().zip(succ.iter()).all(|(a, s)| a.le(s));
+            if smaller && marking != succ {
+                let repeatable = !omega_on_path;
+                let increased: Vec<usize> = (0..succ.len())
+                    .filter(|p| marking[*p] != succ[*p] && succ[*p] != Tokens::Omega)
+                    .collect();
+                for place in increased {
+                    succ[place] = Tokens::Omega;
+                    // Prefer witnesses which do not depend on other pumps
+                    let replace = match &self.witnesses[place] {
+                        None => true,
+                        Some(witness) => repeatable && !witness.repeatable,
+                    };
+                    if replace {
+                        let mut pump = self.firing_sequence(index, parent);
+                        pump.push(self.net.transitions[transition].name.clone());
+                        self.witnesses[place] = Some(Witness {
+                            prefix: self.firing_sequence(0, index),
+                            pump,
+                            repeatable,
+                        });
+                    }
+                }
+            }
+            ancestor = self.nodes[index].parent;
+        }
+    }
+
+    /// Names of the transitions fired on the tree path from `from` down to its descendant `to`
+    fn firing_sequence(&self, from: usize, to: usize) -> Vec<String> {
+        let mut sequence = Vec::new();
+        let mut current = to;
+        while current != from {
+            let node = &self.nodes[current];
+            sequence.push(self.net.transitions[node.transition.unwrap()].name.clone());
+            current = node.parent.unwrap();
+        }
+        sequence.reverse();
+        sequence
+    }
+
+    pub fn markings(&self) -> impl Iterator<Item = &OmegaMarking> {
+        self.nodes.iter().map(|n| &n.marking)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+fn is_enabled(transition: &NetTransition, marking: &OmegaMarking) -> bool {
+    transition
+        .pre
+        .iter()
+        .all(|(place, tokens)| Tokens::Finite(*tokens).le(&marking[*place]))
+}
+
+fn fire(transition: &NetTransition, marking: &OmegaMarking) -> OmegaMarking {
+    let mut succ = marking.clone();
+    for (place, tokens) in &transition.pre {
+        if let Tokens::Finite(t) = succ[*place] {
+            succ[*place] = Tokens::Finite(t - tokens);
+        }
+    }
+    for (place, tokens) in &transition.succ {
+        if let Tokens::Finite(t) = succ[*place] {
+            succ[*place] = Tokens::Finite(t + tokens);
+        }
+    }
+    succ
+}
+
+/// Builds the coverability tree of the net and derives the bound of every place
+pub fn analyze_boundedness(net: &NetStructure) -> BoundednessResult {
+    let tree = CoverabilityTree::new(net);
+    let mut bounds = vec![Tokens::Finite(0); net.places.len()];
+    for marking in tree.markings() {
+        for (bound, tokens) in bounds.iter_mut().zip(marking) {
+            *bound = bound.max(*tokens);
+        }
+    }
+    let places = net
+        .places
+        .iter()
+        .zip(bounds)
+        .zip(tree.witnesses.iter())
+        .map(|((place, bound), witness)| PlaceBound {
+            place: place.clone(),
+            bound,
+            witness: witness.clone(),
+        })
+        .collect();
+    BoundednessResult {
+        places,
+        tree_size: tree.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_graph::ParseImpl;
+    use crate::mcsp::check_boundedness;
+    use crate::parser::petri_net_parser::PetriNetParser;
+    use crate::McspError;
+
+    /// t1 pumps Q, but t2 needs two tokens of Q, so R is only unbounded after pumping Q
+    const NET: &str = "
+P = {R, P, Q, U}
+G = {
+        {P} -> t0 -> {U},
+        {U} -> t1 -> {U, Q},
+        {U, Q(2)} -> t2 -> {P, R}
+}
+M = (0, 1, 0, 0)
+L = (1.0, 1.0, 1.0)
+AP = {
+    (a, {(0,1,0,0)})
+}
+";
+
+    fn net() -> NetStructure {
+        NetStructure::from(PetriNetParser::parse(NET).unwrap().as_ref())
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn finds_the_bounds_of_the_places() {
+        let result = analyze_boundedness(&net());
+        let bounds: Vec<Tokens> = result.places.iter().map(|p| p.bound).collect();
+        assert_eq!(
+            bounds,
+            vec![
+                Tokens::Omega,
+                Tokens::Finite(1),
+                Tokens::Omega,
+                Tokens::Finite(1)
+            ]
+        );
+        assert!(!result.is_bounded());
+    }
+
+    #[test]
+    fn pump_through_omega_markings_is_not_repeatable() {
+        let result = analyze_boundedness(&net());
+        let witness = |place: usize| result.places[place].witness.clone().unwrap();
+        assert_eq!(
+            witness(2),
+            Witness {
+                prefix: names(&["t0"]),
+                pump: names(&["t1"]),
+                repeatable: true,
+            }
+        );
+        assert!(!witness(0).repeatable);
+    }
+
+    #[test]
+    fn check_reports_the_repeatable_pump() {
+        match check_boundedness(&net()) {
+            Err(McspError::UnboundedPlaces {
+                places,
+                place,
+                prefix,
+                pump,
+            }) => {
+                assert_eq!(places, names(&["R", "Q"]));
+                assert_eq!(place, "Q");
+                assert_eq!(prefix, names(&["t0"]));
+                assert_eq!(pump, Some(names(&["t1"])));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+}
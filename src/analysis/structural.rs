@@ -0,0 +1,272 @@
+use super::NetStructure;
+use std::collections::{BTreeSet, HashSet};
+
+/// Set of places given by their indices
+pub type PlaceSet = BTreeSet<usize>;
+
+/// Results of the structural analysis which only depends on the incidence matrix and the
+/// initial marking, not on the reachability graph
+#[derive(Debug)]
+pub struct StructuralResult {
+    /// Minimal semi-positive P-invariants y with yᵀC = 0, indexed by place
+    pub p_invariants: Vec<Vec<u64>>,
+    /// Minimal semi-positive T-invariants x with Cx = 0, indexed by transition
+    pub t_invariants: Vec<Vec<u64>>,
+    pub siphons: Vec<PlaceSet>,
+    pub traps: Vec<PlaceSet>,
+    /// Whether the siphon with the same index contains a trap marked in the initial marking
+    pub siphon_has_marked_trap: Vec<bool>,
+    /// Upper bound of every place derived from the P-invariants. None if the place is not
+    /// covered by any P-invariant
+    pub place_bounds: Vec<Option<usize>>,
+}
+
+impl StructuralResult {
+    /// True if every place is covered by a P-invariant, i.e. the net is bounded for every
+    /// initial marking
+    pub fn is_structurally_bounded(&self) -> bool {
+        self.place_bounds.iter().all(|b| b.is_some())
+    }
+}
+
+/// Computes invariants, minimal siphons and minimal traps of the net
+pub fn analyze_structure(net: &NetStructure) -> StructuralResult {
+    let incidence = net.incidence_matrix();
+    let transposed: Vec<Vec<i64>> = (0..net.transitions.len())
+        .map(|t| incidence.iter().map(|row| row[t]).collect())
+        .collect();
+    let p_invariants = farkas(&incidence);
+    let t_invariants = farkas(&transposed);
+
+    let pre: Vec<Vec<usize>> = net
+        .transitions
+        .iter()
+        .map(|t| t.pre.iter().map(|(p, _)| *p).collect())
+        .collect();
+    let succ: Vec<Vec<usize>> = net
+        .transitions
+        .iter()
+        .map(|t| t.succ.iter().map(|(p, _)| *p).collect())
+        .collect();
+    let siphons = minimal_siphons(net.places.len(), &pre, &succ);
+    // Traps are the siphons of the reversed net
+    let traps = minimal_siphons(net.places.len(), &succ, &pre);
+    let siphon_has_marked_trap = siphons
+        .iter()
+        .map(|siphon| {
+            maximal_trap(siphon, &pre, &succ)
+                .iter()
+                .any(|p| net.initial_marking[*p] > 0)
+        })
+        .collect();
+
+    let place_bounds = (0..net.places.len())
+        .map(|place| {
+            p_invariants
+                .iter()
+                .filter(|y| y[place] > 0)
+                .map(|y| {
+                    let tokens: u64 = y
+                        .iter()
+                        .zip(&net.initial_marking)
+                        .map(|(w, m)| w * *m as u64)
+                        .sum();
+                    (tokens / y[place]) as usize
+                })
+                .min()
+        })
+        .collect();
+
+    StructuralResult {
+        p_invariants,
+        t_invariants,
+        siphons,
+        traps,
+        siphon_has_marked_trap,
+        place_bounds,
+    }
+}
+
+/// Farkas algorithm: computes the minimal-support semi-positive vectors y with yᵀA = 0 for a
+/// matrix A given as rows
+fn farkas(matrix: &[Vec<i64>]) -> Vec<Vec<u64>> {
+    let rows = matrix.len();
+    let columns = matrix.first().map(|r| r.len()).unwrap_or(0);
+    // Every row is the remaining part of A followed by the combination of original rows
+    let mut table: Vec<(Vec<i64>, Vec<i64>)> = matrix
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut unit = vec![0; rows];
+            unit[i] = 1;
+            (row.clone(), unit)
+        })
+        .collect();
+
+    for column in 0..columns {
+        let mut next: Vec<(Vec<i64>, Vec<i64>)> = table
+            .iter()
+            .filter(|(a, _)| a[column] == 0)
+            .cloned()
+            .collect();
+        for (a1, y1) in table.iter().filter(|(a, _)| a[column] > 0) {
+            for (a2, y2) in table.iter().filter(|(a, _)| a[column] < 0) {
+                let f1 = -a2[column];
+                let f2 = a1[column];
+                let combine = |v1: &[i64], v2: &[i64]| -> Vec<i64> {
+                    v1.iter()
+                        .zip(v2)
+                        .map(|(x1, x2)| f1 * x1 + f2 * x2)
+                        .collect()
+                };
+                let mut a = combine(a1, a2);
+                let mut y = combine(y1, y2);
+                let divisor = a.iter().chain(&y).fold(0, |g, v| gcd(g, v.abs()));
+                if divisor > 1 {
+                    a.iter_mut().for_each(|v| *v /= divisor);
+                    y.iter_mut().for_each(|v| *v /= divisor);
+                }
+                next.push((a, y));
+            }
+        }
+        table = remove_non_minimal(next);
+    }
+    table
+        .into_iter()
+        .map(|(_, y)| y.into_iter().map(|v| v as u64).collect())
+        .collect()
+}
+
+/// Removes duplicate rows and rows whose support is a strict superset of another row's support
+fn remove_non_minimal(table: Vec<(Vec<i64>, Vec<i64>)>) -> Vec<(Vec<i64>, Vec<i64>)> {
+    let supports: Vec<BTreeSet<usize>> = table
+        .iter()
+        .map(|(_, y)| (0..y.len()).filter(|i| y[*i] != 0).collect())
+        .collect();
+    let mut seen = HashSet::new();
+    table
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| {
+            !supports
+                .iter()
+                .any(|other| other.is_subset(&supports[*i]) && *other != supports[*i])
+        })
+        .filter(|(_, row)| seen.insert(row.clone()))
+        .map(|(_, row)| row)
+        .collect()
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b),
+    }
+}
+
+/// Minimal non-empty sets S of places with •S ⊆ S•, i.e. every transition putting tokens into S
+/// also takes tokens from S. `pre` and `succ` contain the input and output places per transition
+fn minimal_siphons(places: usize, pre: &[Vec<usize>], succ: &[Vec<usize>]) -> Vec<PlaceSet> {
+    let mut siphons: Vec<PlaceSet> = Vec::new();
+    let mut visited: HashSet<PlaceSet> = HashSet::new();
+    for place in 0..places {
+        let mut upcoming = vec![PlaceSet::from([place])];
+        while let Some(set) = upcoming.pop() {
+            if !visited.insert(set.clone()) || siphons.iter().any(|s| s.is_subset(&set)) {
+                continue;
+            }
+            // A transition which puts tokens into the set without taking any from it
+            let violating = (0..pre.len()).find(|t| {
+                succ[*t].iter().any(|p| set.contains(p)) && !pre[*t].iter().any(|p| set.contains(p))
+            });
+            match violating {
+                None => {
+                    siphons.retain(|s| !set.is_subset(s));
+                    siphons.push(set);
+                }
+                Some(t) => {
+                    for input in &pre[t] {
+                        let mut extended = set.clone();
+                        extended.insert(*input);
+                        upcoming.push(extended);
+                    }
+                }
+            }
+        }
+    }
+    siphons.sort();
+    siphons
+}
+
+/// Largest trap contained in the given set of places
+fn maximal_trap(set: &PlaceSet, pre: &[Vec<usize>], succ: &[Vec<usize>]) -> PlaceSet {
+    let mut trap = set.clone();
+    loop {
+        // Places from which a transition takes tokens without putting any back into the trap
+        let leaking: Vec<usize> = trap
+            .iter()
+            .copied()
+            .filter(|p| {
+                (0..pre.len())
+                    .any(|t| pre[t].contains(p) && !succ[t].iter().any(|q| trap.contains(q)))
+            })
+            .collect();
+        if leaking.is_empty() {
+            return trap;
+        }
+        leaking.iter().for_each(|p| {
+            trap.remove(p);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_graph::ParseImpl;
+    use crate::parser::petri_net_parser::PetriNetParser;
+
+    /// t1 and t2 move a token between A and B and take C as a resource. D is emptied by t3
+    const NET: &str = "
+P = {A, B, C, D}
+G = {
+        {A, C} -> t1 -> {B},
+        {B} -> t2 -> {A, C},
+        {D} -> t3
+}
+M = (1, 0, 1, 1)
+L = (1.0, 1.0, 1.0)
+AP = {
+    (a, {(1,0,1,1)})
+}
+";
+
+    fn result() -> StructuralResult {
+        let net = PetriNetParser::parse(NET).unwrap();
+        analyze_structure(&NetStructure::from(net.as_ref()))
+    }
+
+    #[test]
+    fn finds_the_minimal_invariants() {
+        let result = result();
+        assert_eq!(
+            result.p_invariants,
+            vec![vec![1, 1, 0, 0], vec![0, 1, 1, 0]]
+        );
+        assert_eq!(result.t_invariants, vec![vec![1, 1, 0]]);
+        assert_eq!(result.place_bounds, vec![Some(1), Some(1), Some(1), None]);
+        assert!(!result.is_structurally_bounded());
+    }
+
+    #[test]
+    fn finds_the_minimal_siphons_and_traps() {
+        let result = result();
+        let sets = |sets: &[&[usize]]| -> Vec<PlaceSet> {
+            sets.iter().map(|s| s.iter().copied().collect()).collect()
+        };
+        assert_eq!(result.siphons, sets(&[&[0, 1], &[1, 2], &[3]]));
+        assert_eq!(result.traps, sets(&[&[0, 1], &[1, 2]]));
+        // D is emptied for good, so its siphon contains no marked trap
+        assert_eq!(result.siphon_has_marked_trap, vec![true, true, false]);
+    }
+}
@@ -0,0 +1,306 @@
+use pest::error::{Error as PestError, LineColLocation};
+use pest::RuleType;
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+
+/// Errors which can occur while parsing, building or checking a model
+#[derive(Debug, PartialEq)]
+pub enum McspError {
+    /// The input file could not be read or an output file could not be written
+    Io { path: String, kind: ErrorKind },
+    /// The input does not match the grammar of the net or the formula
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A value could not be converted into the expected type
+    InvalidValue { value: String, expected: String },
+    /// A transition refers to a place which is not part of the set P
+    UnknownPlace(String),
+    /// The number of fire rates in L does not match the number of transitions
+    RateCountMismatch { rates: usize, transitions: usize },
+    /// A reward section (RP or RT) does not assign a reward to every place or transition
+    RewardCountMismatch {
+        section: String,
+        rewards: usize,
+        expected: usize,
+    },
+    /// The initial marking does not assign tokens to every place
+    MarkingLengthMismatch { places: usize, marking: usize },
+    /// The reachability graph is infinite since `larger` can be reached from the smaller `smaller`
+    Unbounded {
+        smaller: Vec<usize>,
+        larger: Vec<usize>,
+    },
+    /// The coverability analysis found places which can hold arbitrarily many tokens. Firing
+    /// `prefix` and repeating `pump` afterwards increases the tokens of `place` forever. The
+    /// pump is missing if it can only be repeated after pumping other places
+    UnboundedPlaces {
+        places: Vec<String>,
+        place: String,
+        prefix: Vec<String>,
+        pump: Option<Vec<String>>,
+    },
+    /// The formula uses an atomic proposition which is not mapped to any reachable marking
+    UnknownAp(String),
+    /// `P=?`, `R=?` or `S=?` was used on a model with nondeterministic choices
+    NondeterministicQuery,
+    /// The input does not contain 'PHI' exactly once
+    MissingFormula,
+    /// A fraction of `--exact` mode does not fit into 128 bits
+    ExactOverflow,
+    /// Floating point errors stopped interval iteration with bounds `width` apart, which is not
+    /// within twice `max_error`
+    PrecisionNotReached { width: f64, max_error: f64 },
+    /// The threads of `--threads` could not be spawned
+    ThreadPool(String),
+}
+
+impl McspError {
+    /// Exit code the CLI terminates with when this error occurs
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            McspError::Io { .. } | McspError::ThreadPool(_) => 2,
+            McspError::Parse { .. }
+            | McspError::InvalidValue { .. }
+            | McspError::UnknownPlace(_)
+            | McspError::RateCountMismatch { .. }
+            | McspError::RewardCountMismatch { .. }
+            | McspError::MarkingLengthMismatch { .. }
+            | McspError::MissingFormula => 3,
+            McspError::Unbounded { .. } | McspError::UnboundedPlaces { .. } => 4,
+            McspError::UnknownAp(_)
+            | McspError::NondeterministicQuery
+            | McspError::ExactOverflow
+            | McspError::PrecisionNotReached { .. } => 5,
+        }
+    }
+
+    /// Moves the position of a parse error which occurred in a substring starting at `offset`
+    /// of `content` to the position in `content`
+    pub fn with_offset(self, content: &str, offset: usize) -> McspError {
+        match self {
+            McspError::Parse {
+                line,
+                column,
+                message,
+            } => {
+                let prefix = &content[..offset];
+                let line_start = prefix.rfind('\n').map(|i| i + 1).unwrap_or(0);
+                let column = match line {
+                    1 => column + prefix[line_start..].chars().count(),
+                    _ => column,
+                };
+                McspError::Parse {
+                    line: line + prefix.matches('\n').count(),
+                    column,
+                    message,
+                }
+            }
+            error => error,
+        }
+    }
+}
+
+impl Display for McspError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            McspError::Io { path, kind } => match kind {
+                ErrorKind::NotFound => write!(f, "File \"{}\" not found", path),
+                _ => write!(f, "Error occurred while attempting to access {}", path),
+            },
+            McspError::Parse {
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "Syntax error at line {}, column {}: {}",
+                line, column, message
+            ),
+            McspError::InvalidValue { value, expected } => {
+                write!(f, "\"{}\" is not a valid {}", value, expected)
+            }
+            McspError::UnknownPlace(place) => {
+                write!(f, "Place \"{}\" was not found in set P", place)
+            }
+            McspError::RateCountMismatch { rates, transitions } => write!(
+                f,
+                "{} fire rates were detected but there are {} transitions",
+                rates, transitions
+            ),
+            McspError::RewardCountMismatch {
+                section,
+                rewards,
+                expected,
+            } => write!(
+                f,
+                "{} has {} rewards but {} were expected",
+                section, rewards, expected
+            ),
+            McspError::MarkingLengthMismatch { places, marking } => write!(
+                f,
+                "{} places were detected but initial marking has {} places",
+                places, marking
+            ),
+            McspError::Unbounded { smaller, larger } => write!(
+                f,
+                "There is a path from {:?} to {:?}! That is why the graph is infinite",
+                smaller, larger
+            ),
+            McspError::UnboundedPlaces {
+                places,
+                place,
+                prefix,
+                pump: Some(pump),
+            } => write!(
+                f,
+                "The places {{{}}} are unbounded! Firing [{}] and repeating [{}] afterwards \
+                increases the tokens of \"{}\" forever",
+                places.join(", "),
+                prefix.join(", "),
+                pump.join(", "),
+                place
+            ),
+            McspError::UnboundedPlaces {
+                places,
+                place,
+                prefix,
+                pump: None,
+            } => write!(
+                f,
+                "The places {{{}}} are unbounded! After firing [{}] the tokens of \"{}\" can be \
+                increased forever",
+                places.join(", "),
+                prefix.join(", "),
+                place
+            ),
+            McspError::UnknownAp(ap) => write!(
+                f,
+                "Formula contains an ap with the name \"{}\" but is not mapped to a marking",
+                ap
+            ),
+            McspError::NondeterministicQuery => write!(
+                f,
+                "P=?, R=? and S=? are undefined for nets with controllable transitions, use their min \
+                or max variants"
+            ),
+            McspError::MissingFormula => write!(f, "Formula must contain 'PHI' exactly once"),
+            McspError::ExactOverflow => write!(
+                f,
+                "Exact probabilities exceed 128 bits, the model is too large for --exact"
+            ),
+            McspError::PrecisionNotReached { width, max_error } => write!(
+                f,
+                "Interval iteration stalled with bounds {} apart, a max error of {} cannot be \
+                guaranteed",
+                width, max_error
+            ),
+            McspError::ThreadPool(message) => {
+                write!(f, "Failed to start the worker threads: {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for McspError {}
+
+impl<R: RuleType> From<PestError<R>> for McspError {
+    fn from(error: PestError<R>) -> Self {
+        let (line, column) = match error.line_col {
+            LineColLocation::Pos(pos) => pos,
+            LineColLocation::Span(start, _) => start,
+        };
+        McspError::Parse {
+            line,
+            column,
+            message: error.variant.message().into_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::McspError;
+    use crate::input_graph::{InputGraph, ParseImpl};
+    use crate::logic::{parse_formula, LogicType};
+    use crate::parser::petri_net_parser::PetriNetParser;
+
+    const NET: &str = "P = {A, B}
+G = {
+        {A} -> t1 -> {B}
+}
+M = (1, 0)
+L = (1.0)
+AP = {
+    (b, {(0,1)})
+}
+";
+
+    fn parse_error(content: &str) -> McspError {
+        PetriNetParser::parse(content).err().unwrap()
+    }
+
+    #[test]
+    fn errors_of_the_net_point_at_its_position_in_the_file() {
+        let error = parse_error(&NET.replace("{A} -> t1", "{A} => t1"));
+        assert!(matches!(
+            error,
+            McspError::Parse {
+                line: 3,
+                column: 9,
+                ..
+            }
+        ));
+        assert_eq!(error.exit_code(), 3);
+    }
+
+    #[test]
+    fn errors_of_the_formula_point_at_its_position_in_the_file() {
+        let content = format!("{}PHI = P(F(b), >= )", NET);
+        let error = parse_formula(LogicType::Pctl, &content).err().unwrap();
+        assert!(matches!(
+            error,
+            McspError::Parse {
+                line: 10,
+                column: 18,
+                ..
+            }
+        ));
+        assert!(error
+            .to_string()
+            .starts_with("Syntax error at line 10, column 18"));
+        assert_eq!(
+            parse_formula(LogicType::Pctl, NET).err(),
+            Some(McspError::MissingFormula)
+        );
+    }
+
+    #[test]
+    fn invalid_nets_exit_with_the_code_of_their_category() {
+        let error = parse_error(&NET.replace("L = (1.0)", "L = (1.0, 2.0)"));
+        assert_eq!(
+            error,
+            McspError::RateCountMismatch {
+                rates: 2,
+                transitions: 1
+            }
+        );
+        assert_eq!(error.exit_code(), 3);
+        let error = parse_error(&NET.replace("-> {B}", "-> {A, C}"));
+        assert_eq!(error, McspError::UnknownPlace("C".into()));
+        assert_eq!(error.exit_code(), 3);
+        // t1 puts a token back into A, so B grows forever
+        let net = PetriNetParser::parse(&NET.replace("-> {B}", "-> {A, B}")).unwrap();
+        let error = net.to_mdp(2).err().unwrap();
+        assert_eq!(
+            error,
+            McspError::Unbounded {
+                smaller: vec![1, 0],
+                larger: vec![1, 1]
+            }
+        );
+        assert_eq!(error.exit_code(), 4);
+    }
+}
@@ -1,13 +1,79 @@
 pub mod dpnet;
 pub mod pnet;
 
-use petgraph::stable_graph::StableDiGraph;
+use crate::analysis::NetStructure;
+use crate::error::McspError;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex, StableDiGraph};
+use petgraph::Outgoing;
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 
 #[allow(clippy::upper_case_acronyms)]
 pub type MDP<T> = StableDiGraph<Node<T>, f64>;
 pub type ApMap<T> = HashMap<String, HashSet<T>>;
+/// Index of the transition fired along each edge from an action to a state. The self-loops of
+/// dead markings have no label
+pub type TransitionLabels = HashMap<EdgeIndex, usize>;
+/// Reachability graph, initial state and transition labels built by `to_labelled_mdp`
+pub type LabelledMDP<S> = (MDP<S>, S, TransitionLabels);
+
+/// Rewards given in the optional sections RM, RP and RT of the net file
+#[derive(Debug, Clone, Default)]
+pub struct Rewards {
+    /// Reward of single markings (RM)
+    pub markings: HashMap<Vec<usize>, f64>,
+    /// Reward per token of every place (RP)
+    pub places: Vec<f64>,
+    /// Reward gained whenever a transition fires (RT)
+    pub transitions: Vec<f64>,
+}
+
+impl Rewards {
+    /// Reward of staying one step in the marking: its marking reward plus the weighted tokens
+    pub fn marking_reward(&self, marking: &[usize]) -> f64 {
+        let tokens: f64 = self
+            .places
+            .iter()
+            .zip(marking)
+            .fold(0.0, |sum, (reward, t)| sum + reward * *t as f64);
+        tokens + self.markings.get(marking).copied().unwrap_or(0.0)
+    }
+
+    pub fn transition_reward(&self, transition: usize) -> f64 {
+        self.transitions.get(transition).copied().unwrap_or(0.0)
+    }
+}
+
+/// Weights of the edges from actions to states of the reachability graph
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EdgeWeights {
+    /// Jump probabilities rounded to the given number of decimal digits
+    Probabilities(i32),
+    /// Fire rates of the transitions, i.e. the continuous-time semantics of the net
+    Rates,
+}
+
+impl EdgeWeights {
+    /// Weight of firing a transition with `rate` while transitions with `sum_rates` are enabled
+    pub fn weight(&self, rate: f64, sum_rates: f64) -> f64 {
+        match self {
+            EdgeWeights::Probabilities(precision) => {
+                let probability = rate / sum_rates;
+                (probability * 10.0_f64.powi(*precision)).round() / 10.0_f64.powi(*precision)
+            }
+            EdgeWeights::Rates => rate,
+        }
+    }
+
+    /// Weight of the self-loop of a marking in which no transition can fire. The marking is
+    /// never left, so no rate leads out of it
+    pub fn dead_weight(&self) -> f64 {
+        match self {
+            EdgeWeights::Probabilities(_) => 1.0,
+            EdgeWeights::Rates => 0.0,
+        }
+    }
+}
 
 #[derive(clap::ValueEnum, Clone, Default)]
 pub enum InputGraphType {
@@ -71,21 +137,155 @@ where
 }
 
 pub trait ParseImpl<T: InputGraph> {
-    fn parse(content: &str) -> Box<T>;
+    fn parse(content: &str) -> Result<Box<T>, McspError>;
 }
 
 pub trait InputGraph {
     type S: State;
-    fn validate_graph(&mut self, graph: &MDP<Self::S>);
-    fn to_mdp(&self, precision: i32) -> (MDP<Self::S>, Self::S);
+    fn validate_graph(&self, graph: &MDP<Self::S>) -> ApMap<Self::S>;
+    fn to_weighted_mdp(&self, weights: EdgeWeights) -> Result<LabelledMDP<Self::S>, McspError>;
+    /// Reachability graph with the jump probabilities rounded to `precision` digits. The rounded
+    /// probabilities of an action may add up to a little more or less than 1, so they are divided
+    /// by their sum
+    fn to_labelled_mdp(&self, precision: i32) -> Result<LabelledMDP<Self::S>, McspError> {
+        let (mut graph, initial, labels) =
+            self.to_weighted_mdp(EdgeWeights::Probabilities(precision))?;
+        normalize_actions(&mut graph);
+        Ok((graph, initial, labels))
+    }
+    /// Continuous-time MDP whose edges carry the fire rates instead of probabilities
+    fn to_ctmdp(&self) -> Result<LabelledMDP<Self::S>, McspError> {
+        self.to_weighted_mdp(EdgeWeights::Rates)
+    }
+    fn to_mdp(&self, precision: i32) -> Result<(MDP<Self::S>, Self::S), McspError> {
+        let (reach_graph, initial_marking, _) = self.to_labelled_mdp(precision)?;
+        Ok((reach_graph, initial_marking))
+    }
     fn get_ap_map(&self) -> &ApMap<Self::S>;
     fn get_init_state(&self) -> &Self::S;
+    /// Reward of staying one step in the state
+    fn state_reward(&self, state: &Self::S) -> f64;
+    /// Reward of firing the transition with the given index
+    fn transition_reward(&self, transition: usize) -> f64;
+    /// Places and transitions of the underlying net
+    fn net_structure(&self) -> NetStructure;
 }
 
 pub trait State: Debug + Clone + PartialEq {
     fn le(&self, other: &Self) -> bool;
 }
 
+/// Divides the weights of the edges leaving every action by their sum. Actions whose
+/// probabilities were all rounded to 0 are left unchanged
+pub fn normalize_actions<S>(graph: &mut MDP<S>) {
+    let actions: Vec<NodeIndex> = graph
+        .node_indices()
+        .filter(|n| matches!(graph[*n], Node::Action(_)))
+        .collect();
+    for action in actions {
+        let total: f64 = graph
+            .edges_directed(action, Outgoing)
+            .map(|e| e.weight())
+            .sum();
+        if total > 0.0 {
+            let mut edges = graph.neighbors_directed(action, Outgoing).detach();
+            while let Some(edge) = edges.next_edge(graph) {
+                graph[edge] /= total;
+            }
+        }
+    }
+}
+
+/// Returns a state on the breadth first search path from the initial state to `parent` which is
+/// strictly smaller than `state`. Firing the transitions between that state and `state` over and
+/// over again increases the tokens forever, so the reachability graph is infinite
+pub fn smaller_predecessor<'a, S: State>(
+    graph: &'a MDP<S>,
+    predecessors: &HashMap<NodeIndex, NodeIndex>,
+    state: &S,
+    parent: NodeIndex,
+) -> Option<&'a S> {
+    let mut current = Some(parent);
+    while let Some(index) = current {
+        if let Node::State(predecessor) = &graph[index] {
+            if predecessor.le(state) && predecessor != state {
+                return Some(predecessor);
+            }
+        }
+        current = predecessors.get(&index).copied();
+    }
+    None
+}
+
 pub trait GenericInputGraph {}
 pub trait GenericMDP {}
 pub trait GenericApMap {}
+
+#[cfg(test)]
+mod tests {
+    use super::dpnet::Marking;
+    use super::{InputGraph, Node, ParseImpl, MDP};
+    use crate::error::McspError;
+    use crate::parser::dpn_parser::DPetriNetParser;
+    use crate::parser::petri_net_parser::PetriNetParser;
+    use std::collections::HashSet;
+
+    /// 40 tokens circle between three places, which gives 41 * 42 / 2 = 861 markings
+    const CIRCLE: &str = "
+P = {A, B, C}
+G = {
+        {A} -> t1 -> {B},
+        {B} -> t2 -> {C},
+        {C} -> t3 -> {A}
+}
+C = {t1}
+M = (40, 0, 0)
+L = (1.0, 1.0, 1.0)
+AP = {
+    (a, {(40,0,0)})
+}
+";
+
+    fn markings(graph: &MDP<Marking>) -> Vec<Marking> {
+        graph
+            .node_weights()
+            .filter_map(|n| match n {
+                Node::State(marking) => Some(marking.clone()),
+                Node::Action(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn adds_every_reachable_marking_once() {
+        let net = PetriNetParser::parse(&CIRCLE.replace("C = {t1}\n", "")).unwrap();
+        let (graph, _) = net.to_mdp(2).unwrap();
+        let petri_markings = markings(&graph);
+        assert_eq!(petri_markings.len(), 861);
+        let unique: HashSet<&Marking> = petri_markings.iter().collect();
+        assert_eq!(unique.len(), 861);
+        assert!(petri_markings.iter().all(|m| m.iter().sum::<usize>() == 40));
+
+        let net = DPetriNetParser::parse(CIRCLE).unwrap();
+        let (graph, _) = net.to_mdp(2).unwrap();
+        let decision_markings: HashSet<Marking> = markings(&graph).into_iter().collect();
+        assert_eq!(decision_markings.len(), 861);
+        assert_eq!(decision_markings, unique.into_iter().cloned().collect());
+    }
+
+    #[test]
+    fn detects_growth_along_the_path_from_the_initial_marking() {
+        // t2 puts the token back into A and adds one to C
+        let net = CIRCLE
+            .replace("{B} -> t2 -> {C}", "{B} -> t2 -> {A, C}")
+            .replace("(40, 0, 0)", "(1, 0, 0)");
+        let expected = || McspError::Unbounded {
+            smaller: vec![1, 0, 0],
+            larger: vec![1, 0, 1],
+        };
+        let petri = PetriNetParser::parse(&net.replace("C = {t1}\n", "")).unwrap();
+        assert_eq!(petri.to_mdp(2).err(), Some(expected()));
+        let decision = DPetriNetParser::parse(&net).unwrap();
+        assert_eq!(decision.to_mdp(2).err(), Some(expected()));
+    }
+}
@@ -1,15 +1,17 @@
 use super::{
-    ApMap, GenericApMap, GenericMDP, InputGraph, Node,
+    smaller_predecessor, ApMap, EdgeWeights, GenericApMap, GenericMDP, InputGraph, LabelledMDP,
+    Node,
     Node::{Action, State},
-    MDP,
+    Rewards, TransitionLabels, MDP,
 };
+use crate::analysis::NetStructure;
+use crate::error::McspError;
 use crate::input_graph;
 use log::warn;
-use petgraph::{algo::dijkstra, graph::NodeIndex};
+use petgraph::graph::NodeIndex;
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, HashSet, VecDeque},
     fmt::{Debug, Display},
-    process::exit,
 };
 
 pub type Marking = Vec<usize>;
@@ -58,42 +60,10 @@ pub struct PetriNet {
     pub transitions: Vec<Transition>,
     pub initial_marking: Marking,
     pub ap_map: ApMap<Marking>,
+    pub rewards: Rewards,
 }
 
 impl PetriNet {
-    fn check_infinite_graph<'a>(
-        graph: &'a MDP<Marking>,
-        marking: &Marking,
-        marking_index: &NodeIndex,
-    ) -> Option<&'a Marking> {
-        // Get all smaller markings
-        let node_indices: Vec<_> = graph
-            .node_indices()
-            .filter(|&m| {
-                if let State(m) = &graph[m] {
-                    return input_graph::State::le(m, marking) && *m != *marking;
-                }
-                false
-            })
-            .collect();
-        if node_indices.is_empty() {
-            return None;
-        };
-
-        //Check whether these smaller markings are connected to this one
-        for node_index in node_indices {
-            let path_res = dijkstra(graph, node_index, Some(*marking_index), |_| 1);
-            if path_res.get(marking_index).is_some() {
-                println!(
-                    "There is a path from {:?} to {:?}! That is why the graph is infinite",
-                    &graph[node_index], marking
-                );
-                exit(0);
-            }
-        }
-        None
-    }
-
     fn get_active_transitions<'a>(
         marking: &Marking,
         transitions: &'a [Transition],
@@ -119,19 +89,19 @@ impl PetriNet {
         succ_marking
     }
 
-    pub fn to_mdp(&self, precision: i32) -> (MDP<Marking>, Marking) {
+    pub fn to_weighted_mdp(&self, weights: EdgeWeights) -> Result<LabelledMDP<Marking>, McspError> {
         let mut reach_graph: MDP<Marking> = MDP::new();
+        let mut labels = TransitionLabels::new();
         let states: &Vec<Place> = &self.places;
         let initial_marking: Marking = states.iter().map(|s| s.token).collect();
-        let mut upcoming_markings = VecDeque::<Marking>::new();
-        upcoming_markings.push_back(initial_marking.clone());
-        reach_graph.add_node(State(initial_marking.clone()));
-        while let Some(marking) = upcoming_markings.pop_front() {
-            let pre_index = reach_graph
-                .node_indices()
-                .find(|&n| reach_graph[n] == State(marking.clone()))
-                .unwrap();
-
+        let mut upcoming_markings = VecDeque::<(Marking, NodeIndex)>::new();
+        let initial_index = reach_graph.add_node(State(initial_marking.clone()));
+        upcoming_markings.push_back((initial_marking.clone(), initial_index));
+        // Index of all markings added so far and the marking each one was discovered from
+        let mut indices: HashMap<Marking, NodeIndex> = HashMap::new();
+        indices.insert(initial_marking.clone(), initial_index);
+        let mut predecessors: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+        while let Some((marking, pre_index)) = upcoming_markings.pop_front() {
             // Add pseudo action
             let pseudo_action: Node<_> = Action("".into());
             let action_index = reach_graph.add_node(pseudo_action);
@@ -143,36 +113,45 @@ impl PetriNet {
 
             // Add an action edge to the marking itself if there are not activated transitions
             if active_transitions.is_empty() {
-                reach_graph.add_edge(action_index, pre_index, 1.0);
+                reach_graph.add_edge(action_index, pre_index, weights.dead_weight());
             }
 
             for active_transition in active_transitions {
                 let succ_marking = PetriNet::succ_marking(&marking, active_transition);
-                let succ_index;
-                if let Some(index) = reach_graph
-                    .node_indices()
-                    .find(|&n| reach_graph[n] == State(succ_marking.clone()))
-                {
-                    succ_index = index;
-                } else {
-                    succ_index = reach_graph.add_node(State(succ_marking.clone()));
-                    upcoming_markings.push_back(succ_marking);
-                }
-                PetriNet::check_infinite_graph(&reach_graph, &marking, &pre_index);
-                let mut probability = active_transition.fire_rate / sum_fire_rates;
-                probability =
-                    (probability * 10.0_f64.powi(precision)).round() / 10.0_f64.powi(precision);
-                reach_graph.add_edge(action_index, succ_index, probability);
+                let succ_index = match indices.get(&succ_marking) {
+                    Some(index) => *index,
+                    None => {
+                        if let Some(smaller) = smaller_predecessor(
+                            &reach_graph,
+                            &predecessors,
+                            &succ_marking,
+                            pre_index,
+                        ) {
+                            return Err(McspError::Unbounded {
+                                smaller: smaller.clone(),
+                                larger: succ_marking,
+                            });
+                        }
+                        let index = reach_graph.add_node(State(succ_marking.clone()));
+                        indices.insert(succ_marking.clone(), index);
+                        predecessors.insert(index, pre_index);
+                        upcoming_markings.push_back((succ_marking, index));
+                        index
+                    }
+                };
+                let weight = weights.weight(active_transition.fire_rate, sum_fire_rates);
+                let edge = reach_graph.add_edge(action_index, succ_index, weight);
+                labels.insert(edge, active_transition.transition_id);
             }
         }
-        (reach_graph, initial_marking)
+        Ok((reach_graph, initial_marking, labels))
     }
 }
 
 impl InputGraph for PetriNet {
     type S = Marking;
-    fn validate_graph(&mut self, graph: &MDP<Marking>) {
-        let graph_markings: Vec<&Marking> = graph
+    fn validate_graph(&self, graph: &MDP<Marking>) -> ApMap<Marking> {
+        let graph_markings: HashSet<&Marking> = graph
             .node_weights()
             .filter_map(|n| match n {
                 State(s) => Some(s),
@@ -180,10 +159,11 @@ impl InputGraph for PetriNet {
             })
             .collect();
         // Check whether the assigned markings are reached (is a node in the reachability graph)
-        self.ap_map
+        let mut ap_map = self.ap_map.clone();
+        ap_map
             .iter_mut()
             .for_each(|(ap, v)| v.retain(|m| {
-                let retain: bool = graph_markings.contains(&m);
+                let retain: bool = graph_markings.contains(m);
                 if !retain {
                     warn!(
                         "{:?} was assigned to \"{}\" but is never reached! Removing from \"{}\" ...",
@@ -192,16 +172,17 @@ impl InputGraph for PetriNet {
                 }
                 retain
             }));
-        self.ap_map.retain(|k, v| {
+        ap_map.retain(|k, v| {
             if v.is_empty() {
                 warn!("\"{}\" is empty! Removing it from the list of all AP's", k)
             }
             !v.is_empty()
         });
+        ap_map
     }
 
-    fn to_mdp(&self, precision: i32) -> (MDP<Marking>, Marking) {
-        self.to_mdp(precision)
+    fn to_weighted_mdp(&self, weights: EdgeWeights) -> Result<LabelledMDP<Marking>, McspError> {
+        self.to_weighted_mdp(weights)
     }
 
     fn get_ap_map(&self) -> &ApMap<Marking> {
@@ -211,6 +192,18 @@ impl InputGraph for PetriNet {
     fn get_init_state(&self) -> &Marking {
         &self.initial_marking
     }
+
+    fn state_reward(&self, state: &Marking) -> f64 {
+        self.rewards.marking_reward(state)
+    }
+
+    fn transition_reward(&self, transition: usize) -> f64 {
+        self.rewards.transition_reward(transition)
+    }
+
+    fn net_structure(&self) -> NetStructure {
+        NetStructure::from(self)
+    }
 }
 
 impl GenericMDP for MDP<Marking> {}
@@ -0,0 +1,33 @@
+pub mod analysis;
+mod common;
+pub mod error;
+pub mod input_graph;
+pub mod logic;
+pub mod mcsp;
+pub mod parser;
+pub mod report;
+#[cfg(test)]
+mod testing;
+pub mod utils;
+
+pub use crate::analysis::behaviour::{analyze_behaviour, BehaviourResult};
+pub use crate::analysis::coverability::{analyze_boundedness, BoundednessResult};
+pub use crate::analysis::structural::{analyze_structure, StructuralResult};
+pub use crate::analysis::{AnalysisType, NetStructure};
+pub use crate::error::McspError;
+pub use crate::input_graph::dpnet::DPetriNet;
+pub use crate::input_graph::pnet::PetriNet;
+pub use crate::input_graph::{InputGraph, InputGraphType, ParseImpl, TransitionLabels, MDP};
+pub use crate::logic::ltl::PhiOp;
+pub use crate::logic::pctl::PctlFormula;
+pub use crate::logic::{parse_formula, Formula, LogicType};
+pub use crate::mcsp::{
+    check, check_boundedness, AutomatonStage, AutomatonType, CheckOptions, CheckResult,
+    Counterexample, Decision, GraphSize, Probability, Solver, Timings, Trace,
+};
+pub use crate::parser::dpn_parser::DPetriNetParser;
+pub use crate::parser::petri_net_parser::PetriNetParser;
+pub use crate::report::{
+    format_behaviour, format_boundedness, format_result, format_scheduler, format_structure,
+    OutputFormat,
+};
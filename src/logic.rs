@@ -2,29 +2,44 @@ use std::collections::BTreeMap;
 
 use petgraph::graph::NodeIndex;
 
+use crate::error::McspError;
 use crate::input_graph::Node;
+use crate::logic::csl::CslImpl;
 use crate::logic::ltl::LtlImpl;
 use crate::logic::pctl::PctlImpl;
-use crate::mcsp::PctlInfo;
-use crate::LogicType;
+use crate::mcsp::{CheckResult, PctlInfo};
 
 use self::ltl::PhiOp;
 use self::pctl::PctlFormula;
 
+pub mod counterexample;
+pub mod csl;
 pub mod ltl;
+mod mec;
 pub mod pctl;
+pub mod sparse;
 
 const FORMULA_ID: &str = "PHI";
 
+#[derive(clap::ValueEnum, Clone, Default)]
+pub enum LogicType {
+    #[default]
+    Pctl,
+    LTL,
+    /// Continuous stochastic logic, evaluated on the continuous-time semantics of the net
+    Csl,
+}
+
 pub trait LogicImpl {
-    fn parse(&self, content: &str) -> Formula;
+    fn parse(&self, content: &str) -> Result<Formula, McspError>;
 
-    fn find_formula(&self, content: &str) -> Option<String> {
+    /// Returns the formula part of the content together with its byte offset in the content
+    fn find_formula(&self, content: &str) -> Option<(usize, String)> {
         if let Some(start_index) = content.find(FORMULA_ID) {
             let end_index = start_index + FORMULA_ID.len();
             let logic_sub_string: String = content.get(start_index..).unwrap().into();
             return match content[end_index..].find(FORMULA_ID) {
-                None => Some(logic_sub_string),
+                None => Some((start_index, logic_sub_string)),
                 Some(_) => None,
             };
         }
@@ -32,26 +47,38 @@ pub trait LogicImpl {
     }
 }
 
-pub fn parse_formula(logic_type: LogicType, content: &str) -> Formula {
+pub fn parse_formula(logic_type: LogicType, content: &str) -> Result<Formula, McspError> {
     match logic_type {
         LogicType::Pctl => PctlImpl.parse(content),
         LogicType::LTL => LtlImpl.parse(content),
+        LogicType::Csl => CslImpl.parse(content),
     }
 }
 
 pub enum Formula {
     Pctl(PctlFormula),
     Ltl(PhiOp),
+    Csl(PctlFormula),
 }
 
 impl Formula {
-    pub fn evaluate<K>(&self, pctl_info: &PctlInfo, rename_map: BTreeMap<NodeIndex, Node<K>>)
+    /// Whether the formula refers to the continuous-time semantics of the net
+    pub fn is_continuous(&self) -> bool {
+        matches!(self, Formula::Csl(_))
+    }
+
+    pub fn evaluate<K>(
+        &self,
+        pctl_info: &PctlInfo,
+        rename_map: BTreeMap<NodeIndex, Node<K>>,
+    ) -> Result<CheckResult<K>, McspError>
     where
         K: std::fmt::Debug + PartialEq + Clone + Ord,
     {
         match self {
             Formula::Pctl(formula) => formula.evaluate(pctl_info, rename_map),
             Formula::Ltl(formula) => formula.evaluate(pctl_info, rename_map),
+            Formula::Csl(formula) => formula.evaluate(pctl_info, rename_map),
         }
     }
 }
@@ -0,0 +1,416 @@
+use crate::input_graph::MDP;
+use crate::logic::pctl::Scheduler;
+use crate::mcsp::{PctlInfo, Trace};
+use log::warn;
+use petgraph::algo::kosaraju_scc;
+use petgraph::graph::DiGraph;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Outgoing;
+use std::cmp::Ordering;
+use std::collections::{hash_map::Entry, BinaryHeap, HashMap, HashSet, VecDeque};
+
+/// Upper limit of the prefixes the search for the most probable paths keeps in memory
+const MAX_PREFIXES: usize = 1_000_000;
+
+/// Run through the chain a scheduler induces on an MDP
+#[derive(Debug, Clone)]
+pub struct Run {
+    /// Visited states, one more than edges
+    pub states: Vec<NodeIndex>,
+    /// Edges from the chosen action to the next state
+    pub edges: Vec<EdgeIndex>,
+    pub probability: f64,
+}
+
+impl Run {
+    /// Converts the run to markings and transition names. `state` returns the marking and the
+    /// state of the automaton every state stands for
+    pub fn to_trace<K>(
+        &self,
+        state: impl Fn(NodeIndex) -> (K, Option<String>),
+        transition: impl Fn(EdgeIndex) -> String,
+    ) -> Trace<K> {
+        let (markings, automaton_states): (Vec<K>, Vec<Option<String>>) =
+            self.states.iter().map(|s| state(*s)).unzip();
+        Trace {
+            markings,
+            automaton_states: automaton_states.into_iter().flatten().collect(),
+            transitions: self.edges.iter().map(|e| transition(*e)).collect(),
+            probability: self.probability,
+        }
+    }
+}
+
+/// Name of the transition fired along the edge of the graph of `pctl_info`
+pub fn transition_name(pctl_info: &PctlInfo, edge: EdgeIndex) -> String {
+    match pctl_info.labels.get(&edge) {
+        Some(transition) => pctl_info.transition_names[*transition].clone(),
+        None => "(stay)".into(),
+    }
+}
+
+/// Edges of the action the scheduler chooses in the state with a positive probability, together
+/// with their targets and probabilities
+pub fn successors<'a>(
+    graph: &'a MDP<NodeIndex>,
+    scheduler: &Scheduler,
+    state: NodeIndex,
+) -> impl Iterator<Item = (EdgeIndex, NodeIndex, f64)> + 'a {
+    let mut edges: Vec<(EdgeIndex, NodeIndex, f64)> = scheduler
+        .get(&state)
+        .into_iter()
+        .flat_map(|action| graph.edges_directed(*action, Outgoing))
+        .filter(|e| *e.weight() > 0.0)
+        .map(|e| (e.id(), e.target(), *e.weight()))
+        .collect();
+    edges.sort_by_key(|(e, _, _)| *e);
+    edges.into_iter()
+}
+
+/// Prefix of a path in the search for the most probable paths, more probable ones come first
+struct Prefix {
+    probability: f64,
+    index: usize,
+}
+
+impl PartialEq for Prefix {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Prefix {}
+
+impl PartialOrd for Prefix {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Prefix {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Equally probable prefixes are expanded in the order they were found
+        self.probability
+            .total_cmp(&other.probability)
+            .then(other.index.cmp(&self.index))
+    }
+}
+
+/// Paths from `initial` to `targets` which only pass through `stay` in the chain induced by the
+/// scheduler, in descending order of their probability. The probabilities of paths only shrink
+/// when they grow, so a best-first search over their prefixes finds them in this order. Stops
+/// as soon as the combined probability of the paths is `enough` or `max_paths` were found
+pub fn most_probable_paths(
+    graph: &MDP<NodeIndex>,
+    scheduler: &Scheduler,
+    initial: NodeIndex,
+    stay: &HashSet<NodeIndex>,
+    targets: &HashSet<NodeIndex>,
+    enough: impl Fn(f64) -> bool,
+    max_paths: usize,
+) -> Vec<Run> {
+    // Prefixes which can not reach the targets anymore are dropped, so every cycle of the
+    // remaining ones has a probability below 1
+    let stay = reaching(graph, scheduler, stay, targets);
+    // Every prefix is its last edge and state together with the prefix it extends
+    let mut prefixes: Vec<(Option<usize>, Option<EdgeIndex>, NodeIndex)> =
+        vec![(None, None, initial)];
+    let mut heap = BinaryHeap::from([Prefix {
+        probability: 1.0,
+        index: 0,
+    }]);
+    let mut paths = Vec::new();
+    let mut mass = 0.0;
+    while let Some(Prefix { probability, index }) = heap.pop() {
+        let state = prefixes[index].2;
+        if targets.contains(&state) {
+            paths.push(path(&prefixes, index, probability));
+            mass += probability;
+            if enough(mass) || paths.len() >= max_paths {
+                break;
+            }
+        } else if stay.contains(&state) && prefixes.len() < MAX_PREFIXES {
+            for (edge, target, p) in successors(graph, scheduler, state) {
+                heap.push(Prefix {
+                    probability: probability * p,
+                    index: prefixes.len(),
+                });
+                prefixes.push((Some(index), Some(edge), target));
+            }
+        }
+    }
+    if !enough(mass) {
+        warn!(
+            "The {} most probable paths found only have a combined probability of {}",
+            paths.len(),
+            mass
+        );
+    }
+    paths
+}
+
+fn path(
+    prefixes: &[(Option<usize>, Option<EdgeIndex>, NodeIndex)],
+    mut index: usize,
+    probability: f64,
+) -> Run {
+    let mut states = vec![prefixes[index].2];
+    let mut edges = Vec::new();
+    while let (Some(parent), Some(edge), _) = prefixes[index] {
+        edges.push(edge);
+        states.push(prefixes[parent].2);
+        index = parent;
+    }
+    states.reverse();
+    edges.reverse();
+    Run {
+        states,
+        edges,
+        probability,
+    }
+}
+
+/// States of `stay` which reach `targets` without leaving `stay` in the chain induced by the
+/// scheduler, together with the targets
+pub fn reaching(
+    graph: &MDP<NodeIndex>,
+    scheduler: &Scheduler,
+    stay: &HashSet<NodeIndex>,
+    targets: &HashSet<NodeIndex>,
+) -> HashSet<NodeIndex> {
+    let mut predecessors: HashMap<NodeIndex, Vec<NodeIndex>> = HashMap::new();
+    for state in stay {
+        for (_, target, _) in successors(graph, scheduler, *state) {
+            predecessors.entry(target).or_default().push(*state);
+        }
+    }
+    let mut reached = targets.clone();
+    let mut stack: Vec<NodeIndex> = targets.iter().copied().collect();
+    while let Some(state) = stack.pop() {
+        for predecessor in predecessors.get(&state).into_iter().flatten() {
+            if reached.insert(*predecessor) {
+                stack.push(*predecessor);
+            }
+        }
+    }
+    reached
+}
+
+/// Run made up of a prefix from `initial` into a bottom strongly connected component of the
+/// chain induced by the scheduler and a cycle through all states of the component. The nearest
+/// such component satisfying `accept` is chosen. None if every run ends in a rejected component
+pub fn lasso(
+    graph: &MDP<NodeIndex>,
+    scheduler: &Scheduler,
+    initial: NodeIndex,
+    accept: impl Fn(&HashSet<NodeIndex>) -> bool,
+) -> Option<(Run, Run)> {
+    // Breadth-first search over the states reachable under the scheduler
+    let mut parents: HashMap<NodeIndex, Option<(NodeIndex, EdgeIndex, f64)>> =
+        HashMap::from([(initial, None)]);
+    let mut order = vec![initial];
+    let mut queue = VecDeque::from([initial]);
+    while let Some(state) = queue.pop_front() {
+        for (edge, target, p) in successors(graph, scheduler, state) {
+            if let Entry::Vacant(entry) = parents.entry(target) {
+                entry.insert(Some((state, edge, p)));
+                order.push(target);
+                queue.push_back(target);
+            }
+        }
+    }
+
+    let mut chain: DiGraph<NodeIndex, ()> = DiGraph::new();
+    let nodes: HashMap<NodeIndex, _> = order.iter().map(|s| (*s, chain.add_node(*s))).collect();
+    for state in &order {
+        for (_, target, _) in successors(graph, scheduler, *state) {
+            if let Some(target) = nodes.get(&target) {
+                chain.add_edge(nodes[state], *target, ());
+            }
+        }
+    }
+    let distance: HashMap<NodeIndex, usize> =
+        order.iter().enumerate().map(|(i, s)| (*s, i)).collect();
+    let component: HashSet<NodeIndex> = kosaraju_scc(&chain)
+        .into_iter()
+        .map(|scc| scc.into_iter().map(|n| chain[n]).collect::<HashSet<_>>())
+        .filter(|scc| {
+            scc.iter().all(|s| {
+                let mut targets = successors(graph, scheduler, *s)
+                    .map(|(_, t, _)| t)
+                    .peekable();
+                targets.peek().is_some() && targets.all(|t| scc.contains(&t))
+            }) && accept(scc)
+        })
+        .min_by_key(|scc| scc.iter().map(|s| distance[s]).min())?;
+    let entry = *component.iter().min_by_key(|s| distance[s]).unwrap();
+
+    let mut prefix = Run {
+        states: vec![entry],
+        edges: Vec::new(),
+        probability: 1.0,
+    };
+    let mut state = entry;
+    while let Some((parent, edge, p)) = parents[&state] {
+        prefix.states.push(parent);
+        prefix.edges.push(edge);
+        prefix.probability *= p;
+        state = parent;
+    }
+    prefix.states.reverse();
+    prefix.edges.reverse();
+
+    // Visits the nearest state not visited yet until all were, then returns to the entry
+    let mut cycle = Run {
+        states: vec![entry],
+        edges: Vec::new(),
+        probability: 1.0,
+    };
+    let mut unvisited: HashSet<NodeIndex> = component.clone();
+    unvisited.remove(&entry);
+    loop {
+        let done = unvisited.is_empty();
+        let run = shortest_run(
+            graph,
+            scheduler,
+            *cycle.states.last().unwrap(),
+            |s| match done {
+                true => s == entry,
+                false => unvisited.contains(&s),
+            },
+        );
+        for s in &run.states[1..] {
+            unvisited.remove(s);
+        }
+        cycle.states.extend(&run.states[1..]);
+        cycle.edges.extend(run.edges);
+        cycle.probability *= run.probability;
+        if done {
+            return Some((prefix, cycle));
+        }
+    }
+}
+
+/// Shortest run with at least one step from `start` to a state satisfying `target`. The target
+/// has to be reachable
+fn shortest_run(
+    graph: &MDP<NodeIndex>,
+    scheduler: &Scheduler,
+    start: NodeIndex,
+    target: impl Fn(NodeIndex) -> bool,
+) -> Run {
+    let mut parents: HashMap<NodeIndex, (NodeIndex, EdgeIndex, f64)> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    let end = 'search: loop {
+        let state = queue.pop_front().expect("Target is not reachable");
+        for (edge, next, p) in successors(graph, scheduler, state) {
+            if parents.contains_key(&next) {
+                continue;
+            }
+            parents.insert(next, (state, edge, p));
+            if target(next) {
+                break 'search next;
+            }
+            queue.push_back(next);
+        }
+    };
+    let mut run = Run {
+        states: vec![end],
+        edges: Vec::new(),
+        probability: 1.0,
+    };
+    let mut state = end;
+    loop {
+        let (parent, edge, p) = parents[&state];
+        run.states.push(parent);
+        run.edges.push(edge);
+        run.probability *= p;
+        if parent == start {
+            break;
+        }
+        state = parent;
+    }
+    run.states.reverse();
+    run.edges.reverse();
+    run
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::LogicType;
+    use crate::mcsp::{CheckOptions, CheckResult, Counterexample, Trace};
+    use crate::testing::{check_net, CHOICE_NET};
+
+    fn check(logic_type: LogicType, phi: &str) -> CheckResult<Vec<usize>> {
+        let options = CheckOptions {
+            precision_digits: 6,
+            counterexample: Some(10),
+            ..Default::default()
+        };
+        check_net(CHOICE_NET, logic_type, phi, &options)
+    }
+
+    fn paths(result: CheckResult<Vec<usize>>) -> Vec<Trace<Vec<usize>>> {
+        match result.counterexample {
+            Some(Counterexample::Paths(paths)) => paths,
+            other => panic!("Expected paths, got {:?}", other),
+        }
+    }
+
+    fn mass(paths: &[Trace<Vec<usize>>]) -> f64 {
+        paths.iter().map(|p| p.probability).sum()
+    }
+
+    #[test]
+    fn upper_bounds_are_violated_by_the_most_probable_paths_to_the_goal() {
+        let paths = paths(check(LogicType::Pctl, "P(F(c), <= 0.4)"));
+        assert_eq!(paths[0].transitions, vec!["t2", "t6"]);
+        assert!((paths[0].probability - 1.0 / 6.0).abs() < 1e-5);
+        assert!(paths
+            .windows(2)
+            .all(|w| w[0].probability >= w[1].probability));
+        assert!(paths
+            .iter()
+            .all(|p| p.markings.last() == Some(&vec![0, 0, 1, 0])));
+        assert!(paths
+            .iter()
+            .all(|p| p.markings.len() == p.transitions.len() + 1));
+        // The paths only just exceed the bound
+        assert!(mass(&paths) > 0.4);
+        assert!(mass(&paths[..paths.len() - 1]) <= 0.4);
+    }
+
+    #[test]
+    fn lower_bounds_are_violated_by_paths_missing_the_goal() {
+        let paths = paths(check(LogicType::Pctl, "P(F(c), >= 0.5)"));
+        assert!(paths
+            .iter()
+            .all(|p| p.markings.last() == Some(&vec![0, 0, 0, 1])));
+        assert!(mass(&paths) > 0.5);
+        assert!(mass(&paths[..paths.len() - 1]) <= 0.5);
+    }
+
+    #[test]
+    fn ltl_formulas_are_violated_by_a_lasso() {
+        let result = check(LogicType::LTL, "G(!d)");
+        let Some(Counterexample::Lasso { prefix, cycle }) = result.counterexample else {
+            panic!("Expected a lasso");
+        };
+        assert_eq!(prefix.markings[0], vec![1, 0, 0, 0]);
+        assert_eq!(prefix.markings.last(), cycle.markings.first());
+        assert_eq!(
+            prefix.automaton_states.last(),
+            cycle.automaton_states.first()
+        );
+        assert_eq!(cycle.markings.first(), cycle.markings.last());
+        assert!(prefix.markings.contains(&vec![0, 0, 0, 1]));
+    }
+
+    #[test]
+    fn satisfied_formulas_have_no_counterexample() {
+        let result = check(LogicType::Pctl, "P(F(c), >= 0.3)");
+        assert!(result.satisfied);
+        assert_eq!(result.counterexample, None);
+    }
+}
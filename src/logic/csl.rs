@@ -0,0 +1,379 @@
+use super::{Formula, LogicImpl};
+use crate::error::McspError;
+use crate::logic::pctl::{
+    AndPhi, Globally, LongRun, NotPhi, Operator, PathPhi, PctlFormula, Prob, Quantifier, Query,
+    ReachReward, StatePhi, True, Until, AP,
+};
+use crate::logic::sparse::SparseMdp;
+use crate::mcsp::{Interval, PctlInfo};
+use crate::utils::common::{Comp, ParseOrError};
+use pest::iterators::Pair;
+use pest::Parser;
+use pest_derive::Parser;
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::{Incoming, Outgoing};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Parser)]
+#[grammar = "logic/csl.pest"]
+struct CslPestParser;
+
+/// Continuous stochastic logic. Formulas are evaluated on the uniformised continuous-time model,
+/// so all operators without time bounds are shared with PCTL
+pub struct CslImpl;
+
+impl CslImpl {
+    fn parse_comp(pair: &Pair<Rule>) -> Comp {
+        match pair.as_str() {
+            "<" => Comp::Less,
+            "<=" => Comp::Leq,
+            ">" => Comp::Greater,
+            ">=" => Comp::Geq,
+            _ => unreachable!(),
+        }
+    }
+
+    fn parse_state(pair: &Pair<Rule>) -> Result<Box<dyn StatePhi>, McspError> {
+        let inner_rules = pair.clone().into_inner().collect::<Vec<Pair<Rule>>>();
+        let state_phi: Box<dyn StatePhi> = match pair.as_rule() {
+            Rule::Phi_and => Box::new(AndPhi {
+                left_phi: Self::parse_state(inner_rules.first().unwrap())?,
+                right_phi: Self::parse_state(inner_rules.get(1).unwrap())?,
+            }),
+            Rule::Phi_or => {
+                let left_rule = Self::parse_state(inner_rules.first().unwrap())?;
+                let right_rule = Self::parse_state(inner_rules.get(1).unwrap())?;
+                Box::new(NotPhi {
+                    phi: Box::new(AndPhi {
+                        left_phi: Box::new(NotPhi { phi: left_rule }),
+                        right_phi: Box::new(NotPhi { phi: right_rule }),
+                    }),
+                })
+            }
+            Rule::Phi_not => Box::new(NotPhi {
+                phi: Self::parse_state(inner_rules.first().unwrap())?,
+            }),
+            Rule::ap => Box::new(AP {
+                value: pair.as_str().into(),
+            }),
+            Rule::r#true => Box::new(True {}),
+            Rule::r#false => Box::new(NotPhi {
+                phi: Box::new(True {}),
+            }),
+            Rule::prob | Rule::prob_alw => {
+                let phi: Box<dyn PathPhi> = match pair.as_rule() {
+                    Rule::prob => Self::parse_path(inner_rules.first().unwrap())?,
+                    _ => Box::new(Globally::new(Self::parse_state(
+                        inner_rules.first().unwrap(),
+                    )?)),
+                };
+                Box::new(Prob {
+                    phi,
+                    comp: Self::parse_comp(inner_rules.get(1).unwrap()),
+                    probability: inner_rules
+                        .get(2)
+                        .unwrap()
+                        .as_str()
+                        .parse_or_error("float")?,
+                })
+            }
+            Rule::long_run => Box::new(LongRun {
+                phi: Self::parse_state(inner_rules.first().unwrap())?,
+                comp: Self::parse_comp(inner_rules.get(1).unwrap()),
+                probability: inner_rules
+                    .get(2)
+                    .unwrap()
+                    .as_str()
+                    .parse_or_error("float")?,
+            }),
+            _ => panic!("Rule is invalid or should have been processed by parent!"),
+        };
+        Ok(state_phi)
+    }
+
+    fn parse_path(pair: &Pair<Rule>) -> Result<Box<dyn PathPhi>, McspError> {
+        let inner_rules = pair.clone().into_inner().collect::<Vec<Pair<Rule>>>();
+        let path_phi: Box<dyn PathPhi> = match pair.as_rule() {
+            Rule::phi_until => Box::new(Until {
+                prev: Self::parse_state(inner_rules.first().unwrap())?,
+                until: Self::parse_state(inner_rules.get(1).unwrap())?,
+            }),
+            Rule::phi_ev => Box::new(Until {
+                prev: Box::new(True {}),
+                until: Self::parse_state(inner_rules.first().unwrap())?,
+            }),
+            Rule::phi_timed_until => Box::new(TimeBoundedUntil {
+                prev: Self::parse_state(inner_rules.first().unwrap())?,
+                time: inner_rules
+                    .get(1)
+                    .unwrap()
+                    .as_str()
+                    .parse_or_error("number")?,
+                until: Self::parse_state(inner_rules.get(2).unwrap())?,
+            }),
+            Rule::phi_timed_ev => Box::new(TimeBoundedUntil {
+                prev: Box::new(True {}),
+                time: inner_rules
+                    .first()
+                    .unwrap()
+                    .as_str()
+                    .parse_or_error("number")?,
+                until: Self::parse_state(inner_rules.get(1).unwrap())?,
+            }),
+            Rule::phi_glob => Box::new(Globally::new(Self::parse_state(
+                inner_rules.first().unwrap(),
+            )?)),
+            _ => unreachable!(),
+        };
+        Ok(path_phi)
+    }
+}
+
+impl LogicImpl for CslImpl {
+    fn parse(&self, content: &str) -> Result<Formula, McspError> {
+        let (offset, phi_content) = self
+            .find_formula(content)
+            .ok_or(McspError::MissingFormula)?;
+        let pairs = CslPestParser::parse(Rule::Main, &phi_content)
+            .map_err(|error| McspError::from(error).with_offset(content, offset))?;
+        let pairs_vec = pairs.collect::<Vec<_>>();
+        let pair = pairs_vec.first().unwrap();
+        if let Rule::query | Rule::long_run_query | Rule::time_query = pair.as_rule() {
+            let inner_rules = pair.clone().into_inner().collect::<Vec<Pair<Rule>>>();
+            let quantifier = match &inner_rules.first().unwrap().as_str()[1..] {
+                "min" => Quantifier::Min,
+                "max" => Quantifier::Max,
+                _ => Quantifier::Value,
+            };
+            let inner_rule = inner_rules.get(1).unwrap();
+            // The reward of every state of the uniformised model is its expected sojourn time
+            let operator = match pair.as_rule() {
+                Rule::query => Operator::Prob(Self::parse_path(inner_rule)?),
+                Rule::long_run_query => Operator::LongRun(Self::parse_state(inner_rule)?),
+                _ => Operator::Reward(Box::new(ReachReward {
+                    goal: Self::parse_state(inner_rule)?,
+                })),
+            };
+            return Ok(Formula::Csl(PctlFormula::Query(Query {
+                operator,
+                quantifier,
+            })));
+        }
+        Ok(Formula::Csl(PctlFormula::State(Self::parse_state(pair)?)))
+    }
+}
+
+/// Turns the fire rates of the continuous-time graph into the probabilities of the uniformised
+/// model, in which every step takes 1/rate time units on average. Every state gets this time as
+/// reward, so reward operators compute expected times
+pub fn uniformise(pctl_info: &mut PctlInfo) {
+    let graph = &mut pctl_info.reach_graph;
+    let actions: Vec<NodeIndex> = graph
+        .node_indices()
+        .filter(|n| !graph[*n].is_state())
+        .collect();
+    let exit_rate = |action: NodeIndex| -> f64 {
+        graph
+            .edges_directed(action, Outgoing)
+            .fold(0.0, |sum, e| sum + e.weight())
+    };
+    let exit_rates: Vec<f64> = actions.iter().map(|a| exit_rate(*a)).collect();
+    let rate = match exit_rates.iter().copied().fold(0.0, f64::max) {
+        r if r > 0.0 => r,
+        _ => 1.0,
+    };
+    for (action, exit_rate) in actions.iter().zip(exit_rates) {
+        let mut edges = graph.neighbors_directed(*action, Outgoing).detach();
+        while let Some(edge) = edges.next_edge(graph) {
+            graph[edge] /= rate;
+        }
+        // The time the faster actions would have left the state is spent in the state itself
+        if exit_rate < rate {
+            let state = graph.neighbors_directed(*action, Incoming).next().unwrap();
+            graph.add_edge(*action, state, 1.0 - exit_rate / rate);
+        }
+    }
+    pctl_info.rewards.states = pctl_info
+        .rewards
+        .states
+        .keys()
+        .map(|state| (*state, 1.0 / rate))
+        .collect();
+    pctl_info.rewards.transitions.clear();
+    pctl_info.uniformisation_rate = Some(rate);
+    pctl_info.sparse = SparseMdp::from_graph(&pctl_info.reach_graph);
+}
+
+/// Until which has to reach `until` within `time` time units
+pub struct TimeBoundedUntil {
+    pub prev: Box<dyn StatePhi>,
+    pub until: Box<dyn StatePhi>,
+    pub time: f64,
+}
+
+impl PathPhi for TimeBoundedUntil {
+    fn fmt(&self) -> String {
+        format!("({}) U[0,{}] ({})", self.prev, self.time, self.until)
+    }
+
+    fn evaluate_inner(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+        prob_bound: f64,
+    ) -> Result<HashSet<NodeIndex>, McspError> {
+        Ok(self
+            .probabilities(pctl_info, comp)?
+            .into_iter()
+            .filter(|(_, v)| comp.evaluate(*v, prob_bound))
+            .map(|(k, _)| k)
+            .collect())
+    }
+
+    /// Uniformisation: the probability is the sum over all k of the probability of k steps of
+    /// the uniformised model within the time bound times the probability of reaching `until`
+    /// within k steps. For nondeterministic models the schedulers only see the number of steps
+    /// taken, not the time which has passed
+    fn probabilities(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, f64>, McspError> {
+        let graph = &pctl_info.reach_graph;
+        let rate = pctl_info
+            .uniformisation_rate
+            .expect("Time bounds need a uniformised continuous-time model");
+        let left_phi = self.prev.evaluate_inner(pctl_info)?;
+        let right_phi = self.until.evaluate_inner(pctl_info)?;
+        let s_q: Vec<NodeIndex> = left_phi.difference(&right_phi).copied().collect();
+        let poisson = poisson_probabilities(rate * self.time, pctl_info.max_error);
+
+        let mut prob_map: HashMap<NodeIndex, f64> = graph
+            .node_indices()
+            .filter(|n| graph[*n].is_state())
+            .map(|n| (n, 0.0))
+            .collect();
+        for psi in poisson.iter().rev() {
+            let next: Vec<(NodeIndex, f64)> = s_q
+                .iter()
+                .map(|state| {
+                    let prob = graph
+                        .neighbors_directed(*state, Outgoing)
+                        .map(|action| {
+                            graph
+                                .edges_directed(action, Outgoing)
+                                .fold(0.0, |sum, e| sum + e.weight() * prob_map[&e.target()])
+                        })
+                        .reduce(|p1, p2| match comp.is_upper_bound() {
+                            true => p1.max(p2),
+                            false => p1.min(p2),
+                        })
+                        .unwrap_or(0.0);
+                    (*state, prob)
+                })
+                .collect();
+            prob_map.extend(next);
+            // Reached states stay reached for all remaining steps
+            for state in &right_phi {
+                *prob_map.get_mut(state).unwrap() += psi;
+            }
+        }
+        Ok(prob_map)
+    }
+
+    /// The truncated Poisson probabilities miss less than `max_error` of the probability mass,
+    /// so the real probabilities are at most this much larger
+    fn probability_bounds(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, Interval>, McspError> {
+        Ok(self
+            .probabilities(pctl_info, comp)?
+            .into_iter()
+            .map(|(n, p)| {
+                let interval = Interval {
+                    lo: p,
+                    hi: (p + pctl_info.max_error).min(1.0),
+                };
+                (n, interval)
+            })
+            .collect())
+    }
+}
+
+/// Probabilities of 0, 1, 2, ... events of a Poisson process with the given mean, truncated
+/// once the remaining probability mass is below `error`
+fn poisson_probabilities(mean: f64, error: f64) -> Vec<f64> {
+    let mut probabilities = Vec::new();
+    // Computed in log space since e^-mean underflows for large means
+    let mut log_probability = -mean;
+    let mut total = 0.0;
+    let mut k = 0;
+    while total < 1.0 - error || (k as f64) < mean {
+        let probability = log_probability.exp();
+        probabilities.push(probability);
+        total += probability;
+        k += 1;
+        log_probability += mean.ln() - (k as f64).ln();
+    }
+    probabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::LogicType;
+    use crate::mcsp::CheckOptions;
+    use crate::testing::check_petri_net;
+
+    /// A token moves from A to B with rate 1, back with rate 3 and on to the absorbing C with
+    /// rate 2
+    const NET: &str = "
+P = {A, B, C}
+G = {
+        {A} -> t1 -> {B},
+        {B} -> t2 -> {A},
+        {B} -> t3 -> {C}
+}
+M = (1, 0, 0)
+L = (1.0, 3.0, 2.0)
+AP = {
+    (a, {(1,0,0)}),
+    (b, {(0,1,0)}),
+    (c, {(0,0,1)})
+}
+";
+
+    fn value(net: &str, phi: &str) -> f64 {
+        let options = CheckOptions {
+            max_error: 1e-8,
+            ..Default::default()
+        };
+        check_petri_net(net, LogicType::Csl, phi, &options)
+            .value
+            .unwrap()
+    }
+
+    #[test]
+    fn time_bounded_until_follows_the_rates() {
+        // The token leaves A after an exponentially distributed time with rate 1
+        let expected = 1.0 - (-1.5_f64).exp();
+        assert!((value(NET, "P=? [F<=1.5 b]") - expected).abs() < 1e-6);
+        assert!((value(NET, "P=? [a U[0,1.5] b]") - expected).abs() < 1e-6);
+        assert!(value(NET, "P=? [F<=1.5 c]") < value(NET, "P=? [F<=3 c]"));
+    }
+
+    #[test]
+    fn expected_times_and_long_run_averages_weigh_the_sojourn_times() {
+        // h(A) = 1 + h(B) and h(B) = 1/5 + 3/5 h(A)
+        assert!((value(NET, "T=? [F c]") - 3.0).abs() < 1e-6);
+        // Without t3 the token stays in A three times as long as in B, although it jumps
+        // between them equally often
+        let cycle = NET
+            .replace(",\n        {B} -> t3 -> {C}", "")
+            .replace("(1.0, 3.0, 2.0)", "(1.0, 3.0)")
+            .replace(",\n    (c, {(0,0,1)})", "");
+        assert!((value(&cycle, "S=? [a]") - 0.75).abs() < 1e-6);
+    }
+}
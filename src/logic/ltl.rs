@@ -2,24 +2,39 @@ use self::ba::to_ba;
 use self::powerba::to_powerba;
 use super::{Formula, LogicImpl, PctlInfo};
 use crate::common::rename_map;
+use crate::error::McspError;
 use crate::input_graph::Node;
-use crate::logic::ltl::mdpa::cross_mdp;
+use crate::input_graph::MDP;
+use crate::logic::counterexample::{lasso, transition_name};
+use crate::logic::ltl::dpa::to_dpa;
+use crate::logic::ltl::ldba::to_ldba;
+use crate::logic::ltl::mdpa::{cross_mdp, AcceptingComponent, Automaton};
 use crate::logic::ltl::safra::determinize;
-use crate::logic::pctl::{True as Pctl_True, Until as Pctl_Until, AP as Pctl_AP};
+use crate::logic::pctl::{
+    decisions, reachability_scheduler, staying_scheduler, Scheduler, True as Pctl_True,
+    Until as Pctl_Until, AP as Pctl_AP,
+};
+use crate::logic::sparse::SparseMdp;
+use crate::mcsp::{
+    AutomatonStage, AutomatonType, CheckResult, Counterexample, GraphSize, Interval, Probability,
+    RewardMaps, Timings,
+};
 use crate::utils::common::Comp;
-use log::{error, info};
+use log::{info, warn};
 use pest::{iterators::Pair, Parser};
 use pest_derive::Parser;
-use petgraph::graph::NodeIndex;
-use std::collections::{BTreeMap, BTreeSet, HashMap};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::{Incoming, Outgoing};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::Display;
 use std::hash::Hash;
-use std::process::exit;
 
 mod ba;
 mod common;
-mod debug;
+mod dpa;
 mod gba;
+mod hoa;
+mod ldba;
 mod mdpa;
 mod powerba;
 mod safra;
@@ -88,24 +103,15 @@ impl LtlImpl {
 }
 
 impl LogicImpl for LtlImpl {
-    fn parse(&self, content: &str) -> Formula {
-        let phi_content = match self.find_formula(content) {
-            None => panic!("Formula must contain 'PHI' exactly once!"),
-            Some(c) => c,
-        };
-        let parse_result = LtlPestParser::parse(Rule::Main, &phi_content);
-        match parse_result {
-            Ok(pairs) => {
-                let pairs_vec = pairs.collect::<Vec<_>>();
-                let pair = pairs_vec.first().unwrap();
-                Formula::Ltl(Self::parse_phi(pair))
-            }
-            Err(error) => {
-                error!("PCTL Parsing error!");
-                println!("{}", error);
-                exit(0);
-            }
-        }
+    fn parse(&self, content: &str) -> Result<Formula, McspError> {
+        let (offset, phi_content) = self
+            .find_formula(content)
+            .ok_or(McspError::MissingFormula)?;
+        let pairs = LtlPestParser::parse(Rule::Main, &phi_content)
+            .map_err(|error| McspError::from(error).with_offset(content, offset))?;
+        let pairs_vec = pairs.collect::<Vec<_>>();
+        let pair = pairs_vec.first().unwrap();
+        Ok(Formula::Ltl(Self::parse_phi(pair)))
     }
 }
 
@@ -123,13 +129,211 @@ pub enum PhiOp {
 }
 
 impl PhiOp {
-    pub fn evaluate<K>(&self, pctl_info: &PctlInfo, normalization_map: BTreeMap<NodeIndex, Node<K>>)
+    pub fn evaluate<K>(
+        &self,
+        pctl_info: &PctlInfo,
+        normalization_map: BTreeMap<NodeIndex, Node<K>>,
+    ) -> Result<CheckResult<K>, McspError>
     where
-        K: std::fmt::Debug,
+        K: std::fmt::Debug + Clone + Ord,
     {
-        let dra = self.to_dra();
-        let dra_initial = dra.initial.clone();
-        let (cross_mdp, aec) = cross_mdp(dra, pctl_info);
+        let translation = self.translate(pctl_info);
+        // Every automaton only yields maximal probabilities. The minimal one is the complement of
+        // the maximal probability of the negated formula
+        let product = match pctl_info.automaton {
+            AutomatonType::Dra => Product::of(translation.dra.as_ref().unwrap(), pctl_info)?,
+            AutomatonType::Dpa => Product::of(translation.dpa.as_ref().unwrap(), pctl_info)?,
+            AutomatonType::Ldba => Product::of(translation.ldba.as_ref().unwrap(), pctl_info)?,
+        };
+        let negated = self.negate().product(pctl_info)?;
+        // Both are only within max_error of the real probabilities, so for equal probabilities the
+        // estimate of the minimum may exceed the one of the maximum
+        let min = |node: NodeIndex| (1.0 - negated.max(node)).min(product.max(node));
+        let initial_min = min(pctl_info.initial_marking);
+        let initial_max = product.max(pctl_info.initial_marking);
+        // The formula holds almost surely under every scheduler iff no scheduler reaches an
+        // accepting component of the negated formula, which only depends on the graph
+        let satisfies = |node: NodeIndex| !negated.reaches_accepting(node);
+        let satisfied = satisfies(pctl_info.initial_marking);
+        // Every scheduler's probability lies between the lower bound of the minimum and the upper
+        // bound of the maximum
+        let value_bounds = Interval {
+            lo: 1.0 - negated.bounds(pctl_info.initial_marking).hi,
+            hi: product.bounds(pctl_info.initial_marking).hi,
+        };
+        let original_initial_marking =
+            match normalization_map.get(&pctl_info.initial_marking).unwrap() {
+                Node::State(k) => k,
+                Node::Action(_) => unreachable!(),
+            };
+        print_results(&initial_min, &initial_max, original_initial_marking);
+
+        // A marking's probability is the one of the product state pairing it with the initial
+        // state of the automaton, which every product has for every marking
+        let mut probabilities = BTreeMap::new();
+        for (node, marking) in &normalization_map {
+            if let Node::State(marking) = marking {
+                let (min, max) = (min(*node), product.max(*node));
+                probabilities.insert(marking.clone(), Probability { min, max });
+            }
+        }
+        let satisfying_markings = normalization_map
+            .iter()
+            .filter_map(|(node, marking)| match marking {
+                Node::State(marking) if satisfies(*node) => Some(marking.clone()),
+                _ => None,
+            })
+            .collect();
+
+        let scheduler = match pctl_info.scheduler {
+            true => Some(decisions(
+                &product.info.reach_graph,
+                &product.max_scheduler(),
+                |s| product.marking(s, &normalization_map),
+            )),
+            false => None,
+        };
+
+        // A violating run is accepted by the automaton of the negated formula under the
+        // maximizing scheduler
+        let counterexample = match pctl_info.counterexample {
+            Some(_) if !satisfied => {
+                let lasso = lasso(
+                    &negated.info.reach_graph,
+                    &negated.max_scheduler(),
+                    negated.info.initial_marking,
+                    |scc| {
+                        negated
+                            .components
+                            .iter()
+                            .any(|c| c.targets.iter().any(|t| scc.contains(t)))
+                    },
+                );
+                if lasso.is_none() {
+                    warn!("No run accepted by the automaton of the negated formula was found under the maximizing scheduler");
+                }
+                let state = |s| negated.marking(s, &normalization_map);
+                let transition = |e| negated.transition_name(e, pctl_info);
+                lasso.map(|(prefix, cycle)| Counterexample::Lasso {
+                    prefix: prefix.to_trace(state, transition),
+                    cycle: cycle.to_trace(state, transition),
+                })
+            }
+            _ => None,
+        };
+        Ok(CheckResult {
+            initial_marking: original_initial_marking.clone(),
+            satisfied,
+            value: None,
+            exact_value: None,
+            value_bounds: Some(value_bounds),
+            satisfying_markings,
+            probabilities,
+            reach_graph: GraphSize::of(&pctl_info.reach_graph),
+            product: Some(GraphSize::of(&product.graph)),
+            scheduler,
+            counterexample,
+            automata: translation.hoa,
+            timings: Timings::default(),
+        })
+    }
+
+    /// Translates the formula to the automaton the product is built with. The HOA of the stages
+    /// in `dump_automata` is recorded, and stages only they need are built as well
+    fn translate(&self, pctl_info: &PctlInfo) -> Translation {
+        let stages = &pctl_info.dump_automata;
+        let automaton = pctl_info.automaton;
+        let name = self.to_string();
+        let mut hoa = Vec::new();
+        let mut dump = |stage: AutomatonStage, to_hoa: &dyn Fn() -> String| {
+            if stages.contains(&stage) {
+                hoa.push((stage, to_hoa()));
+            }
+        };
+
+        let vwaa = vwaa::to_vwaa(self.clone());
+        dump(AutomatonStage::Vwaa, &|| hoa::vwaa(&vwaa, &name));
+        let gba = gba::to_gba(vwaa);
+        dump(AutomatonStage::Gba, &|| hoa::gba(&gba, &name));
+        let ldba = (automaton == AutomatonType::Ldba || stages.contains(&AutomatonStage::Ldba))
+            .then(|| to_ldba(&gba));
+        if let Some(ldba) = &ldba {
+            dump(AutomatonStage::Ldba, &|| hoa::ldba(ldba, &name));
+        }
+        let needs_dra = automaton != AutomatonType::Ldba
+            || stages.iter().any(|s| {
+                matches!(
+                    s,
+                    AutomatonStage::Ba | AutomatonStage::Dra | AutomatonStage::Dpa
+                )
+            });
+        let dra = needs_dra.then(|| {
+            let ba = to_ba(gba);
+            dump(AutomatonStage::Ba, &|| hoa::ba(&ba, &name));
+            let powerba = to_powerba(&ba);
+            let dra = determinize(powerba);
+            dump(AutomatonStage::Dra, &|| hoa::dra(&dra, &name));
+            dra
+        });
+        let dpa = (automaton == AutomatonType::Dpa || stages.contains(&AutomatonStage::Dpa))
+            .then(|| to_dpa(dra.as_ref().unwrap()));
+        if let Some(dpa) = &dpa {
+            dump(AutomatonStage::Dpa, &|| hoa::dpa(dpa, &name));
+        }
+        Translation {
+            ldba,
+            dra,
+            dpa,
+            hoa,
+        }
+    }
+
+    /// Product with the automaton of the type in `pctl_info`, without recording any HOA
+    fn product(&self, pctl_info: &PctlInfo) -> Result<Product, McspError> {
+        let gba = gba::to_gba(vwaa::to_vwaa(self.clone()));
+        if pctl_info.automaton == AutomatonType::Ldba {
+            return Product::of(&to_ldba(&gba), pctl_info);
+        }
+        let dra = determinize(to_powerba(&to_ba(gba)));
+        match pctl_info.automaton {
+            AutomatonType::Dpa => Product::of(&to_dpa(&dra), pctl_info),
+            _ => Product::of(&dra, pctl_info),
+        }
+    }
+}
+
+/// Automata of the translation of a formula, None for the stages which were not built
+struct Translation {
+    ldba: Option<ldba::LDBA>,
+    dra: Option<safra::DRA>,
+    dpa: Option<dpa::DPA>,
+    /// HOA of the requested stages
+    hoa: Vec<(AutomatonStage, String)>,
+}
+
+/// Product of the model with an automaton, together with the probabilities of reaching its
+/// accepting components
+struct Product {
+    graph: MDP<(NodeIndex, String)>,
+    components: Vec<AcceptingComponent>,
+    automaton_initial: String,
+    rename_map: BTreeMap<Node<(NodeIndex, String)>, NodeIndex>,
+    /// The product as a model with the atomic proposition "aec" holding in the accepting
+    /// components
+    info: PctlInfo,
+    s_1: HashSet<NodeIndex>,
+    s_q: HashSet<NodeIndex>,
+    prob_map_max: HashMap<NodeIndex, f64>,
+    max_bounds: HashMap<NodeIndex, Interval>,
+}
+
+impl Product {
+    fn of<A: Automaton>(automaton: &A, pctl_info: &PctlInfo) -> Result<Product, McspError> {
+        let (cross_mdp, components) = cross_mdp(automaton, pctl_info);
+        let aec: HashSet<NodeIndex> = components
+            .iter()
+            .flat_map(|c| c.nodes.iter().copied())
+            .collect();
         let rename_map = rename_map(&cross_mdp);
 
         let renamed_mdp = cross_mdp.map(
@@ -140,15 +344,30 @@ impl PhiOp {
             |_, e| *e,
         );
         let renamed_initial = rename_map
-            .get(&Node::State((pctl_info.initial_marking, dra_initial)))
+            .get(&Node::State((
+                pctl_info.initial_marking,
+                automaton.initial().to_string(),
+            )))
             .unwrap();
         let mut adapter_ap_map = HashMap::new();
         adapter_ap_map.insert("aec".into(), aec);
+        let sparse = SparseMdp::from_graph(&renamed_mdp);
         let adapter_pctl_info = PctlInfo {
             initial_marking: *renamed_initial,
             reach_graph: renamed_mdp,
             ap_map: adapter_ap_map,
             max_error: pctl_info.max_error,
+            rewards: RewardMaps::default(),
+            uniformisation_rate: None,
+            exact: None,
+            solver: pctl_info.solver,
+            automaton: pctl_info.automaton,
+            sparse,
+            scheduler: false,
+            labels: Default::default(),
+            transition_names: Vec::new(),
+            counterexample: None,
+            dump_automata: Vec::new(),
         };
 
         let pctl_until = Pctl_Until {
@@ -158,37 +377,114 @@ impl PhiOp {
             }),
         };
 
-        let mut prob_map_min: HashMap<NodeIndex, f64> = HashMap::new();
-        let (s_1, s_q) = pctl_until.s1_sq(&adapter_pctl_info, &mut prob_map_min);
-        let mut prob_map_max = prob_map_min.clone();
-        Pctl_Until::iterate_prob(
+        let mut prob_map_max: HashMap<NodeIndex, f64> = HashMap::new();
+        let (s_1, s_q) = pctl_until.s1_sq(&adapter_pctl_info, &mut prob_map_max)?;
+        let max_bounds = Pctl_Until::iterate_prob(
             &adapter_pctl_info,
             s_q.clone(),
-            &mut prob_map_min,
+            &mut prob_map_max,
             s_1.clone(),
-            &Comp::Geq,
+            &Comp::Leq,
+        )?;
+        Ok(Product {
+            graph: cross_mdp,
+            components,
+            automaton_initial: automaton.initial().to_string(),
+            rename_map,
+            info: adapter_pctl_info,
+            s_1,
+            s_q,
+            prob_map_max,
+            max_bounds,
+        })
+    }
+
+    /// Product state pairing the node of the model with the initial state of the automaton
+    fn initial_state(&self, node: NodeIndex) -> NodeIndex {
+        self.rename_map[&Node::State((node, self.automaton_initial.clone()))]
+    }
+
+    fn max(&self, node: NodeIndex) -> f64 {
+        self.prob_map_max[&self.initial_state(node)]
+    }
+
+    /// Interval the maximal probability is guaranteed to lie in
+    fn bounds(&self, node: NodeIndex) -> Interval {
+        self.max_bounds[&self.initial_state(node)]
+    }
+
+    /// Whether some scheduler reaches an accepting component with positive probability
+    fn reaches_accepting(&self, node: NodeIndex) -> bool {
+        let state = self.initial_state(node);
+        self.s_1.contains(&state) || self.s_q.contains(&state)
+    }
+
+    /// The scheduler maximizing the probability reaches an accepting component and stays in it
+    /// afterwards. It remembers the state of the automaton, so it decides per product state
+    fn max_scheduler(&self) -> Scheduler {
+        let graph = &self.info.reach_graph;
+        let tolerance = self
+            .max_bounds
+            .values()
+            .map(|i| i.hi - i.lo)
+            .fold(1e-12, f64::max);
+        let mut scheduler = reachability_scheduler(
+            graph,
+            &self.s_1,
+            &self.s_q,
+            &self.prob_map_max,
+            &Comp::Leq,
+            tolerance,
         );
-        Pctl_Until::iterate_prob(&adapter_pctl_info, s_q, &mut prob_map_max, s_1, &Comp::Leq);
-        let initial_min = prob_map_min
-            .get(renamed_initial)
-            .expect("Initial marking not found in min probabilites map!");
-        let initial_max = prob_map_max
-            .get(renamed_initial)
-            .expect("Initial marking not found in max probabilites map!");
-        let original_initial_marking =
-            match normalization_map.get(&pctl_info.initial_marking).unwrap() {
-                Node::State(k) => k,
-                Node::Action(_) => unreachable!(),
-            };
-        print_results(initial_min, initial_max, original_initial_marking);
+        // States of several components stay in the first one
+        for component in self.components.iter().rev() {
+            scheduler.extend(staying_scheduler(
+                graph,
+                &component.nodes,
+                &component.targets,
+            ));
+        }
+        scheduler
     }
 
-    fn to_dra(&self) -> safra::DRA {
-        let vwaa = vwaa::to_vwaa(self.clone());
-        let gba = gba::to_gba(vwaa);
-        let ba = to_ba(gba);
-        let powerba = to_powerba(&ba);
-        determinize(powerba)
+    /// Marking and state of the automaton of the product state
+    fn marking<K: Clone>(
+        &self,
+        state: NodeIndex,
+        normalization_map: &BTreeMap<NodeIndex, Node<K>>,
+    ) -> (K, Option<String>) {
+        let Node::State((node, automaton_state)) = &self.graph[state] else {
+            unreachable!()
+        };
+        let Some(Node::State(marking)) = normalization_map.get(node) else {
+            unreachable!()
+        };
+        (marking.clone(), Some(automaton_state.clone()))
+    }
+
+    /// The edges of the product map to the edge of the net's graph between the same markings
+    /// with the action of the same name
+    fn transition_name(&self, edge: EdgeIndex, pctl_info: &PctlInfo) -> String {
+        let (action, target) = self.graph.edge_endpoints(edge).unwrap();
+        let source = self
+            .graph
+            .neighbors_directed(action, Incoming)
+            .next()
+            .unwrap();
+        let (Node::State((source, _)), Node::Action(name), Node::State((target, _))) = (
+            &self.graph[source],
+            &self.graph[action],
+            &self.graph[target],
+        ) else {
+            unreachable!()
+        };
+        let original = &pctl_info.reach_graph;
+        original
+            .neighbors_directed(*source, Outgoing)
+            .filter(|a| matches!(&original[*a], Node::Action(a) if a == name))
+            .find_map(|a| original.find_edge(a, *target))
+            .map(|e| transition_name(pctl_info, e))
+            .unwrap_or_else(|| "(stay)".into())
     }
 }
 
@@ -268,7 +564,9 @@ trait Phi {
     fn fmt(&self) -> String;
     fn negate(&self) -> PhiOp;
     fn is_temporal(&self) -> bool;
+    #[allow(dead_code)]
     fn get_subformula(&self) -> Vec<PhiOp>;
+    #[allow(dead_code)]
     fn is_atomic(&self) -> bool;
 }
 
@@ -702,3 +1000,85 @@ impl Conjuction {
         Conjuction(elements)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::LogicType;
+    use crate::mcsp::{AutomatonType, CheckOptions};
+    use crate::testing::{check_net, CHOICE_NET, MAX_REACH_C, MIN_REACH_C};
+
+    /// Formulas with the minimal and maximal probability of the initial marking of `CHOICE_NET`
+    const FORMULAS: [(&str, f64, f64); 8] = [
+        ("F(c)", MIN_REACH_C, MAX_REACH_C),
+        ("(!d) U (c)", MIN_REACH_C, MAX_REACH_C),
+        ("(F(c)) & (F(d))", MIN_REACH_C, MAX_REACH_C),
+        ("X(X(c))", 0.125, 0.25),
+        ("G(F(c))", 0.0, 0.0),
+        ("G(!d)", 0.0, 0.0),
+        ("F(G(d))", 1.0, 1.0),
+        ("G((c) | (F(d)))", 1.0, 1.0),
+    ];
+
+    fn options(automaton: AutomatonType) -> CheckOptions {
+        CheckOptions {
+            max_error: 1e-4,
+            precision_digits: 6,
+            automaton,
+            ..Default::default()
+        }
+    }
+
+    fn assert_probabilities(automaton: AutomatonType) {
+        for (phi, min, max) in FORMULAS {
+            let result = check_net(CHOICE_NET, LogicType::LTL, phi, &options(automaton));
+            let probability = result.probabilities[&result.initial_marking];
+            assert!((probability.min - min).abs() <= 2e-4, "{}", phi);
+            assert!((probability.max - max).abs() <= 2e-4, "{}", phi);
+            assert!(probability.min <= probability.max, "{}", phi);
+            assert_eq!(result.satisfied, min == 1.0, "{}", phi);
+        }
+    }
+
+    #[test]
+    fn dra_gives_the_probabilities() {
+        assert_probabilities(AutomatonType::Dra);
+    }
+
+    #[test]
+    fn ldba_gives_the_probabilities_of_the_dra() {
+        assert_probabilities(AutomatonType::Ldba);
+    }
+
+    #[test]
+    fn dpa_gives_the_probabilities_of_the_dra() {
+        assert_probabilities(AutomatonType::Dpa);
+    }
+
+    #[test]
+    fn automata_agree_on_every_marking() {
+        let automata = [AutomatonType::Dra, AutomatonType::Dpa, AutomatonType::Ldba];
+        for (phi, min, max) in FORMULAS {
+            let results = automata.map(|a| check_net(CHOICE_NET, LogicType::LTL, phi, &options(a)));
+            let dra = &results[0];
+            assert_eq!(dra.probabilities.len(), 4, "{}", phi);
+            for result in &results {
+                assert_eq!(
+                    result.satisfying_markings, dra.satisfying_markings,
+                    "{}",
+                    phi
+                );
+                for (marking, probability) in &result.probabilities {
+                    let expected = dra.probabilities[marking];
+                    assert!((probability.min - expected.min).abs() <= 2e-4, "{}", phi);
+                    assert!((probability.max - expected.max).abs() <= 2e-4, "{}", phi);
+                }
+                let bounds = result.value_bounds.unwrap();
+                assert!(
+                    bounds.lo <= min + 1e-9 && max <= bounds.hi + 1e-9,
+                    "{}",
+                    phi
+                );
+            }
+        }
+    }
+}
@@ -31,7 +31,7 @@ pub fn to_ba(gba: GBA) -> BA {
     let mut acc_transitions: Vec<HashSet<(String, SimpleTransition)>> =
         gba.acc_transitions.into_values().collect();
     acc_transitions.sort_by_key(|s1| Reverse(s1.len()));
-    pop_queue.extend(initials);
+    pop_queue.extend(initials.iter().cloned());
     while let Some(state) = pop_queue.pop_front() {
         let transitions = delta(&state, &gba.trans_f, &acc_transitions);
         trans_f.insert(state.clone(), transitions);
@@ -44,12 +44,11 @@ pub fn to_ba(gba: GBA) -> BA {
         }
     }
 
-    trans_f = prune_states(trans_f, acc_transitions.len());
+    let (trans_f, merged) = prune_states(trans_f, acc_transitions.len());
     let rename_map = get_rename_map(&trans_f);
-    let initials = trans_f
-        .keys()
-        .filter(|(_, index)| *index == 0)
-        .map(|state| rename_map.get(state).unwrap().clone())
+    let initials = initials
+        .iter()
+        .map(|state| rename_map[merged.get(state).unwrap_or(state)].clone())
         .collect::<BTreeSet<_>>();
     let finals = trans_f
         .keys()
@@ -82,7 +81,7 @@ pub fn to_ba(gba: GBA) -> BA {
 fn delta(
     state: &State,
     old_delta: &HashMap<String, SimpleTransitions>,
-    acc_t: &Vec<HashSet<(String, SimpleTransition)>>,
+    acc_t: &[HashSet<(String, SimpleTransition)>],
 ) -> HashSet<Transition> {
     let mut result: HashSet<Transition> = HashSet::new();
     let delta_state = old_delta.get(&state.0).unwrap();
@@ -99,7 +98,7 @@ fn delta(
 }
 
 fn next(
-    acc_t: &Vec<HashSet<(String, SimpleTransition)>>,
+    acc_t: &[HashSet<(String, SimpleTransition)>],
     j: usize,
     transition: &(String, SimpleTransition),
 ) -> usize {
@@ -108,27 +107,30 @@ fn next(
         false => j,
     };
 
-    return (start_index..=acc_t.len())
+    (start_index..=acc_t.len())
         .filter(|&i| {
             ((start_index + 1)..=i).all(|k| acc_t.get(k - 1).unwrap().contains(transition))
         })
         .max()
-        .unwrap_or(start_index);
+        .unwrap_or(start_index)
 }
 
+/// Removes the transitions another transition to the same target with fewer conditions covers
 fn prune_transitions(transitions: &mut HashSet<Transition>) {
     let copy = transitions.clone();
     transitions.retain(|t| {
         !copy.iter().any(|ot| {
-            t.props != ot.props && t.props.0.is_subset(&ot.props.0) && t.target == ot.target
+            t.props != ot.props && ot.props.0.is_subset(&t.props.0) && t.target == ot.target
         })
     })
 }
 
+/// Merges the states with the same transitions which are both final or both not. Returns the
+/// remaining states and the state every removed one was merged into
 fn prune_states(
     mut trans_f: HashMap<State, HashSet<Transition>>,
     r: usize,
-) -> HashMap<State, HashSet<Transition>> {
+) -> (HashMap<State, HashSet<Transition>>, HashMap<State, State>) {
     let mut temp_trans_f: HashMap<State, HashSet<Transition>> = HashMap::new();
     let mut rename_map: HashMap<State, State> = HashMap::new();
     for (state, transitions) in trans_f.clone() {
@@ -142,7 +144,7 @@ fn prune_states(
         }
     }
     trans_f.retain(|k, _| temp_trans_f.contains_key(k));
-    trans_f
+    let trans_f = trans_f
         .into_iter()
         .map(|(k, transitions)| {
             let mapped_transitions = transitions
@@ -161,5 +163,6 @@ fn prune_states(
                 .collect();
             (k, mapped_transitions)
         })
-        .collect()
+        .collect();
+    (trans_f, rename_map)
 }
@@ -44,6 +44,11 @@ impl Alphabet {
         new_set.insert(ap);
         Alphabet(new_set)
     }
+    /// The letter with only the propositions of `aps`
+    pub fn restrict(&self, aps: &BTreeSet<PhiOp>) -> Alphabet {
+        Alphabet(self.0.intersection(aps).cloned().collect())
+    }
+
     pub fn intersection(&self, other: &Alphabet) -> Option<Alphabet> {
         if self.0.iter().any(|s| {
             other
@@ -91,10 +96,8 @@ where
     T: PartialEq,
 {
     let mut conj_to_index: HashMap<&S, String> = HashMap::with_capacity(trans_f.len());
-    let mut index = 1;
-    for (conj, _) in trans_f {
+    for (index, conj) in (1..).zip(trans_f.keys()) {
         conj_to_index.insert(conj, index.to_string());
-        index += 1;
     }
     conj_to_index
 }
@@ -0,0 +1,250 @@
+use super::{
+    common::Alphabet,
+    mdpa::{Acceptance, Automaton},
+    safra::DRA,
+    PhiOp,
+};
+use petgraph::algo::kosaraju_scc;
+use petgraph::graph::{DiGraph, NodeIndex};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+/// Deterministic parity automaton. A run is accepted if the maximal priority it visits
+/// infinitely often is even
+#[allow(clippy::upper_case_acronyms)]
+pub struct DPA {
+    pub initial: String,
+    pub trans_f: HashMap<String, BTreeMap<Alphabet, String>>,
+    pub priority: HashMap<String, usize>,
+    /// Atomic propositions of the formula. Every state has a transition for each of their subsets
+    pub aps: BTreeSet<PhiOp>,
+}
+
+impl DPA {
+    /// Target of the transition on the letter without the propositions the formula does not
+    /// mention
+    pub fn delta(&self, state: &str, alphabet: &Alphabet) -> String {
+        self.trans_f[state][&alphabet.restrict(&self.aps)].clone()
+    }
+}
+
+impl Automaton for DPA {
+    fn initial(&self) -> &str {
+        &self.initial
+    }
+
+    fn successors(&self, state: &str, letter: &Alphabet) -> Vec<String> {
+        vec![self.delta(state, letter)]
+    }
+
+    fn acceptance(&self) -> Acceptance<'_> {
+        Acceptance::Parity(&self.priority)
+    }
+}
+
+/// State of the DRA with its index appearance record: the Rabin pairs ordered by the last visit
+/// of their set L, most recent first
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct State {
+    dra_state: String,
+    record: Vec<usize>,
+    priority: usize,
+}
+
+/// Converts the DRA with index appearance records. Entering a state of L of the pair at
+/// position i of the record has priority 2i + 3, of K priority 2i + 2, and others 1. The pairs
+/// whose L is visited finitely often end up behind all others in the record, so the maximal
+/// priority visited infinitely often is even iff K of one of them is visited infinitely often.
+/// The records tell apart many states with the same future, so the result is minimized
+pub fn to_dpa(dra: &DRA) -> DPA {
+    let initial = State {
+        dra_state: dra.initial.clone(),
+        record: (0..dra.acc.len()).collect(),
+        priority: 1,
+    };
+    let mut names: HashMap<State, String> = HashMap::from([(initial.clone(), "1".into())]);
+    let mut pop_queue = VecDeque::from([initial.clone()]);
+    let mut trans_f: HashMap<String, BTreeMap<Alphabet, String>> = HashMap::new();
+    let mut priority = HashMap::new();
+    while let Some(state) = pop_queue.pop_front() {
+        let name = names[&state].clone();
+        priority.insert(name.clone(), state.priority);
+        let mut transitions = BTreeMap::new();
+        for (letter, dra_target) in &dra.trans_f[&state.dra_state] {
+            let target = step(dra, &state, dra_target);
+            let next_name = names.len() + 1;
+            let target_name = names
+                .entry(target.clone())
+                .or_insert_with(|| {
+                    pop_queue.push_back(target);
+                    next_name.to_string()
+                })
+                .clone();
+            transitions.insert(letter.clone(), target_name);
+        }
+        trans_f.insert(name, transitions);
+    }
+
+    normalize_priorities(&trans_f, &mut priority);
+    minimize(DPA {
+        initial: names[&initial].clone(),
+        trans_f,
+        priority,
+        aps: dra.aps.clone(),
+    })
+}
+
+/// Runs only visit the states outside of cycles finitely often, so their priority does not
+/// matter and becomes 0. Within a strongly connected component only the order and the parity of
+/// the priorities matter, so they are compressed to the smallest ones with the same parity changes
+fn normalize_priorities(
+    trans_f: &HashMap<String, BTreeMap<Alphabet, String>>,
+    priority: &mut HashMap<String, usize>,
+) {
+    let mut graph: DiGraph<&str, ()> = DiGraph::new();
+    let nodes: HashMap<&str, NodeIndex> = trans_f
+        .keys()
+        .map(|state| (state.as_str(), graph.add_node(state)))
+        .collect();
+    for (state, transitions) in trans_f {
+        for target in transitions.values() {
+            graph.update_edge(nodes[state.as_str()], nodes[target.as_str()], ());
+        }
+    }
+    for component in kosaraju_scc(&graph) {
+        if component.len() == 1 && graph.find_edge(component[0], component[0]).is_none() {
+            priority.insert(graph[component[0]].to_string(), 0);
+            continue;
+        }
+        let priorities: BTreeSet<usize> = component
+            .iter()
+            .map(|node| priority[graph[*node]])
+            .collect();
+        let mut compressed = HashMap::new();
+        let mut next = priorities.first().unwrap() % 2;
+        for p in priorities {
+            if p % 2 != next % 2 {
+                next += 1;
+            }
+            compressed.insert(p, next);
+        }
+        for node in component {
+            let state = graph[node];
+            priority.insert(state.to_string(), compressed[&priority[state]]);
+        }
+    }
+}
+
+/// Merges the states with the same priority whose successors on every letter are merged as well,
+/// refining the partition by priorities until it is stable
+fn minimize(dpa: DPA) -> DPA {
+    let mut states: Vec<&String> = dpa.trans_f.keys().collect();
+    states.sort();
+    let mut block: HashMap<&String, usize> =
+        states.iter().map(|s| (*s, dpa.priority[*s])).collect();
+    let mut count = 0;
+    loop {
+        let mut signatures: HashMap<(usize, Vec<usize>), usize> = HashMap::new();
+        let refined: HashMap<&String, usize> = states
+            .iter()
+            .map(|s| {
+                let successors = dpa.trans_f[*s].values().map(|t| block[t]).collect();
+                let next = signatures.len();
+                (
+                    *s,
+                    *signatures.entry((block[*s], successors)).or_insert(next),
+                )
+            })
+            .collect();
+        block = refined;
+        if signatures.len() == count {
+            break;
+        }
+        count = signatures.len();
+    }
+
+    // Names the blocks in the order they are found from the initial state
+    let mut names: HashMap<usize, String> = HashMap::from([(block[&dpa.initial], "1".to_string())]);
+    let mut pop_queue = VecDeque::from([&dpa.initial]);
+    let mut trans_f = HashMap::new();
+    let mut priority = HashMap::new();
+    while let Some(state) = pop_queue.pop_front() {
+        let name = names[&block[state]].clone();
+        priority.insert(name.clone(), dpa.priority[state]);
+        let mut transitions = BTreeMap::new();
+        for (letter, target) in &dpa.trans_f[state] {
+            let next_name = names.len() + 1;
+            let target_name = names
+                .entry(block[target])
+                .or_insert_with(|| {
+                    pop_queue.push_back(target);
+                    next_name.to_string()
+                })
+                .clone();
+            transitions.insert(letter.clone(), target_name);
+        }
+        trans_f.insert(name, transitions);
+    }
+    DPA {
+        initial: "1".into(),
+        trans_f,
+        priority,
+        aps: dpa.aps,
+    }
+}
+
+fn step(dra: &DRA, state: &State, dra_target: &str) -> State {
+    let mut priority = 1;
+    for (position, pair) in state.record.iter().enumerate() {
+        let (l, k) = &dra.acc[*pair];
+        if l.contains(dra_target) {
+            priority = priority.max(2 * position + 3);
+        }
+        if k.contains(dra_target) {
+            priority = priority.max(2 * position + 2);
+        }
+    }
+    let (visited, others): (Vec<usize>, Vec<usize>) = state
+        .record
+        .iter()
+        .copied()
+        .partition(|pair| dra.acc[*pair].0.contains(dra_target));
+    State {
+        dra_state: dra_target.to_string(),
+        record: visited.into_iter().chain(others).collect(),
+        priority,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::ltl::{
+        ba::to_ba, gba::to_gba, powerba::to_powerba, safra::determinize, vwaa::to_vwaa, LtlImpl,
+    };
+    use crate::logic::{Formula, LogicImpl};
+
+    fn dra(formula: &str) -> DRA {
+        let Formula::Ltl(phi) = LtlImpl.parse(&format!("PHI = {}", formula)).unwrap() else {
+            unreachable!()
+        };
+        determinize(to_powerba(&to_ba(to_gba(to_vwaa(phi)))))
+    }
+
+    #[test]
+    fn merges_states_with_the_same_priority_and_successors() {
+        let dra = dra("F(B)");
+        let dpa = to_dpa(&dra);
+        assert_eq!(dpa.trans_f.len(), dra.trans_f.len());
+    }
+
+    #[test]
+    fn states_outside_of_cycles_have_priority_0() {
+        let dpa = to_dpa(&dra("X(X(B))"));
+        let initial_successors: Vec<&String> = dpa.trans_f[&dpa.initial].values().collect();
+        assert_eq!(dpa.priority[&dpa.initial], 0);
+        for state in initial_successors {
+            assert_ne!(*state, dpa.initial);
+            assert_eq!(dpa.priority[state], 0);
+        }
+    }
+}
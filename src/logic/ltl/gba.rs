@@ -9,6 +9,7 @@ use std::{
 };
 
 type ConjTransitions = HashSet<ConjTransition>;
+type ConjAcceptance = HashMap<PhiOp, HashSet<(Conjuction, ConjTransition)>>;
 pub type SimpleTransitions = HashSet<SimpleTransition>;
 
 #[allow(clippy::upper_case_acronyms)]
@@ -79,10 +80,16 @@ pub fn to_gba(vwaa: VWAA) -> GBA {
     }
 
     prune_transitions(&mut accept_t, &mut trans_f);
-    (trans_f, accept_t) = prune_states(trans_f, accept_t);
+    let merged;
+    (trans_f, accept_t, merged) = prune_states(trans_f, accept_t);
     let rename_map = get_rename_map(&trans_f);
     let renamed_trans_f = rename_trans_f(&trans_f, &rename_map);
-    let renamed_initial = rename_initial(vwaa.initial, &rename_map);
+    let initial = vwaa
+        .initial
+        .into_iter()
+        .map(|c| merged.get(&c).cloned().unwrap_or(c))
+        .collect();
+    let renamed_initial = rename_initial(initial, &rename_map);
     let renamed_accept_t = rename_accept_t(accept_t, &rename_map);
 
     GBA {
@@ -145,35 +152,69 @@ fn rename_accept_t(
         .collect()
 }
 
+/// Removes the transitions another transition of the same state to the same target covers, which
+/// has fewer conditions and is in every acceptance set the removed one is in
 fn prune_transitions(
     accept_t: &mut HashMap<PhiOp, HashSet<(Conjuction, ConjTransition)>>,
     trans_f: &mut HashMap<Conjuction, HashSet<ConjTransition>>,
 ) {
-    for set in accept_t.values_mut() {
-        let set_duplicate = set.clone();
-        set.retain(|(s, t)| {
-            let delete = set_duplicate.iter().any(|(s2, t2)| {
-                t != t2 && s == s2 && t2.props.0.is_subset(&t.props.0) && t2.target.0 == t.target.0
-            });
-            if delete {
-                trans_f.get_mut(s).unwrap().remove(t);
+    for (state, transitions) in trans_f.iter_mut() {
+        let accepting = |t: &ConjTransition| -> Vec<bool> {
+            let pair = (state.clone(), t.clone());
+            accept_t.values().map(|set| set.contains(&pair)).collect()
+        };
+        let covered: Vec<ConjTransition> = transitions
+            .iter()
+            .filter(|t| {
+                transitions.iter().any(|t2| {
+                    t != &t2
+                        && t2.props.0.is_subset(&t.props.0)
+                        && t2.target == t.target
+                        && accepting(t)
+                            .iter()
+                            .zip(accepting(t2))
+                            .all(|(in_t, in_t2)| !in_t || in_t2)
+                })
+            })
+            .cloned()
+            .collect();
+        for t in covered {
+            transitions.remove(&t);
+            for set in accept_t.values_mut() {
+                set.remove(&(state.clone(), t.clone()));
             }
-            !delete
-        });
+        }
     }
 }
 
+/// Merges the states with the same transitions in the same acceptance sets. Returns the remaining
+/// states, their acceptance sets and the state every removed one was merged into
 fn prune_states(
     trans_f: HashMap<Conjuction, HashSet<ConjTransition>>,
     accept_t: HashMap<PhiOp, HashSet<(Conjuction, ConjTransition)>>,
 ) -> (
     HashMap<Conjuction, HashSet<ConjTransition>>,
-    HashMap<PhiOp, HashSet<(Conjuction, ConjTransition)>>,
+    ConjAcceptance,
+    HashMap<Conjuction, Conjuction>,
 ) {
+    let accepting = |state: &Conjuction, transitions: &HashSet<ConjTransition>| {
+        accept_t
+            .values()
+            .map(|set| {
+                transitions
+                    .iter()
+                    .filter(|t| set.contains(&(state.clone(), (*t).clone())))
+                    .cloned()
+                    .collect::<HashSet<_>>()
+            })
+            .collect::<Vec<_>>()
+    };
     let mut temp_trans_f: HashMap<Conjuction, HashSet<ConjTransition>> = HashMap::new();
     let mut rename_map: HashMap<Conjuction, Conjuction> = HashMap::new();
     for (state, transitions) in trans_f.clone() {
-        let opt_equiv_state = temp_trans_f.iter().find(|(_, ot)| **ot == transitions);
+        let opt_equiv_state = temp_trans_f.iter().find(|(os, ot)| {
+            **ot == transitions && accepting(os, ot) == accepting(&state, &transitions)
+        });
         if let Some((os, _)) = opt_equiv_state {
             rename_map.insert(state, os.clone());
         } else {
@@ -222,7 +263,7 @@ fn prune_states(
             (phi, new_transitions)
         })
         .collect();
-    (new_trans_f, new_accept_t)
+    (new_trans_f, new_accept_t, rename_map)
 }
 
 fn delta2(conj: &Conjuction) -> Transitions {
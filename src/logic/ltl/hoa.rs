@@ -0,0 +1,705 @@
+use super::{
+    ba::BA, common::Alphabet, dpa::DPA, gba::GBA, ldba::LDBA, safra::DRA, vwaa::VWAA, And,
+    Conjuction, PhiOp,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Display;
+
+/// Label, conjunction of targets and acceptance sets of an edge
+type Edge = (String, Vec<usize>, Vec<usize>);
+
+/// Automaton in the Hanoi Omega-Automata format. States are numbered in the order of `states`
+struct Hoa {
+    name: String,
+    /// Conjunctions of states the automaton starts in
+    start: Vec<Vec<usize>>,
+    aps: Vec<String>,
+    acc_name: String,
+    /// Number of acceptance sets followed by the condition on them
+    acceptance: String,
+    properties: &'static str,
+    /// Name and acceptance sets of every state
+    states: Vec<(String, Vec<usize>)>,
+    /// Edges of every state
+    edges: Vec<Vec<Edge>>,
+}
+
+impl Display for Hoa {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "HOA: v1")?;
+        writeln!(f, "tool: \"mcsp\"")?;
+        writeln!(f, "name: {}", quote(&self.name))?;
+        writeln!(f, "States: {}", self.states.len())?;
+        for start in &self.start {
+            writeln!(f, "Start: {}", join(start, "&"))?;
+        }
+        write!(f, "AP: {}", self.aps.len())?;
+        for ap in &self.aps {
+            write!(f, " {}", quote(ap))?;
+        }
+        writeln!(f)?;
+        writeln!(f, "acc-name: {}", self.acc_name)?;
+        writeln!(f, "Acceptance: {}", self.acceptance)?;
+        writeln!(f, "properties: {}", self.properties)?;
+        writeln!(f, "--BODY--")?;
+        for (index, ((name, acc), edges)) in self.states.iter().zip(&self.edges).enumerate() {
+            writeln!(f, "State: {} {}{}", index, quote(name), acc_sets(acc))?;
+            for (label, targets, acc) in edges {
+                writeln!(f, "[{}] {}{}", label, join(targets, "&"), acc_sets(acc))?;
+            }
+        }
+        writeln!(f, "--END--")
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn join(indices: &[usize], separator: &str) -> String {
+    let indices: Vec<String> = indices.iter().map(|i| i.to_string()).collect();
+    indices.join(separator)
+}
+
+fn acc_sets(acc: &[usize]) -> String {
+    match acc.is_empty() {
+        true => String::new(),
+        false => format!(" {{{}}}", join(acc, " ")),
+    }
+}
+
+fn ap_name(phi: &PhiOp) -> Option<&str> {
+    match phi {
+        PhiOp::AP(ap) => Some(&ap.value),
+        PhiOp::Not(not) => Some(&not.ap.value),
+        _ => None,
+    }
+}
+
+/// Atomic propositions occurring in the alphabets, in alphabetical order
+fn aps<'a>(alphabets: impl Iterator<Item = &'a Alphabet>) -> Vec<String> {
+    alphabets
+        .flat_map(|alphabet| alphabet.0.iter().filter_map(ap_name))
+        .map(String::from)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Label of a transition enabled by the literals of the alphabet
+fn conjunction(props: &Alphabet, aps: &[String]) -> String {
+    let literals: Vec<String> = props
+        .0
+        .iter()
+        .filter_map(|phi| {
+            let index = aps
+                .iter()
+                .position(|ap| Some(ap.as_str()) == ap_name(phi))?;
+            Some(match phi {
+                PhiOp::Not(_) => format!("!{}", index),
+                _ => index.to_string(),
+            })
+        })
+        .collect();
+    match literals.is_empty() {
+        true => "t".into(),
+        false => literals.join(" & "),
+    }
+}
+
+/// Label of a letter, which holds exactly the atomic propositions it contains
+fn letter(letter: &Alphabet, aps: &[String]) -> String {
+    let literals: Vec<String> = aps
+        .iter()
+        .enumerate()
+        .map(
+            |(index, ap)| match letter.0.iter().any(|phi| ap_name(phi) == Some(ap.as_str())) {
+                true => index.to_string(),
+                false => format!("!{}", index),
+            },
+        )
+        .collect();
+    match literals.is_empty() {
+        true => "t".into(),
+        false => literals.join(" & "),
+    }
+}
+
+/// Numbers the states named by numbers in ascending order
+fn numbered<'a>(states: impl Iterator<Item = &'a String>) -> Vec<&'a String> {
+    let mut states: Vec<&String> = states.collect::<BTreeSet<_>>().into_iter().collect();
+    states.sort_by_key(|s| (s.len(), *s));
+    states
+}
+
+fn indices<'a>(states: &[&'a String]) -> HashMap<&'a String, usize> {
+    states.iter().enumerate().map(|(i, s)| (*s, i)).collect()
+}
+
+/// Co-Büchi acceptance: no branch of a run may stay in a state of an until formula forever
+pub fn vwaa(vwaa: &VWAA, name: &str) -> String {
+    let mut states: Vec<&Conjuction> = vwaa.delta.keys().collect();
+    states.sort_by_cached_key(|c| c.to_string());
+    let index: HashMap<&Conjuction, usize> =
+        states.iter().enumerate().map(|(i, c)| (*c, i)).collect();
+    let targets = |target: &PhiOp| -> Vec<usize> {
+        And::flatten(target.clone())
+            .into_iter()
+            .map(|phi| index[&Conjuction(BTreeSet::from([phi]))])
+            .collect()
+    };
+    let aps = aps(vwaa.delta.values().flatten().map(|t| &t.props));
+    let mut start: Vec<Vec<usize>> = vwaa
+        .initial
+        .iter()
+        .map(|c| {
+            c.0.iter()
+                .map(|phi| index[&Conjuction(BTreeSet::from([phi.clone()]))])
+                .collect()
+        })
+        .collect();
+    start.sort();
+    let edges = states
+        .iter()
+        .map(|state| {
+            let mut edges: Vec<Edge> = vwaa.delta[*state]
+                .iter()
+                .map(|t| (conjunction(&t.props, &aps), targets(&t.target), Vec::new()))
+                .collect();
+            edges.sort();
+            edges
+        })
+        .collect();
+    let states = states
+        .iter()
+        .map(|c| {
+            let accepting = c.0.iter().any(|phi| vwaa.final_states.contains(phi));
+            (c.to_string(), if accepting { vec![0] } else { Vec::new() })
+        })
+        .collect();
+    Hoa {
+        name: name.into(),
+        start,
+        aps,
+        acc_name: "co-Buchi".into(),
+        acceptance: "1 Fin(0)".into(),
+        properties: "trans-labels explicit-labels state-acc univ-branch",
+        states,
+        edges,
+    }
+    .to_string()
+}
+
+/// Generalized Büchi acceptance on transitions, with one set per until formula
+pub fn gba(gba: &GBA, name: &str) -> String {
+    let mut acc_sets: Vec<_> = gba.acc_transitions.iter().collect();
+    acc_sets.sort_by_key(|(phi, _)| *phi);
+    let states = numbered(
+        gba.trans_f
+            .keys()
+            .chain(&gba.initial)
+            .chain(gba.trans_f.values().flatten().map(|t| &t.target)),
+    );
+    let index = indices(&states);
+    let aps = aps(gba.trans_f.values().flatten().map(|t| &t.props));
+    let edges = states
+        .iter()
+        .map(|state| {
+            let mut edges: Vec<Edge> = gba
+                .trans_f
+                .get(*state)
+                .into_iter()
+                .flatten()
+                .map(|t| {
+                    let acc = acc_sets
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (_, set))| set.contains(&((*state).clone(), t.clone())))
+                        .map(|(i, _)| i)
+                        .collect();
+                    (conjunction(&t.props, &aps), vec![index[&t.target]], acc)
+                })
+                .collect();
+            edges.sort();
+            edges
+        })
+        .collect();
+    let acceptance = match acc_sets.len() {
+        0 => "0 t".into(),
+        n => {
+            let sets: Vec<String> = (0..n).map(|i| format!("Inf({})", i)).collect();
+            format!("{} {}", n, sets.join("&"))
+        }
+    };
+    Hoa {
+        name: name.into(),
+        start: numbered(gba.initial.iter())
+            .iter()
+            .map(|s| vec![index[s]])
+            .collect(),
+        aps,
+        acc_name: format!("generalized-Buchi {}", acc_sets.len()),
+        acceptance,
+        properties: "trans-labels explicit-labels trans-acc",
+        states: states.iter().map(|s| (s.to_string(), Vec::new())).collect(),
+        edges,
+    }
+    .to_string()
+}
+
+pub fn ba(ba: &BA, name: &str) -> String {
+    let states = numbered(
+        ba.transitions
+            .keys()
+            .chain(&ba.initials)
+            .chain(ba.transitions.values().flatten().map(|t| &t.target)),
+    );
+    let index = indices(&states);
+    let aps = aps(ba.transitions.values().flatten().map(|t| &t.props));
+    let edges = states
+        .iter()
+        .map(|state| {
+            let mut edges: Vec<Edge> = ba
+                .transitions
+                .get(*state)
+                .into_iter()
+                .flatten()
+                .map(|t| {
+                    (
+                        conjunction(&t.props, &aps),
+                        vec![index[&t.target]],
+                        Vec::new(),
+                    )
+                })
+                .collect();
+            edges.sort();
+            edges
+        })
+        .collect();
+    Hoa {
+        name: name.into(),
+        start: ba.initials.iter().map(|s| vec![index[s]]).collect(),
+        aps,
+        acc_name: "Buchi".into(),
+        acceptance: "1 Inf(0)".into(),
+        properties: "trans-labels explicit-labels state-acc",
+        states: states
+            .iter()
+            .map(|s| {
+                let acc = if ba.finals.contains(*s) {
+                    vec![0]
+                } else {
+                    Vec::new()
+                };
+                (s.to_string(), acc)
+            })
+            .collect(),
+        edges,
+    }
+    .to_string()
+}
+
+/// Edges of a deterministic automaton on every letter over the propositions of the formula
+fn deterministic_edges(
+    trans_f: &HashMap<String, BTreeMap<Alphabet, String>>,
+    delta: impl Fn(&str, &Alphabet) -> String,
+) -> (Vec<&String>, Vec<String>, Vec<Vec<Edge>>) {
+    let states = numbered(trans_f.keys());
+    let index = indices(&states);
+    let letters: BTreeSet<&Alphabet> = trans_f.values().flat_map(|m| m.keys()).collect();
+    let aps = aps(letters.iter().copied());
+    let edges = states
+        .iter()
+        .map(|state| {
+            letters
+                .iter()
+                .map(|l| (letter(l, &aps), vec![index[&delta(state, l)]], Vec::new()))
+                .collect()
+        })
+        .collect();
+    (states, aps, edges)
+}
+
+/// State-based Rabin acceptance, the pair i consists of the sets 2i (L) and 2i + 1 (K)
+pub fn dra(dra: &DRA, name: &str) -> String {
+    let (states, aps, edges) = deterministic_edges(&dra.trans_f, |s, l| dra.delta(s, l));
+    let index = indices(&states);
+    let acceptance = match dra.acc.len() {
+        0 => "0 f".into(),
+        n => {
+            let pairs: Vec<String> = (0..n)
+                .map(|i| format!("(Fin({})&Inf({}))", 2 * i, 2 * i + 1))
+                .collect();
+            format!("{} {}", 2 * n, pairs.join(" | "))
+        }
+    };
+    Hoa {
+        name: name.into(),
+        start: vec![vec![index[&dra.initial]]],
+        aps,
+        acc_name: format!("Rabin {}", dra.acc.len()),
+        acceptance,
+        properties: "trans-labels explicit-labels state-acc deterministic",
+        states: states
+            .iter()
+            .map(|s| {
+                let acc = dra
+                    .acc
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, (l, k))| [(l.contains(*s), 2 * i), (k.contains(*s), 2 * i + 1)])
+                    .filter_map(|(contained, set)| contained.then_some(set))
+                    .collect();
+                (s.to_string(), acc)
+            })
+            .collect(),
+        edges,
+    }
+    .to_string()
+}
+
+/// State-based max even parity acceptance, every state is in the set of its priority
+pub fn dpa(dpa: &DPA, name: &str) -> String {
+    let (states, aps, edges) = deterministic_edges(&dpa.trans_f, |s, l| dpa.delta(s, l));
+    let index = indices(&states);
+    let sets = dpa.priority.values().max().map_or(1, |p| p + 1);
+    // Built from the lowest priority up, each one decides if it is visited infinitely often
+    let mut acceptance = "Inf(0)".to_string();
+    for priority in 1..sets {
+        let inner = match priority {
+            1 => acceptance,
+            _ => format!("({})", acceptance),
+        };
+        acceptance = match priority % 2 {
+            0 => format!("Inf({}) | {}", priority, inner),
+            _ => format!("Fin({}) & {}", priority, inner),
+        };
+    }
+    Hoa {
+        name: name.into(),
+        start: vec![vec![index[&dpa.initial]]],
+        aps,
+        acc_name: format!("parity max even {}", sets),
+        acceptance: format!("{} {}", sets, acceptance),
+        properties: "trans-labels explicit-labels state-acc deterministic colored",
+        states: states
+            .iter()
+            .map(|s| (s.to_string(), vec![dpa.priority[*s]]))
+            .collect(),
+        edges,
+    }
+    .to_string()
+}
+
+/// Büchi acceptance on the states of the breakpoint construction after the last acceptance set
+pub fn ldba(ldba: &LDBA, name: &str) -> String {
+    let states = numbered(ldba.trans_f.keys());
+    let index = indices(&states);
+    let aps = aps(ldba.trans_f.values().flat_map(|m| m.keys()));
+    let accepting = &ldba.acc[0].1;
+    let edges = states
+        .iter()
+        .map(|state| {
+            ldba.trans_f[*state]
+                .iter()
+                .flat_map(|(l, targets)| {
+                    targets
+                        .iter()
+                        .map(|t| (letter(l, &aps), vec![index[t]], Vec::new()))
+                })
+                .collect()
+        })
+        .collect();
+    Hoa {
+        name: name.into(),
+        start: vec![vec![index[&ldba.initial]]],
+        aps,
+        acc_name: "Buchi".into(),
+        acceptance: "1 Inf(0)".into(),
+        properties: "trans-labels explicit-labels state-acc semi-deterministic",
+        states: states
+            .iter()
+            .map(|s| {
+                let acc = if accepting.contains(*s) {
+                    vec![0]
+                } else {
+                    Vec::new()
+                };
+                (s.to_string(), acc)
+            })
+            .collect(),
+        edges,
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::ltl::{
+        ba::to_ba, dpa::to_dpa, gba::to_gba, ldba::to_ldba, powerba::to_powerba,
+        safra::determinize, vwaa::to_vwaa, LtlImpl,
+    };
+    use crate::logic::{Formula, LogicImpl};
+    use petgraph::algo::tarjan_scc;
+    use petgraph::graph::{DiGraph, NodeIndex};
+
+    /// Letters of a word, each one holds the atomic propositions it contains
+    type Letters = &'static [&'static [&'static str]];
+
+    /// Ultimately periodic words, a prefix followed by a loop repeated forever
+    const WORDS: &[(Letters, Letters)] = &[
+        (&[], &[&[]]),
+        (&[], &[&["c"]]),
+        (&[&["c"]], &[&[]]),
+        (&[], &[&["d"]]),
+        (&[&["c"]], &[&["d"]]),
+        (&[&[]], &[&["c"], &[]]),
+        (&[&["c"], &["d"]], &[&[]]),
+        (&[&["d"]], &[&["c"]]),
+        (&[], &[&["c", "d"]]),
+        (&[&["d"]], &[&["c"], &["d"]]),
+    ];
+
+    /// Label, conjunction of targets and acceptance sets of an edge, including those of its source
+    type ParsedEdge = (String, Vec<usize>, BTreeSet<usize>);
+
+    /// Automaton read back from its HOA
+    struct Parsed {
+        states: usize,
+        start: Vec<usize>,
+        aps: Vec<String>,
+        sets: usize,
+        acceptance: String,
+        edges: Vec<Vec<ParsedEdge>>,
+    }
+
+    fn numbers(text: &str, separator: char) -> Vec<usize> {
+        text.split(separator)
+            .map(|n| n.trim().parse().unwrap())
+            .collect()
+    }
+
+    /// Acceptance sets in braces at the end of a line, if any
+    fn acc_sets_of(line: &str) -> (&str, BTreeSet<usize>) {
+        match line.split_once('{') {
+            Some((rest, sets)) => (
+                rest.trim(),
+                numbers(sets.trim_end_matches('}'), ' ')
+                    .into_iter()
+                    .collect(),
+            ),
+            None => (line.trim(), BTreeSet::new()),
+        }
+    }
+
+    fn parse(hoa: &str) -> Parsed {
+        let (header, body) = hoa.split_once("--BODY--\n").unwrap();
+        assert!(header.starts_with("HOA: v1\n"));
+        assert!(body.ends_with("--END--\n"));
+        let field = |name: &str| {
+            header
+                .lines()
+                .find_map(|l| l.strip_prefix(name))
+                .unwrap()
+                .trim()
+        };
+        let (ap_count, names) = field("AP:").split_once(' ').unwrap_or((field("AP:"), ""));
+        let aps: Vec<String> = names
+            .split_whitespace()
+            .map(|ap| ap.trim_matches('"').to_string())
+            .collect();
+        assert_eq!(aps.len(), ap_count.parse::<usize>().unwrap());
+        let (sets, acceptance) = field("Acceptance:").split_once(' ').unwrap();
+        let mut parsed = Parsed {
+            states: field("States:").parse().unwrap(),
+            start: numbers(field("Start:"), '&'),
+            aps,
+            sets: sets.parse().unwrap(),
+            acceptance: acceptance.to_string(),
+            edges: Vec::new(),
+        };
+        let mut state_sets = BTreeSet::new();
+        for line in body.lines().filter(|l| *l != "--END--") {
+            if let Some(state) = line.strip_prefix("State: ") {
+                let (state, sets) = acc_sets_of(state);
+                let number = state.split_once(' ').unwrap().0;
+                assert_eq!(number.parse::<usize>().unwrap(), parsed.edges.len());
+                state_sets = sets;
+                parsed.edges.push(Vec::new());
+            } else {
+                let (label, rest) = line.strip_prefix('[').unwrap().split_once("] ").unwrap();
+                let (targets, mut sets) = acc_sets_of(rest);
+                sets.extend(&state_sets);
+                let edge = (label.to_string(), numbers(targets, '&'), sets);
+                parsed.edges.last_mut().unwrap().push(edge);
+            }
+        }
+        parsed
+    }
+
+    /// Checks that the states, propositions and acceptance sets referenced are all declared
+    fn assert_well_formed(automaton: &Parsed) {
+        assert_eq!(automaton.edges.len(), automaton.states);
+        assert!(automaton.start.iter().all(|s| *s < automaton.states));
+        for (label, targets, sets) in automaton.edges.iter().flatten() {
+            assert!(targets.iter().all(|t| *t < automaton.states));
+            assert!(sets.iter().all(|s| *s < automaton.sets));
+            if label != "t" {
+                for literal in label.split(" & ") {
+                    let index: usize = literal.trim_start_matches('!').parse().unwrap();
+                    assert!(index < automaton.aps.len());
+                }
+            }
+        }
+    }
+
+    fn enabled(label: &str, aps: &[String], letter: &[&str]) -> bool {
+        label == "t"
+            || label.split(" & ").all(|literal| {
+                let (negated, index) = match literal.strip_prefix('!') {
+                    Some(index) => (true, index),
+                    None => (false, literal),
+                };
+                let ap = &aps[index.parse::<usize>().unwrap()];
+                letter.contains(&ap.as_str()) != negated
+            })
+    }
+
+    /// Evaluates the acceptance condition on the sets visited infinitely often
+    fn holds(condition: &mut std::iter::Peekable<std::str::Chars>, inf: &BTreeSet<usize>) -> bool {
+        fn atom(chars: &mut std::iter::Peekable<std::str::Chars>, inf: &BTreeSet<usize>) -> bool {
+            while chars.next_if_eq(&' ').is_some() {}
+            let value = match chars.next().unwrap() {
+                't' => true,
+                'f' => false,
+                '(' => {
+                    let value = holds(chars, inf);
+                    assert_eq!(chars.next(), Some(')'));
+                    value
+                }
+                first => {
+                    let name: String = std::iter::once(first)
+                        .chain(chars.by_ref().take_while(|c| *c != '('))
+                        .collect();
+                    let set: String = chars.by_ref().take_while(|c| *c != ')').collect();
+                    let visited = inf.contains(&set.parse().unwrap());
+                    match name.as_str() {
+                        "Inf" => visited,
+                        "Fin" => !visited,
+                        _ => panic!("unknown acceptance atom {}", name),
+                    }
+                }
+            };
+            while chars.next_if_eq(&' ').is_some() {}
+            value
+        }
+        let mut disjunction = false;
+        loop {
+            let mut conjunction = atom(condition, inf);
+            while condition.next_if_eq(&'&').is_some() {
+                conjunction &= atom(condition, inf);
+            }
+            disjunction |= conjunction;
+            if condition.next_if_eq(&'|').is_none() {
+                return disjunction;
+            }
+        }
+    }
+
+    /// Runs the automaton on the product with the word. As there is no universal branching and
+    /// the automaton is deterministic or its condition has no Fin, the word is accepted iff some
+    /// cycle of the product satisfies the condition with every set it visits
+    fn accepts(automaton: &Parsed, (prefix, period): (Letters, Letters)) -> bool {
+        let word: Vec<&[&str]> = prefix.iter().chain(period.iter()).copied().collect();
+        let next = |i: usize| {
+            if i + 1 == word.len() {
+                prefix.len()
+            } else {
+                i + 1
+            }
+        };
+        let mut product = DiGraph::<(usize, usize), BTreeSet<usize>>::new();
+        let mut nodes: HashMap<(usize, usize), NodeIndex> = HashMap::new();
+        let mut stack = vec![(automaton.start[0], 0)];
+        nodes.insert(stack[0], product.add_node(stack[0]));
+        while let Some((state, i)) = stack.pop() {
+            for (label, targets, sets) in &automaton.edges[state] {
+                if enabled(label, &automaton.aps, word[i]) {
+                    let target = (targets[0], next(i));
+                    let node = *nodes.entry(target).or_insert_with(|| {
+                        stack.push(target);
+                        product.add_node(target)
+                    });
+                    product.add_edge(nodes[&(state, i)], node, sets.clone());
+                }
+            }
+        }
+        tarjan_scc(&product).iter().any(|component| {
+            let inner: Vec<&BTreeSet<usize>> = product
+                .edge_indices()
+                .filter(|e| {
+                    let (source, target) = product.edge_endpoints(*e).unwrap();
+                    component.contains(&source) && component.contains(&target)
+                })
+                .map(|e| &product[e])
+                .collect();
+            let inf = inner.iter().copied().flatten().copied().collect();
+            !inner.is_empty() && holds(&mut automaton.acceptance.chars().peekable(), &inf)
+        })
+    }
+
+    /// HOA of every stage of the translation of the formula, except the alternating automaton
+    fn translate(formula: &str) -> (String, Vec<(&'static str, String)>) {
+        let Formula::Ltl(phi) = LtlImpl.parse(&format!("PHI = {}", formula)).unwrap() else {
+            unreachable!()
+        };
+        let alternating = to_vwaa(phi);
+        let vwaa_hoa = vwaa(&alternating, formula);
+        let generalized = to_gba(alternating);
+        let mut stages = vec![
+            ("gba", gba(&generalized, formula)),
+            ("ldba", ldba(&to_ldba(&generalized), formula)),
+        ];
+        let buchi = to_ba(generalized);
+        let rabin = determinize(to_powerba(&buchi));
+        stages.push(("ba", ba(&buchi, formula)));
+        stages.push(("dpa", dpa(&to_dpa(&rabin), formula)));
+        stages.push(("dra", dra(&rabin, formula)));
+        (vwaa_hoa, stages)
+    }
+
+    fn assert_language(formula: &str, satisfied: impl Fn(Letters, Letters) -> bool) {
+        let (vwaa_hoa, stages) = translate(formula);
+        assert_well_formed(&parse(&vwaa_hoa));
+        for (stage, hoa) in stages {
+            let automaton = parse(&hoa);
+            assert_well_formed(&automaton);
+            assert_eq!(automaton.start.len(), 1);
+            let deterministic = hoa.contains(" deterministic");
+            assert!(deterministic || !automaton.acceptance.contains("Fin"));
+            for (prefix, period) in WORDS {
+                assert_eq!(
+                    accepts(&automaton, (prefix, period)),
+                    satisfied(prefix, period),
+                    "{} of {} on {:?} {:?}",
+                    stage,
+                    formula,
+                    prefix,
+                    period
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn automata_read_back_from_hoa_accept_the_models_of_the_formula() {
+        let has = |letter: &[&str], ap| letter.contains(&ap);
+        assert_language("G(F(c))", |_, period| period.iter().any(|l| has(l, "c")));
+        assert_language("F(G(d))", |_, period| period.iter().all(|l| has(l, "d")));
+        assert_language("F((c) & (X d))", |prefix, period| {
+            let word: Vec<&[&str]> = [prefix, period, period].concat();
+            word.windows(2).any(|w| has(w[0], "c") && has(w[1], "d"))
+        });
+    }
+}
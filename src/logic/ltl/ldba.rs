@@ -0,0 +1,206 @@
+use super::{
+    common::{Alphabet, SimpleTransition},
+    gba::GBA,
+    mdpa::{Acceptance, Automaton},
+    PhiOp,
+};
+use crate::utils::common::powerset;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+/// Limit-deterministic Büchi automaton. The initial part is the subset construction of the GBA.
+/// Any of its transitions may also jump to a single state of the GBA, from which on all runs of
+/// the GBA are tracked deterministically by a breakpoint construction
+#[allow(clippy::upper_case_acronyms)]
+pub struct LDBA {
+    pub initial: String,
+    pub trans_f: HashMap<String, BTreeMap<Alphabet, Vec<String>>>,
+    /// Single Büchi pair: no state is forbidden and the accepting states of the breakpoint
+    /// construction have to be visited infinitely often
+    pub acc: Vec<(HashSet<String>, HashSet<String>)>,
+    /// Atomic propositions of the formula. Every state has transitions for each of their subsets
+    pub aps: BTreeSet<PhiOp>,
+}
+
+impl Automaton for LDBA {
+    fn initial(&self) -> &str {
+        &self.initial
+    }
+
+    fn successors(&self, state: &str, letter: &Alphabet) -> Vec<String> {
+        self.trans_f[state][&letter.restrict(&self.aps)].clone()
+    }
+
+    fn acceptance(&self) -> Acceptance<'_> {
+        Acceptance::Rabin(&self.acc)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum State {
+    /// States of the GBA the runs on the word read so far can be in
+    Initial(BTreeSet<String>),
+    /// States of the runs from the state jumped to, and the ones of the runs which took a
+    /// transition of the acceptance set `level` since the last breakpoint. When both are equal,
+    /// the next set is checked. Passing the last one makes the state accepting
+    Accepting {
+        tracked: BTreeSet<String>,
+        breakpoint: BTreeSet<String>,
+        level: usize,
+        accepting: bool,
+    },
+    /// All runs of the GBA died
+    Sink,
+}
+
+/// Builds the LDBA of the GBA over all sets of the propositions its transitions mention
+pub fn to_ldba(gba: &GBA) -> LDBA {
+    let aps: BTreeSet<PhiOp> = gba
+        .trans_f
+        .values()
+        .flatten()
+        .flat_map(|transition| transition.props.0.iter())
+        .map(|phi| match phi {
+            PhiOp::Not(not) => PhiOp::AP(not.ap.clone()),
+            _ => phi.clone(),
+        })
+        .collect();
+    let aps_vec: Vec<PhiOp> = aps.iter().cloned().collect();
+    let letters: Vec<Alphabet> = powerset(&aps_vec)
+        .into_iter()
+        .map(|set| Alphabet(set.into_iter().cloned().collect()))
+        .collect();
+
+    let mut acc_sets: Vec<(&PhiOp, &HashSet<(String, SimpleTransition)>)> =
+        gba.acc_transitions.iter().collect();
+    acc_sets.sort_by_key(|(phi, _)| *phi);
+    let acc_sets: Vec<&HashSet<(String, SimpleTransition)>> =
+        acc_sets.into_iter().map(|(_, set)| set).collect();
+
+    let initial = State::Initial(gba.initial.iter().cloned().collect());
+    let mut names: HashMap<State, String> = HashMap::from([(initial.clone(), "1".into())]);
+    let mut pop_queue = VecDeque::from([initial.clone()]);
+    let mut trans_f: HashMap<String, BTreeMap<Alphabet, Vec<String>>> = HashMap::new();
+    let mut accepting_states = HashSet::new();
+    while let Some(state) = pop_queue.pop_front() {
+        let name = names[&state].clone();
+        if let State::Accepting {
+            accepting: true, ..
+        } = state
+        {
+            accepting_states.insert(name.clone());
+        }
+        let mut transitions = BTreeMap::new();
+        for letter in &letters {
+            let targets = delta(gba, &acc_sets, &state, letter)
+                .into_iter()
+                .map(|target| {
+                    let next_name = names.len() + 1;
+                    names
+                        .entry(target.clone())
+                        .or_insert_with(|| {
+                            pop_queue.push_back(target);
+                            next_name.to_string()
+                        })
+                        .clone()
+                })
+                .collect();
+            transitions.insert(letter.clone(), targets);
+        }
+        trans_f.insert(name, transitions);
+    }
+
+    LDBA {
+        initial: names[&initial].clone(),
+        trans_f,
+        acc: vec![(HashSet::new(), accepting_states)],
+        aps,
+    }
+}
+
+fn delta(
+    gba: &GBA,
+    acc_sets: &[&HashSet<(String, SimpleTransition)>],
+    state: &State,
+    letter: &Alphabet,
+) -> Vec<State> {
+    match state {
+        State::Initial(states) => {
+            let successors = post(gba, states, letter, |_, _| true);
+            if successors.is_empty() {
+                return vec![State::Sink];
+            }
+            let jumps = successors.iter().map(|q| State::Accepting {
+                tracked: BTreeSet::from([q.clone()]),
+                breakpoint: BTreeSet::new(),
+                level: 0,
+                accepting: false,
+            });
+            let mut targets = vec![State::Initial(successors.clone())];
+            targets.extend(jumps);
+            targets
+        }
+        State::Accepting {
+            tracked,
+            breakpoint,
+            level,
+            ..
+        } => {
+            let tracked_successors = post(gba, tracked, letter, |_, _| true);
+            if tracked_successors.is_empty() {
+                return vec![State::Sink];
+            }
+            // Without acceptance sets every transition is accepting
+            let mut breakpoint_successors = post(gba, breakpoint, letter, |_, _| true);
+            breakpoint_successors.extend(post(gba, tracked, letter, |source, transition| {
+                acc_sets
+                    .get(*level)
+                    .is_none_or(|set| set.contains(&(source.to_string(), transition.clone())))
+            }));
+            if breakpoint_successors == tracked_successors {
+                let next_level = (level + 1) % acc_sets.len().max(1);
+                return vec![State::Accepting {
+                    tracked: tracked_successors,
+                    breakpoint: BTreeSet::new(),
+                    level: next_level,
+                    accepting: next_level == 0,
+                }];
+            }
+            vec![State::Accepting {
+                tracked: tracked_successors,
+                breakpoint: breakpoint_successors,
+                level: *level,
+                accepting: false,
+            }]
+        }
+        State::Sink => vec![State::Sink],
+    }
+}
+
+/// Targets of the transitions from the states which are enabled by the letter and satisfy the
+/// filter
+fn post(
+    gba: &GBA,
+    states: &BTreeSet<String>,
+    letter: &Alphabet,
+    filter: impl Fn(&str, &SimpleTransition) -> bool,
+) -> BTreeSet<String> {
+    states
+        .iter()
+        .flat_map(|state| {
+            gba.trans_f
+                .get(state)
+                .into_iter()
+                .flatten()
+                .filter(|transition| enabled(transition, letter) && filter(state, transition))
+                .map(|transition| transition.target.clone())
+        })
+        .collect()
+}
+
+fn enabled(transition: &SimpleTransition, letter: &Alphabet) -> bool {
+    transition.props.0.iter().all(|prop| match prop {
+        PhiOp::Not(not) => !letter.0.contains(&PhiOp::AP(not.ap.clone())),
+        PhiOp::AP(_) => letter.0.contains(prop),
+        _ => unreachable!(),
+    })
+}
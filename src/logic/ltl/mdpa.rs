@@ -1,20 +1,50 @@
-use super::{safra::DRA, PhiOp, AP};
+use super::{PhiOp, AP};
 use crate::{
     input_graph::{Node, MDP},
     logic::ltl::common::Alphabet,
+    logic::mec::maximal_end_components,
     mcsp::PctlInfo,
     utils::common::reverse_map,
 };
-use petgraph::{algo::kosaraju_scc, stable_graph::NodeIndex, visit::EdgeRef, Direction};
+use petgraph::{stable_graph::NodeIndex, visit::EdgeRef};
+use rayon::prelude::*;
 use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 
-pub fn cross_mdp(dra: DRA, pctl_info: &PctlInfo) -> (MDP<(NodeIndex, String)>, HashSet<NodeIndex>) {
-    let reversed_ap_map = reverse_map(&pctl_info.ap_map);
-    let reversed_ap_map = reversed_ap_map
+/// Component of the product in which a run can stay forever and satisfy a Rabin pair
+pub struct AcceptingComponent {
+    /// States and actions of the component
+    pub nodes: HashSet<NodeIndex>,
+    /// States of the component in the set of the pair which has to be visited infinitely often
+    pub targets: HashSet<NodeIndex>,
+}
+
+/// Automaton read along the runs of the MDP in the product
+pub trait Automaton: Sync {
+    fn initial(&self) -> &str;
+
+    /// Successors of the state after reading the letter. The scheduler of the product chooses
+    /// one if there are several
+    fn successors(&self, state: &str, letter: &Alphabet) -> Vec<String>;
+
+    fn acceptance(&self) -> Acceptance<'_>;
+}
+
+/// Condition on the states of the automaton a run visits infinitely often
+pub enum Acceptance<'a> {
+    /// Rabin pairs (L, K): a run is accepted if it visits L finitely and K infinitely often
+    Rabin(&'a [(HashSet<String>, HashSet<String>)]),
+    /// Priorities of the states: a run is accepted if the maximal priority it visits infinitely
+    /// often is even
+    Parity(&'a HashMap<String, usize>),
+}
+
+/// Set of atomic propositions holding in every state of the MDP. States without any are missing
+pub fn letters(pctl_info: &PctlInfo) -> HashMap<NodeIndex, Alphabet> {
+    reverse_map(&pctl_info.ap_map)
         .into_iter()
         .map(|(k, v)| {
             (
-                k,
+                *k,
                 Alphabet(
                     v.into_iter()
                         .map(|ap| PhiOp::AP(AP { value: ap.into() }))
@@ -22,162 +52,145 @@ pub fn cross_mdp(dra: DRA, pctl_info: &PctlInfo) -> (MDP<(NodeIndex, String)>, H
                 ),
             )
         })
-        .collect::<HashMap<_, _>>();
+        .collect()
+}
+
+pub fn cross_mdp<A: Automaton>(
+    automaton: &A,
+    pctl_info: &PctlInfo,
+) -> (MDP<(NodeIndex, String)>, Vec<AcceptingComponent>) {
+    let letters = letters(pctl_info);
+    let empty = Alphabet::full();
+    let initial = automaton.initial().to_string();
     let mdp_graph = &pctl_info.reach_graph;
     let mut cross_graph: MDP<(NodeIndex, String)> = MDP::new();
+    let mut node_indices: HashMap<(NodeIndex, String), NodeIndex> = HashMap::new();
     let mut pop_queue = VecDeque::new();
-    pop_queue.push_back((pctl_info.initial_marking, dra.initial.clone()));
-    while let Some((mdp_node, dra_state)) = pop_queue.pop_front() {
-        let new_node_index = find_or_create_node(&mut cross_graph, mdp_node, &dra_state);
-        let opt_props = reversed_ap_map.get(&mdp_node);
+    let mut queued: HashSet<(NodeIndex, String)> = HashSet::new();
+    // Every state of the MDP starts a run of the automaton, so that the probabilities of all of
+    // them can be read off the product and do not depend on which automaton states the runs from
+    // the initial state happen to pass. The initial state comes first
+    let starts = std::iter::once(pctl_info.initial_marking).chain(
+        mdp_graph
+            .node_indices()
+            .filter(|n| mdp_graph[*n].is_state() && *n != pctl_info.initial_marking),
+    );
+    for node in starts {
+        pop_queue.push_back((node, initial.clone()));
+        queued.insert((node, initial.clone()));
+    }
+    while !pop_queue.is_empty() {
+        // The transitions of the automaton are computed for a whole level of the search in
+        // parallel. Adding the nodes in the order of the queue afterwards keeps the product
+        // independent of the number of threads
+        let level: Vec<(NodeIndex, String)> = pop_queue.drain(..).collect();
+        let target_automaton_states: Vec<Vec<String>> = level
+            .par_iter()
+            .map(|(mdp_node, state)| {
+                automaton.successors(state, letters.get(mdp_node).unwrap_or(&empty))
+            })
+            .collect();
+        for ((mdp_node, state), target_states) in level.into_iter().zip(target_automaton_states) {
+            queued.remove(&(mdp_node, state.clone()));
+            let new_node_index =
+                find_or_create_node(&mut cross_graph, &mut node_indices, mdp_node, &state);
 
-        // For all edges between State --> Action
-        for edge in mdp_graph.edges(mdp_node) {
-            let action = edge.target();
-            if let Node::Action(action_weight) = mdp_graph.node_weight(action).unwrap() {
-                let new_action_node_index =
-                    cross_graph.add_node(Node::Action(action_weight.clone()));
-                cross_graph.add_edge(new_node_index, new_action_node_index, *edge.weight());
+            // Every action of the MDP is paired with every choice of the automaton
+            for edge in mdp_graph.edges(mdp_node) {
+                let action = edge.target();
+                let Node::Action(action_weight) = mdp_graph.node_weight(action).unwrap() else {
+                    unreachable!();
+                };
+                for target_state in &target_states {
+                    let new_action_node_index =
+                        cross_graph.add_node(Node::Action(action_weight.clone()));
+                    cross_graph.add_edge(new_node_index, new_action_node_index, *edge.weight());
 
-                // For all edges between Action --> State
-                for edge in mdp_graph.edges(action) {
-                    let target_state = edge.target();
-                    let target_dra_state = prop_to_state(&dra_state, opt_props, &dra);
-                    let new_target_node =
-                        find_or_create_node(&mut cross_graph, target_state, &target_dra_state);
-                    if !(pop_queue.contains(&(target_state, target_dra_state.clone()))
-                        || has_edges(&(target_state, target_dra_state.clone()), &cross_graph))
-                    {
-                        pop_queue.push_back((target_state, target_dra_state));
+                    // For all edges between Action --> State
+                    for edge in mdp_graph.edges(action) {
+                        let target = (edge.target(), target_state.clone());
+                        let new_target_node = find_or_create_node(
+                            &mut cross_graph,
+                            &mut node_indices,
+                            target.0,
+                            &target.1,
+                        );
+                        let expanded = cross_graph.edges(new_target_node).next().is_some();
+                        if !(queued.contains(&target) || expanded) {
+                            queued.insert(target.clone());
+                            pop_queue.push_back(target);
+                        }
+                        cross_graph.add_edge(
+                            new_action_node_index,
+                            new_target_node,
+                            *edge.weight(),
+                        );
                     }
-                    cross_graph.add_edge(new_action_node_index, new_target_node, *edge.weight());
                 }
-            } else {
-                unreachable!();
             }
         }
     }
 
-    let aec = aec(&dra.acc, &cross_graph);
+    let aec = aec(automaton.acceptance(), &cross_graph);
     (cross_graph, aec)
 }
 
-fn aec(
-    acc: &[(HashSet<String>, HashSet<String>)],
-    cross_graph: &MDP<(NodeIndex, String)>,
-) -> HashSet<NodeIndex> {
-    // let scc = kosaraju_scc(&cross_graph);
-    let mut aec: HashSet<NodeIndex> = HashSet::new();
-    for (l, k) in acc {
-        let mut graph = cross_graph.clone();
-        for l_state in l {
-            graph.retain_nodes(|g, ni| match &g[ni] {
-                Node::State(s) => s.1 != *l_state,
-                _ => true,
-            });
+/// Maximal end components of the product in which a run can satisfy the acceptance condition
+fn aec(acceptance: Acceptance, cross_graph: &MDP<(NodeIndex, String)>) -> Vec<AcceptingComponent> {
+    match acceptance {
+        // For a pair (L, K), the components avoid the states in L and contain one in K
+        Acceptance::Rabin(acc) => acc
+            .iter()
+            .flat_map(|(l, k)| components(cross_graph, |s| !l.contains(s), |s| k.contains(s)))
+            .collect(),
+        // For an even priority, the components avoid higher priorities and contain the priority
+        Acceptance::Parity(priority) => {
+            let even: BTreeSet<usize> =
+                priority.values().filter(|p| *p % 2 == 0).copied().collect();
+            even.into_iter()
+                .rev()
+                .flat_map(|p| components(cross_graph, |s| priority[s] <= p, |s| priority[s] == p))
+                .collect()
         }
-        iterative_remove(&mut graph);
-        let scc = kosaraju_scc(&graph);
-        scc.iter()
-            .filter(|c| {
-                c.len() > 1
-                    && k.iter().any(|ke| {
-                        c.iter().any(|ce| match &graph[*ce] {
-                            Node::State(s) => s.1 == *ke,
-                            Node::Action(_) => false,
-                        })
-                    })
-            })
-            .flatten()
-            .for_each(|cs| {
-                aec.insert(*cs);
-            });
     }
-    aec
 }
 
-fn iterative_remove(graph: &mut MDP<(NodeIndex, String)>) {
-    loop {
-        let mut remove_list: Vec<NodeIndex> = Vec::with_capacity(graph.node_count());
-        graph
-            .node_indices()
-            .filter(|ni| match &graph[*ni] {
-                Node::State(_) => false,
-                Node::Action(_) => {
-                    let incoming_edges_count =
-                        graph.edges_directed(*ni, Direction::Incoming).count();
-                    let outgoing_edges = graph.edges_directed(*ni, Direction::Outgoing);
-                    let sum_weights = outgoing_edges
-                        .clone()
-                        .map(|e| e.weight().clone())
-                        .sum::<f64>()
-                        .clone()
-                        != 1.0;
-                    return incoming_edges_count == 0
-                        || outgoing_edges.clone().count() == 0
-                        || sum_weights;
-                }
-            })
-            .for_each(|ni| remove_list.push(ni));
-        if remove_list.is_empty() {
-            break;
-        } else {
-            for ni in &remove_list {
-                let _ = graph.remove_node(*ni);
-            }
-        }
-
-        // Remove all states with
-        graph
-            .node_indices()
-            .filter(|ni| match graph[*ni] {
-                Node::Action(_) => false,
-                Node::State(_) => graph.edges_directed(*ni, Direction::Outgoing).count() == 0,
+/// Maximal end components of the product states whose automaton state is allowed, which
+/// contain one with a target state. A run can stay in such a component forever and visit the
+/// target states infinitely often
+fn components(
+    cross_graph: &MDP<(NodeIndex, String)>,
+    allowed: impl Fn(&str) -> bool,
+    target: impl Fn(&str) -> bool,
+) -> Vec<AcceptingComponent> {
+    let states: HashSet<NodeIndex> = cross_graph
+        .node_indices()
+        .filter(|n| matches!(&cross_graph[*n], Node::State(s) if allowed(&s.1)))
+        .collect();
+    maximal_end_components(cross_graph, &states)
+        .into_iter()
+        .filter_map(|mec| {
+            let targets: HashSet<NodeIndex> = mec
+                .states
+                .iter()
+                .filter(|s| matches!(&cross_graph[**s], Node::State(s) if target(&s.1)))
+                .copied()
+                .collect();
+            (!targets.is_empty()).then(|| AcceptingComponent {
+                nodes: mec.states.into_iter().chain(mec.actions).collect(),
+                targets,
             })
-            .for_each(|ni| remove_list.push(ni));
-        if remove_list.is_empty() {
-            break;
-        } else {
-            for ni in remove_list {
-                let _ = graph.remove_node(ni);
-            }
-        }
-    }
-}
-
-fn prop_to_state(src_state: &String, opt_alphabet: Option<&Alphabet>, dra: &DRA) -> String {
-    let full_alph = Alphabet::full();
-    dra.delta(
-        src_state,
-        match opt_alphabet {
-            Some(alph) => alph,
-            None => &full_alph,
-        },
-    )
+        })
+        .collect()
 }
 
 fn find_or_create_node(
     cross_graph: &mut MDP<(NodeIndex, String)>,
+    node_indices: &mut HashMap<(NodeIndex, String), NodeIndex>,
     mdp_node: NodeIndex,
-    dra_state: &String,
+    dra_state: &str,
 ) -> NodeIndex {
-    match cross_graph
-        .node_indices()
-        .find(|i| cross_graph[*i] == Node::State((mdp_node, dra_state.into())))
-    {
-        Some(index) => index,
-        None => cross_graph.add_node(Node::State((mdp_node, dra_state.into()))),
-    }
-}
-
-fn has_edges(node: &(NodeIndex, String), graph: &MDP<(NodeIndex, String)>) -> bool {
-    graph
-        .edges(
-            graph
-                .node_indices()
-                .find(|&i| graph[i] == Node::State(node.clone()))
-                .unwrap(),
-        )
-        .count()
-        > 0
+    *node_indices
+        .entry((mdp_node, dra_state.to_string()))
+        .or_insert_with(|| cross_graph.add_node(Node::State((mdp_node, dra_state.to_string()))))
 }
@@ -17,11 +17,12 @@ pub struct PowerBA {
 pub fn to_powerba(ba: &BA) -> PowerBA {
     let mut phis: BTreeSet<PhiOp> = BTreeSet::new();
     for symbol in &ba.symbols {
+        // Propositions only occurring negated still split the letters
         for phi in &symbol.0 {
-            if matches!(phi, PhiOp::Not(_)) {
-                continue;
-            }
-            phis.insert(phi.clone());
+            match phi {
+                PhiOp::Not(not) => phis.insert(PhiOp::AP(not.ap.clone())),
+                _ => phis.insert(phi.clone()),
+            };
         }
     }
     let phis_vec: Vec<PhiOp> = phis.into_iter().collect();
@@ -52,7 +53,7 @@ pub fn to_powerba(ba: &BA) -> PowerBA {
         }
         new_transition_map.insert(state.into(), new_transitions);
     }
-    let symbols: HashSet<Alphabet> = power_phis.into();
+    let symbols: HashSet<Alphabet> = power_phis;
 
     PowerBA {
         initials: ba.initials.clone(),
@@ -1,6 +1,8 @@
 use super::{
     common::{get_rename_map, Alphabet, SimpleTransition},
+    mdpa::{Acceptance, Automaton},
     powerba::PowerBA,
+    PhiOp,
 };
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
@@ -15,15 +17,29 @@ pub struct DRA {
     pub initial: String,
     pub trans_f: HashMap<String, BTreeMap<Alphabet, String>>,
     pub acc: Vec<(HashSet<String>, HashSet<String>)>,
+    /// Atomic propositions of the formula. Every state has a transition for each of their subsets
+    pub aps: BTreeSet<PhiOp>,
 }
 
 impl DRA {
-    pub fn delta(&self, state: &String, alphabet: &Alphabet) -> String {
-        let transition_map = self.trans_f.get(state).unwrap();
-        match transition_map.get(alphabet) {
-            Some(state) => state.clone(),
-            None => transition_map.get(&Alphabet::full()).unwrap().clone(),
-        }
+    /// Target of the transition on the letter without the propositions the formula does not
+    /// mention
+    pub fn delta(&self, state: &str, alphabet: &Alphabet) -> String {
+        self.trans_f[state][&alphabet.restrict(&self.aps)].clone()
+    }
+}
+
+impl Automaton for DRA {
+    fn initial(&self) -> &str {
+        &self.initial
+    }
+
+    fn successors(&self, state: &str, letter: &Alphabet) -> Vec<String> {
+        vec![self.delta(state, letter)]
+    }
+
+    fn acceptance(&self) -> Acceptance<'_> {
+        Acceptance::Rabin(&self.acc)
     }
 }
 
@@ -70,20 +86,28 @@ impl SafraNode {
         self.children
             .iter()
             .map(|c| c.get_max_id())
-            .max()
-            .unwrap_or(self.index)
+            .fold(self.index, usize::max)
+    }
+
+    /// Adds the ids of the node and all its descendants
+    fn ids(&self, ids: &mut BTreeSet<usize>) {
+        ids.insert(self.index);
+        for child in &self.children {
+            child.ids(ids);
+        }
     }
 
     fn remove_symbol(&mut self, rem_labels: &HashSet<&String>) {
         self.labels.retain(|l| !rem_labels.contains(l));
+        for child in &mut self.children {
+            child.remove_symbol(rem_labels);
+        }
     }
 
+    /// Removes the states of older siblings from the younger ones on every level of the tree
     fn merge_horizontal(&mut self) {
         let length = self.children.len();
-        if length < 2 {
-            return;
-        }
-        for i in 0..length - 1 {
+        for i in 0..length.saturating_sub(1) {
             for j in (i + 1)..length {
                 let older_child = self.children.get(i).unwrap().clone();
                 let younger_child = self.children.get_mut(j).unwrap();
@@ -93,13 +117,11 @@ impl SafraNode {
                     .intersection(&younger_labels)
                     .collect::<HashSet<_>>();
 
-                // Delete the labels of the younger child and of his children
+                // Delete the labels of the younger child and of all its descendants
                 younger_child.remove_symbol(&intersection);
-                for grand_children in &mut younger_child.children {
-                    grand_children.remove_symbol(&intersection);
-                }
             }
         }
+        self.children.iter_mut().for_each(|c| c.merge_horizontal());
     }
 
     fn merge_vertical(&mut self) {
@@ -126,18 +148,24 @@ impl SafraNode {
         }
     }
 
-    fn branch_finals(&mut self, acc: &BTreeSet<String>) {
+    /// Gives every node with final states a new youngest child holding them. The children take
+    /// the smallest ids not in `used`, so the ids of removed nodes are reused and the number of
+    /// trees stays finite
+    fn branch_finals(&mut self, acc: &BTreeSet<String>, used: &mut BTreeSet<usize>) {
         for child in &mut self.children {
-            child.branch_finals(acc);
+            child.branch_finals(acc, used);
         }
         let finals = self
             .labels
             .intersection(acc)
             .cloned()
             .collect::<BTreeSet<_>>();
-        let new_id = self.get_max_id() + 1;
-        let new_node = SafraNode::with_labels(finals, new_id);
-        self.children.push(new_node);
+        if finals.is_empty() {
+            return;
+        }
+        let new_id = (NODE_START_INDEX..).find(|id| !used.contains(id)).unwrap();
+        used.insert(new_id);
+        self.children.push(SafraNode::with_labels(finals, new_id));
     }
 
     fn powerset(&mut self, trans_f: &TransitionFunction, symbol: &Alphabet) {
@@ -202,7 +230,9 @@ impl SafraTree {
         // Step 1 and 2 are independent from the transition
         let mut new_tree = self.clone();
         new_tree.root.remove_mark();
-        new_tree.root.branch_finals(acc);
+        let mut used = BTreeSet::new();
+        new_tree.root.ids(&mut used);
+        new_tree.root.branch_finals(acc, &mut used);
 
         let mut succ_trees = Vec::new();
 
@@ -224,20 +254,26 @@ impl SafraTree {
 }
 
 pub fn determinize(ba: PowerBA) -> DRA {
+    let aps = ba
+        .symbols
+        .iter()
+        .flat_map(|s| s.0.iter().cloned())
+        .collect();
     let mut trans_f = HashMap::new();
     let mut pop_queue: VecDeque<SafraTree> = VecDeque::new();
     let initial_tree = SafraTree::with_root(ba.initials);
+    let mut seen = HashSet::from([initial_tree.clone()]);
     pop_queue.push_back(initial_tree.clone());
     while let Some(safra_tree) = pop_queue.pop_front() {
         let succ = safra_tree
             .clone()
             .succ_tree(&ba.transitions, &ba.finals, &ba.symbols);
-        trans_f.insert(safra_tree.clone(), succ.clone());
-        succ.into_iter().for_each(|t| {
-            if !(pop_queue.contains(&t.target) || trans_f.contains_key(&t.target)) {
-                pop_queue.push_back(t.target);
+        for transition in &succ {
+            if seen.insert(transition.target.clone()) {
+                pop_queue.push_back(transition.target.clone());
             }
-        });
+        }
+        trans_f.insert(safra_tree, succ);
     }
 
     // Rename all safra trees to simple state names
@@ -281,5 +317,6 @@ pub fn determinize(ba: PowerBA) -> DRA {
         initial: renamed_initial,
         trans_f: renamed_trans_f,
         acc,
+        aps,
     }
 }
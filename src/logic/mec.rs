@@ -0,0 +1,180 @@
+use crate::input_graph::MDP;
+use petgraph::algo::kosaraju_scc;
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Outgoing;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Set of states together with actions of these states which never leave the set and under
+/// which every state of the set can reach every other one
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndComponent {
+    pub states: BTreeSet<NodeIndex>,
+    pub actions: BTreeSet<NodeIndex>,
+}
+
+impl EndComponent {
+    /// Actions of the state which belong to the end component
+    pub fn actions_of<'a, T>(
+        &'a self,
+        graph: &'a MDP<T>,
+        state: NodeIndex,
+    ) -> impl Iterator<Item = NodeIndex> + 'a {
+        graph
+            .neighbors_directed(state, Outgoing)
+            .filter(|action| self.actions.contains(action))
+    }
+}
+
+/// Maximal end components of the MDP restricted to the given states, ordered by their smallest
+/// state. Starting with the actions which never leave the states, the strongly connected
+/// components of the states are refined by removing actions which can leave the component of
+/// their state until no action is removed anymore. Single states with a self-loop are end
+/// components as well. Edges whose probability was rounded to 0 are ignored
+pub fn maximal_end_components<T>(graph: &MDP<T>, states: &HashSet<NodeIndex>) -> Vec<EndComponent> {
+    let mut actions: HashMap<NodeIndex, Vec<NodeIndex>> = states
+        .iter()
+        .map(|state| {
+            let staying = graph
+                .neighbors_directed(*state, Outgoing)
+                .filter(|action| successors(graph, *action).all(|t| states.contains(&t)))
+                .collect();
+            (*state, staying)
+        })
+        .collect();
+    actions.retain(|_, a| !a.is_empty());
+    loop {
+        let component = components(graph, &actions);
+        let mut changed = false;
+        for (state, state_actions) in actions.iter_mut() {
+            let before = state_actions.len();
+            // Actions which can leave the strongly connected component of the state
+            state_actions.retain(|action| {
+                successors(graph, *action).all(|t| component.get(&t) == component.get(state))
+            });
+            changed |= state_actions.len() != before;
+        }
+        actions.retain(|_, a| !a.is_empty());
+        if !changed {
+            let mut end_components: HashMap<usize, EndComponent> = HashMap::new();
+            for (state, state_actions) in actions {
+                let end_component =
+                    end_components
+                        .entry(component[&state])
+                        .or_insert_with(|| EndComponent {
+                            states: BTreeSet::new(),
+                            actions: BTreeSet::new(),
+                        });
+                end_component.states.insert(state);
+                end_component.actions.extend(state_actions);
+            }
+            let mut end_components: Vec<EndComponent> = end_components.into_values().collect();
+            end_components.sort_by_key(|ec| *ec.states.first().unwrap());
+            return end_components;
+        }
+    }
+}
+
+/// Index of the strongly connected component of every state using only the given actions
+fn components<T>(
+    graph: &MDP<T>,
+    actions: &HashMap<NodeIndex, Vec<NodeIndex>>,
+) -> HashMap<NodeIndex, usize> {
+    let mut state_graph: DiGraph<NodeIndex, ()> = DiGraph::new();
+    let indices: HashMap<NodeIndex, NodeIndex> = actions
+        .keys()
+        .map(|state| (*state, state_graph.add_node(*state)))
+        .collect();
+    for (state, state_actions) in actions {
+        for action in state_actions {
+            for target in successors(graph, *action) {
+                if let Some(target_index) = indices.get(&target) {
+                    state_graph.add_edge(indices[state], *target_index, ());
+                }
+            }
+        }
+    }
+    kosaraju_scc(&state_graph)
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, scc)| {
+            scc.into_iter()
+                .map(|n| (state_graph[n], i))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Targets of the edges of the action with a positive probability
+fn successors<T>(graph: &MDP<T>, action: NodeIndex) -> impl Iterator<Item = NodeIndex> + '_ {
+    graph
+        .edges_directed(action, Outgoing)
+        .filter(|e| *e.weight() > 0.0)
+        .map(|e| e.target())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_graph::Node;
+
+    /// s0 and s1 can move back and forth with a and c, b leaves to s3, which can only continue
+    /// to s1 or the absorbing s2. s4 leaks to s2. The edge of i to s0 was rounded to 0
+    fn mdp() -> (MDP<usize>, Vec<NodeIndex>, HashMap<&'static str, NodeIndex>) {
+        let mut graph = MDP::default();
+        let states: Vec<NodeIndex> = (0..5).map(|s| graph.add_node(Node::State(s))).collect();
+        let mut actions = HashMap::new();
+        let mut add_action = |name: &'static str, source: usize, targets: &[(usize, f64)]| {
+            let action = graph.add_node(Node::Action(name.into()));
+            graph.add_edge(states[source], action, 1.0);
+            for (target, p) in targets {
+                graph.add_edge(action, states[*target], *p);
+            }
+            actions.insert(name, action);
+        };
+        add_action("a", 0, &[(1, 1.0)]);
+        add_action("b", 0, &[(0, 0.5), (3, 0.5)]);
+        add_action("c", 1, &[(0, 1.0)]);
+        add_action("d", 1, &[(2, 1.0)]);
+        add_action("e", 2, &[(2, 1.0)]);
+        add_action("i", 2, &[(2, 1.0), (0, 0.0)]);
+        add_action("f", 3, &[(1, 0.5), (2, 0.5)]);
+        add_action("g", 4, &[(4, 0.5), (2, 0.5)]);
+        add_action("h", 4, &[(2, 1.0)]);
+        (graph, states, actions)
+    }
+
+    #[test]
+    fn finds_the_maximal_end_components() {
+        let (graph, states, actions) = mdp();
+        let end_components = maximal_end_components(&graph, &states.iter().copied().collect());
+        assert_eq!(
+            end_components,
+            vec![
+                EndComponent {
+                    states: BTreeSet::from([states[0], states[1]]),
+                    actions: BTreeSet::from([actions["a"], actions["c"]]),
+                },
+                EndComponent {
+                    states: BTreeSet::from([states[2]]),
+                    actions: BTreeSet::from([actions["e"], actions["i"]]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn only_uses_the_given_states() {
+        let (graph, states, actions) = mdp();
+        let subset = HashSet::from([states[0], states[2], states[3], states[4]]);
+        let end_components = maximal_end_components(&graph, &subset);
+        assert_eq!(end_components.len(), 1);
+        assert_eq!(end_components[0].states, BTreeSet::from([states[2]]));
+        assert_eq!(
+            end_components[0]
+                .actions_of(&graph, states[2])
+                .collect::<BTreeSet<_>>(),
+            BTreeSet::from([actions["e"], actions["i"]])
+        );
+    }
+}
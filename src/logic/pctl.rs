@@ -1,17 +1,39 @@
+use crate::error::McspError;
 use crate::input_graph::{Node, MDP};
+use crate::logic::counterexample::{
+    most_probable_paths, reaching, successors, transition_name, Run,
+};
+use crate::logic::mec::maximal_end_components;
+use crate::logic::sparse::SparseMdp;
 use crate::logic::{Formula, LogicImpl};
-use crate::mcsp::PctlInfo;
-use crate::utils::common::Comp;
-use log::{error, info};
+use crate::mcsp::{
+    CheckResult, Counterexample, GraphSize, Interval, PctlInfo, Probability, Solver, Timings,
+};
+use crate::utils::common::{Comp, ParseOrError};
+use crate::utils::rational::Rational;
+use log::{info, warn};
 use pest::iterators::Pair;
 use pest::Parser;
 use pest_derive::Parser;
-use petgraph::stable_graph::{Edges, NodeIndex};
+use petgraph::algo::kosaraju_scc;
+use petgraph::graph::DiGraph;
+use petgraph::stable_graph::NodeIndex;
 use petgraph::visit::EdgeRef;
 use petgraph::{Incoming, Outgoing};
-use std::collections::{BTreeMap, HashMap, HashSet};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::Display;
-use std::process::exit;
+
+mod exact;
+mod long_run;
+mod reward;
+mod scheduler;
+mod solver;
+
+pub use self::exact::ExactWeights;
+pub use self::long_run::{long_run_averages, LongRun};
+pub use self::reward::{CumulativeReward, InstantaneousReward, ReachReward, Reward, RewardPhi};
+pub use self::scheduler::{decisions, reachability_scheduler, staying_scheduler, Scheduler};
 
 #[derive(Parser)]
 #[grammar = "logic/pctl.pest"]
@@ -20,20 +42,20 @@ struct PctlPestParser;
 pub struct PctlImpl;
 
 impl PctlImpl {
-    fn parse_state(pair: &Pair<Rule>) -> Box<dyn StatePhi> {
+    fn parse_state(pair: &Pair<Rule>) -> Result<Box<dyn StatePhi>, McspError> {
         let inner_rules = pair.clone().into_inner().collect::<Vec<Pair<Rule>>>();
-        match pair.as_rule() {
+        let state_phi: Box<dyn StatePhi> = match pair.as_rule() {
             Rule::Phi_and => {
-                let left_rule = Self::parse_state(inner_rules.first().unwrap());
-                let right_rule = Self::parse_state(inner_rules.get(1).unwrap());
+                let left_rule = Self::parse_state(inner_rules.first().unwrap())?;
+                let right_rule = Self::parse_state(inner_rules.get(1).unwrap())?;
                 Box::new(AndPhi {
                     left_phi: left_rule,
                     right_phi: right_rule,
                 })
             }
             Rule::Phi_or => {
-                let left_rule = Self::parse_state(inner_rules.first().unwrap());
-                let right_rule = Self::parse_state(inner_rules.get(1).unwrap());
+                let left_rule = Self::parse_state(inner_rules.first().unwrap())?;
+                let right_rule = Self::parse_state(inner_rules.get(1).unwrap())?;
                 Box::new(NotPhi {
                     phi: Box::new(AndPhi {
                         left_phi: Box::new(NotPhi { phi: left_rule }),
@@ -42,7 +64,7 @@ impl PctlImpl {
                 })
             }
             Rule::Phi_not => {
-                let inner_phi = Self::parse_state(inner_rules.first().unwrap());
+                let inner_phi = Self::parse_state(inner_rules.first().unwrap())?;
                 Box::new(NotPhi { phi: inner_phi })
             }
             Rule::ap => Box::new(AP {
@@ -53,152 +75,511 @@ impl PctlImpl {
                 phi: Box::new(True {}),
             }),
             Rule::prob => {
-                let inner_phi = Self::parse_path(inner_rules.first().unwrap());
-                let comp_char: &str = inner_rules.get(1).unwrap().as_str();
-                let comp: Comp = match comp_char {
-                    "<" => Comp::Less,
-                    "<=" => Comp::Leq,
-                    ">" => Comp::Greater,
-                    ">=" => Comp::Geq,
-                    _ => {
-                        error!(
-                        "Syntax error! \"{}\" is not a valid comparison character. Terminating...",
-                        comp_char
-                    );
-                        exit(0);
-                    }
-                };
-                let prob_str: &str = inner_rules.get(2).unwrap().as_str();
-                match prob_str.parse::<f64>() {
-                    Ok(prob) => Box::new(Prob {
-                        phi: inner_phi,
-                        comp,
-                        probability: prob,
-                    }),
-                    Err(_) => {
-                        error!("\"{}\" is not a valid float! Terminating...", prob_str);
-                        exit(0);
-                    }
-                }
+                let inner_phi = Self::parse_path(inner_rules.first().unwrap())?;
+                let comp = Self::parse_comp(inner_rules.get(1).unwrap());
+                let probability = inner_rules
+                    .get(2)
+                    .unwrap()
+                    .as_str()
+                    .parse_or_error("float")?;
+                Box::new(Prob {
+                    phi: inner_phi,
+                    comp,
+                    probability,
+                })
             }
             Rule::prob_alw => {
-                let inner_state_phi = Self::parse_state(inner_rules.first().unwrap());
-                let comp_char: &str = inner_rules.get(1).unwrap().as_str();
-                let flipped_comp: Comp = match comp_char {
-                    "<" => Comp::Greater,
-                    "<=" => Comp::Geq,
-                    ">" => Comp::Less,
-                    ">=" => Comp::Leq,
-                    _ => {
-                        error!(
-                        "Syntax error! \"{}\" is not a valid comparison character. Terminating...",
-                        comp_char
-                    );
-                        exit(0);
-                    }
-                };
-                let prob_str: &str = inner_rules.get(2).unwrap().as_str();
-                match prob_str.parse::<f64>() {
-                    Ok(prob) => Box::new(Prob {
-                        phi: Box::new(Until {
-                            prev: Box::new(True {}),
-                            until: Box::new(NotPhi {
-                                phi: inner_state_phi,
-                            }),
-                        }),
-                        comp: flipped_comp,
-                        probability: 1.0 - prob,
-                    }),
-                    Err(_) => {
-                        error!("\"{}\" is not a valid float! Terminating...", prob_str);
-                        exit(0);
-                    }
-                }
+                let inner_state_phi = Self::parse_state(inner_rules.first().unwrap())?;
+                let comp = Self::parse_comp(inner_rules.get(1).unwrap());
+                let probability: f64 = inner_rules
+                    .get(2)
+                    .unwrap()
+                    .as_str()
+                    .parse_or_error("float")?;
+                Box::new(Prob {
+                    phi: Box::new(Globally::new(inner_state_phi)),
+                    comp,
+                    probability,
+                })
+            }
+            Rule::reward => {
+                let inner_phi = Self::parse_reward(inner_rules.first().unwrap())?;
+                let comp = Self::parse_comp(inner_rules.get(1).unwrap());
+                let bound = inner_rules
+                    .get(2)
+                    .unwrap()
+                    .as_str()
+                    .parse_or_error("number")?;
+                Box::new(Reward {
+                    phi: inner_phi,
+                    comp,
+                    bound,
+                })
+            }
+            Rule::long_run => {
+                let inner_phi = Self::parse_state(inner_rules.first().unwrap())?;
+                let comp = Self::parse_comp(inner_rules.get(1).unwrap());
+                let probability = inner_rules
+                    .get(2)
+                    .unwrap()
+                    .as_str()
+                    .parse_or_error("float")?;
+                Box::new(LongRun {
+                    phi: inner_phi,
+                    comp,
+                    probability,
+                })
             }
             _ => panic!("Rule is invalid or should have been processed by parent!"),
+        };
+        Ok(state_phi)
+    }
+
+    fn parse_comp(pair: &Pair<Rule>) -> Comp {
+        match pair.as_str() {
+            "<" => Comp::Less,
+            "<=" => Comp::Leq,
+            ">" => Comp::Greater,
+            ">=" => Comp::Geq,
+            _ => unreachable!(),
         }
     }
 
-    fn parse_path(pair: &Pair<Rule>) -> Box<dyn PathPhi> {
+    fn parse_reward(pair: &Pair<Rule>) -> Result<Box<dyn RewardPhi>, McspError> {
         let inner_rules = pair.clone().into_inner().collect::<Vec<Pair<Rule>>>();
-        match pair.as_rule() {
+        let reward_phi: Box<dyn RewardPhi> = match pair.as_rule() {
+            Rule::reward_reach => {
+                let goal = Self::parse_state(inner_rules.first().unwrap())?;
+                Box::new(ReachReward { goal })
+            }
+            Rule::reward_cumulative => {
+                let steps = inner_rules
+                    .first()
+                    .unwrap()
+                    .as_str()
+                    .parse_or_error("integer")?;
+                Box::new(CumulativeReward { steps })
+            }
+            Rule::reward_instantaneous => {
+                let steps = inner_rules
+                    .first()
+                    .unwrap()
+                    .as_str()
+                    .parse_or_error("integer")?;
+                Box::new(InstantaneousReward { steps })
+            }
+            _ => unreachable!(),
+        };
+        Ok(reward_phi)
+    }
+
+    fn parse_path(pair: &Pair<Rule>) -> Result<Box<dyn PathPhi>, McspError> {
+        let inner_rules = pair.clone().into_inner().collect::<Vec<Pair<Rule>>>();
+        let path_phi: Box<dyn PathPhi> = match pair.as_rule() {
             Rule::phi_next => {
-                let inner_phi = Self::parse_state(inner_rules.first().unwrap());
+                let inner_phi = Self::parse_state(inner_rules.first().unwrap())?;
                 Box::new(Next { phi: inner_phi })
             }
             Rule::phi_until => {
-                let left_phi = Self::parse_state(inner_rules.first().unwrap());
-                let right_phi = Self::parse_state(inner_rules.get(1).unwrap());
+                let left_phi = Self::parse_state(inner_rules.first().unwrap())?;
+                let right_phi = Self::parse_state(inner_rules.get(1).unwrap())?;
                 Box::new(Until {
                     prev: left_phi,
                     until: right_phi,
                 })
             }
             Rule::phi_ev => {
-                let inner_phi = Self::parse_state(inner_rules.first().unwrap());
+                let inner_phi = Self::parse_state(inner_rules.first().unwrap())?;
                 Box::new(Until {
                     prev: Box::new(True {}),
                     until: inner_phi,
                 })
             }
+            Rule::phi_bounded_until => {
+                let left_phi = Self::parse_state(inner_rules.first().unwrap())?;
+                let steps = inner_rules
+                    .get(1)
+                    .unwrap()
+                    .as_str()
+                    .parse_or_error("integer")?;
+                let right_phi = Self::parse_state(inner_rules.get(2).unwrap())?;
+                Box::new(BoundedUntil {
+                    prev: left_phi,
+                    until: right_phi,
+                    steps,
+                })
+            }
+            Rule::phi_bounded_ev => {
+                let steps = inner_rules
+                    .first()
+                    .unwrap()
+                    .as_str()
+                    .parse_or_error("integer")?;
+                let inner_phi = Self::parse_state(inner_rules.get(1).unwrap())?;
+                Box::new(BoundedUntil {
+                    prev: Box::new(True {}),
+                    until: inner_phi,
+                    steps,
+                })
+            }
+            Rule::phi_glob => {
+                let inner_phi = Self::parse_state(inner_rules.first().unwrap())?;
+                Box::new(Globally::new(inner_phi))
+            }
             _ => unreachable!(),
-        }
+        };
+        Ok(path_phi)
     }
 }
 
 impl LogicImpl for PctlImpl {
-    fn parse(&self, content: &str) -> Formula {
-        let phi_content = match self.find_formula(content) {
-            None => panic!("Formula must contain 'PHI' exactly once!"),
-            Some(c) => c,
-        };
-        let parse_result = PctlPestParser::parse(Rule::Main, &phi_content);
-        match parse_result {
-            Ok(pairs) => {
-                let pairs_vec = pairs.collect::<Vec<_>>();
-                let pair = pairs_vec.first().unwrap();
-                let state_phi: Box<dyn StatePhi> = Self::parse_state(pair);
-                Formula::Pctl(PctlFormula(state_phi))
-            }
-            Err(error) => {
-                error!("PCTL Parsing error!");
-                println!("{}", error);
-                exit(0);
-            }
+    fn parse(&self, content: &str) -> Result<Formula, McspError> {
+        let (offset, phi_content) = self
+            .find_formula(content)
+            .ok_or(McspError::MissingFormula)?;
+        let pairs = PctlPestParser::parse(Rule::Main, &phi_content)
+            .map_err(|error| McspError::from(error).with_offset(content, offset))?;
+        let pairs_vec = pairs.collect::<Vec<_>>();
+        let pair = pairs_vec.first().unwrap();
+        if let Rule::query | Rule::reward_query | Rule::long_run_query = pair.as_rule() {
+            let inner_rules = pair.clone().into_inner().collect::<Vec<Pair<Rule>>>();
+            let quantifier = match &inner_rules.first().unwrap().as_str()[1..] {
+                "min" => Quantifier::Min,
+                "max" => Quantifier::Max,
+                _ => Quantifier::Value,
+            };
+            let inner_rule = inner_rules.get(1).unwrap();
+            let operator = match pair.as_rule() {
+                Rule::query => Operator::Prob(Self::parse_path(inner_rule)?),
+                Rule::reward_query => Operator::Reward(Self::parse_reward(inner_rule)?),
+                _ => Operator::LongRun(Self::parse_state(inner_rule)?),
+            };
+            return Ok(Formula::Pctl(PctlFormula::Query(Query {
+                operator,
+                quantifier,
+            })));
+        }
+        let state_phi: Box<dyn StatePhi> = Self::parse_state(pair)?;
+        Ok(Formula::Pctl(PctlFormula::State(state_phi)))
+    }
+}
+
+pub enum PctlFormula {
+    /// Formula which either holds in a marking or not
+    State(Box<dyn StatePhi>),
+    /// Asks for the probability of a path formula, the expected reward or the long-run fraction
+    /// of time spent in a set of markings
+    Query(Query),
+}
+
+/// Which value a query asks for
+pub enum Quantifier {
+    /// `P=?`, `R=?` or `S=?`, only defined if there is no nondeterminism
+    Value,
+    /// `Pmin=?`, `Rmin=?` or `Smin=?`, minimum over all schedulers
+    Min,
+    /// `Pmax=?`, `Rmax=?` or `Smax=?`, maximum over all schedulers
+    Max,
+}
+
+/// Operator whose value a query asks for
+pub enum Operator {
+    Prob(Box<dyn PathPhi>),
+    Reward(Box<dyn RewardPhi>),
+    LongRun(Box<dyn StatePhi>),
+}
+
+impl Operator {
+    fn values(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, f64>, McspError> {
+        match self {
+            Operator::Prob(phi) => phi.probabilities(pctl_info, comp),
+            Operator::Reward(phi) => phi.rewards(pctl_info, comp),
+            Operator::LongRun(phi) => long_run_averages(pctl_info, phi.as_ref(), comp),
         }
     }
+
+    /// Intervals containing the real values if the operator computes them
+    fn bounds(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<Option<HashMap<NodeIndex, Interval>>, McspError> {
+        match self {
+            Operator::Prob(phi) => phi.probability_bounds(pctl_info, comp).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn exact_values(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<Option<HashMap<NodeIndex, Rational>>, McspError> {
+        match self {
+            Operator::Prob(phi) => phi.exact_probabilities(pctl_info, comp),
+            _ => Ok(None),
+        }
+    }
+}
+
+pub struct Query {
+    pub operator: Operator,
+    pub quantifier: Quantifier,
 }
 
-pub struct PctlFormula(Box<dyn StatePhi>);
+/// Exact values converted to floats count as equal if they differ by less
+const EPSILON: f64 = 1e-12;
+
+/// Strongly connected components with at least this many states are swept by the topological
+/// solver in parallel
+const PARALLEL_BLOCK_SIZE: usize = 256;
+
+/// Value of a query for the initial state together with the min/max values of all states
+struct QueryValue {
+    value: f64,
+    /// The value as a fraction if it was computed exactly
+    exact_value: Option<Rational>,
+    /// Interval the value is guaranteed to lie in
+    value_bounds: Option<Interval>,
+    probabilities: HashMap<NodeIndex, Probability>,
+    scheduler: Option<Scheduler>,
+}
+
+impl Query {
+    fn evaluate(&self, pctl_info: &PctlInfo) -> Result<QueryValue, McspError> {
+        let graph = &pctl_info.reach_graph;
+        if let Quantifier::Value = self.quantifier {
+            let nondeterministic = graph
+                .node_indices()
+                .filter(|n| graph[*n].is_state())
+                .any(|n| graph.neighbors_directed(n, Outgoing).count() > 1);
+            if nondeterministic {
+                return Err(McspError::NondeterministicQuery);
+            }
+        }
+        if let Some(min) = self.operator.exact_values(pctl_info, &Comp::Geq)? {
+            let max = match self.quantifier {
+                Quantifier::Value => min.clone(),
+                _ => self.operator.exact_values(pctl_info, &Comp::Leq)?.unwrap(),
+            };
+            let values = match self.quantifier {
+                Quantifier::Max => &max,
+                _ => &min,
+            };
+            let value = values[&pctl_info.initial_marking];
+            return Ok(QueryValue {
+                value: value.to_f64(),
+                exact_value: Some(value),
+                value_bounds: None,
+                probabilities: min_max(&to_f64(&min), &to_f64(&max)),
+                scheduler: self.scheduler(pctl_info, &to_f64(values), EPSILON)?,
+            });
+        }
+        if let Some(min) = self.operator.bounds(pctl_info, &Comp::Geq)? {
+            let max = match self.quantifier {
+                Quantifier::Value => min.clone(),
+                _ => self.operator.bounds(pctl_info, &Comp::Leq)?.unwrap(),
+            };
+            let bounds = match self.quantifier {
+                Quantifier::Max => &max,
+                _ => &min,
+            };
+            let value_bounds = bounds[&pctl_info.initial_marking];
+            // The expected value of an action is off by at most the widest interval
+            let tolerance = bounds.values().map(|i| i.hi - i.lo).fold(EPSILON, f64::max);
+            return Ok(QueryValue {
+                value: value_bounds.midpoint(),
+                exact_value: None,
+                value_bounds: Some(value_bounds),
+                probabilities: min_max(&midpoints(&min), &midpoints(&max)),
+                scheduler: self.scheduler(pctl_info, &midpoints(bounds), tolerance)?,
+            });
+        }
+        let min = self.operator.values(pctl_info, &Comp::Geq)?;
+        let max = match self.quantifier {
+            Quantifier::Value => min.clone(),
+            _ => self.operator.values(pctl_info, &Comp::Leq)?,
+        };
+        let values = match self.quantifier {
+            Quantifier::Max => &max,
+            _ => &min,
+        };
+        Ok(QueryValue {
+            value: values[&pctl_info.initial_marking],
+            exact_value: None,
+            value_bounds: None,
+            probabilities: min_max(&min, &max),
+            scheduler: self.scheduler(pctl_info, values, pctl_info.max_error)?,
+        })
+    }
+
+    /// Scheduler attaining the values of the query if one was requested
+    fn scheduler(
+        &self,
+        pctl_info: &PctlInfo,
+        values: &HashMap<NodeIndex, f64>,
+        tolerance: f64,
+    ) -> Result<Option<Scheduler>, McspError> {
+        if !pctl_info.scheduler {
+            return Ok(None);
+        }
+        let Operator::Prob(phi) = &self.operator else {
+            warn!("Schedulers are only extracted for P operators");
+            return Ok(None);
+        };
+        let comp = match self.quantifier {
+            Quantifier::Max => Comp::Leq,
+            _ => Comp::Geq,
+        };
+        let scheduler = phi.scheduler(pctl_info, values, &comp, tolerance)?;
+        if scheduler.is_none() {
+            warn!("The optimal schedulers of {} need memory", phi);
+        }
+        Ok(scheduler)
+    }
+}
 
 impl PctlFormula {
-    pub fn evaluate<K>(&self, pctl_info: &PctlInfo, rename_map: BTreeMap<NodeIndex, Node<K>>)
+    pub fn evaluate<K>(
+        &self,
+        pctl_info: &PctlInfo,
+        rename_map: BTreeMap<NodeIndex, Node<K>>,
+    ) -> Result<CheckResult<K>, McspError>
     where
-        K: std::fmt::Debug,
+        K: std::fmt::Debug + Clone + Ord,
     {
-        let nodes = self.0.evaluate_inner(pctl_info);
-        info!("The following markings satisfy the given pctl statement:");
-        for node in &nodes {
-            let marking = match rename_map.get(&node).unwrap() {
-                Node::State(m) => m,
-                Node::Action(_) => unreachable!(),
-            };
-            info!("Marking {:?}", marking);
-        }
-        if nodes.contains(&pctl_info.initial_marking) {
-            let state = rename_map.get(&pctl_info.initial_marking).unwrap();
-            if let Node::State(marking) = state {
-                println!("Initial marking: {:?} satisfies the formula. So the petri net also satifies the formula.", marking);
-            } else {
-                panic!("Initial marking was mapped to an action")
+        let to_marking = |node: &NodeIndex| match rename_map.get(node).unwrap() {
+            Node::State(marking) => marking.clone(),
+            Node::Action(_) => panic!("Marking was mapped to an action"),
+        };
+        let mut counterexample = None;
+        let (nodes, probabilities, value, exact_value, value_bounds, scheduler) = match self {
+            PctlFormula::State(state_phi) => {
+                if pctl_info.scheduler {
+                    warn!("Schedulers are only extracted for Pmin=? and Pmax=? queries");
+                }
+                let (nodes, probabilities) = state_phi.evaluate_probabilities(pctl_info)?;
+                if let Some(max_paths) = pctl_info.counterexample {
+                    if !nodes.contains(&pctl_info.initial_marking) {
+                        counterexample = state_phi.counterexample(pctl_info, max_paths)?;
+                        if counterexample.is_none() {
+                            warn!("No counterexample is generated for {}", state_phi);
+                        }
+                    }
+                }
+                (nodes, probabilities, None, None, None, None)
+            }
+            PctlFormula::Query(query) => {
+                if pctl_info.counterexample.is_some() {
+                    warn!("Counterexamples are only generated for formulas with a bound");
+                }
+                let result = query.evaluate(pctl_info)?;
+                info!("Value of the initial marking: {}", result.value);
+                (
+                    HashSet::new(),
+                    result.probabilities,
+                    Some(result.value),
+                    result.exact_value,
+                    result.value_bounds,
+                    result.scheduler,
+                )
+            }
+        };
+        let satisfying_markings: BTreeSet<K> = nodes.iter().map(to_marking).collect();
+        if value.is_none() {
+            info!("The following markings satisfy the given pctl statement:");
+            for marking in &satisfying_markings {
+                info!("Marking {:?}", marking);
             }
         }
+        Ok(CheckResult {
+            initial_marking: to_marking(&pctl_info.initial_marking),
+            satisfied: nodes.contains(&pctl_info.initial_marking),
+            value,
+            exact_value,
+            value_bounds,
+            satisfying_markings,
+            probabilities: probabilities
+                .iter()
+                .map(|(node, probability)| (to_marking(node), *probability))
+                .collect(),
+            reach_graph: GraphSize::of(&pctl_info.reach_graph),
+            product: None,
+            scheduler: scheduler.map(|scheduler| {
+                decisions(&pctl_info.reach_graph, &scheduler, |s| {
+                    (to_marking(&s), None)
+                })
+            }),
+            counterexample: counterexample.map(|runs| {
+                Counterexample::Paths(
+                    runs.iter()
+                        .map(|run| {
+                            run.to_trace(
+                                |s| (to_marking(&s), None),
+                                |e| transition_name(pctl_info, e),
+                            )
+                        })
+                        .collect(),
+                )
+            }),
+            automata: Vec::new(),
+            timings: Timings::default(),
+        })
     }
 }
 
-pub trait StatePhi {
+fn midpoints(values: &HashMap<NodeIndex, Interval>) -> HashMap<NodeIndex, f64> {
+    values.iter().map(|(n, v)| (*n, v.midpoint())).collect()
+}
+
+fn to_f64(values: &HashMap<NodeIndex, Rational>) -> HashMap<NodeIndex, f64> {
+    values.iter().map(|(n, v)| (*n, v.to_f64())).collect()
+}
+
+/// Combines the minimal and maximal values of every state
+fn min_max(
+    min: &HashMap<NodeIndex, f64>,
+    max: &HashMap<NodeIndex, f64>,
+) -> HashMap<NodeIndex, Probability> {
+    min.iter()
+        .map(|(n, p)| {
+            (
+                *n,
+                Probability {
+                    min: *p,
+                    max: max[n],
+                },
+            )
+        })
+        .collect()
+}
+
+pub trait StatePhi: Send + Sync {
     fn fmt(&self) -> String;
-    fn evaluate_inner(&self, pctl_info: &PctlInfo) -> HashSet<NodeIndex>;
+    fn evaluate_inner(&self, pctl_info: &PctlInfo) -> Result<HashSet<NodeIndex>, McspError>;
+
+    /// Evaluates the formula like `evaluate_inner` and additionally returns the min and max
+    /// probabilities per state if the formula is a probabilistic operator
+    fn evaluate_probabilities(
+        &self,
+        pctl_info: &PctlInfo,
+    ) -> Result<(HashSet<NodeIndex>, HashMap<NodeIndex, Probability>), McspError> {
+        Ok((self.evaluate_inner(pctl_info)?, HashMap::new()))
+    }
+
+    /// At most `max_paths` runs from the initial state showing that it violates the formula.
+    /// None if no counterexample is generated for the formula
+    fn counterexample(
+        &self,
+        _pctl_info: &PctlInfo,
+        _max_paths: usize,
+    ) -> Result<Option<Vec<Run>>, McspError> {
+        Ok(None)
+    }
 }
 
 impl Display for dyn StatePhi {
@@ -207,14 +588,73 @@ impl Display for dyn StatePhi {
     }
 }
 
-pub trait PathPhi {
+pub trait PathPhi: Send + Sync {
     fn fmt(&self) -> String;
     fn evaluate_inner(
         &self,
         pctl_info: &PctlInfo,
         comp: &Comp,
         prob_bound: f64,
-    ) -> HashSet<NodeIndex>;
+    ) -> Result<HashSet<NodeIndex>, McspError>;
+
+    /// Probability of satisfying the path formula for every state. Upper bounds (see `comp`) use
+    /// the maximal probability, lower bounds the minimal one
+    fn probabilities(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, f64>, McspError>;
+
+    /// Intervals the real probabilities are guaranteed to lie in. Their midpoints are the best
+    /// estimates of the probabilities
+    fn probability_bounds(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, Interval>, McspError> {
+        Ok(self
+            .probabilities(pctl_info, comp)?
+            .into_iter()
+            .map(|(n, p)| (n, Interval::point(p)))
+            .collect())
+    }
+
+    /// Exact probabilities like `probabilities` if the graph was built in `--exact` mode and the
+    /// formula can be solved exactly
+    fn exact_probabilities(
+        &self,
+        _pctl_info: &PctlInfo,
+        _comp: &Comp,
+    ) -> Result<Option<HashMap<NodeIndex, Rational>>, McspError> {
+        Ok(None)
+    }
+
+    /// Memoryless scheduler attaining the probabilities `values` computed for `comp`. Expected
+    /// values of actions within `tolerance` of the best one count as optimal. None if the
+    /// optimal schedulers of the formula need memory
+    fn scheduler(
+        &self,
+        _pctl_info: &PctlInfo,
+        _values: &HashMap<NodeIndex, f64>,
+        _comp: &Comp,
+        _tolerance: f64,
+    ) -> Result<Option<Scheduler>, McspError> {
+        Ok(None)
+    }
+
+    /// At most `max_paths` most probable runs from the initial state whose combined probability
+    /// shows that it violates the bound. Upper bounds (see `comp`) are shown by runs satisfying
+    /// the formula under the maximizing scheduler, lower bounds by runs violating it under the
+    /// minimizing one. None if no counterexample is generated for the formula
+    fn counterexample(
+        &self,
+        _pctl_info: &PctlInfo,
+        _comp: &Comp,
+        _prob_bound: f64,
+        _max_paths: usize,
+    ) -> Result<Option<Vec<Run>>, McspError> {
+        Ok(None)
+    }
 }
 
 impl Display for dyn PathPhi {
@@ -230,12 +670,12 @@ impl StatePhi for True {
         "true".into()
     }
 
-    fn evaluate_inner(&self, pctl_info: &PctlInfo) -> HashSet<NodeIndex> {
-        pctl_info
+    fn evaluate_inner(&self, pctl_info: &PctlInfo) -> Result<HashSet<NodeIndex>, McspError> {
+        Ok(pctl_info
             .reach_graph
             .node_indices()
             .filter(|n| pctl_info.reach_graph[*n].is_state())
-            .collect()
+            .collect())
     }
 }
 
@@ -248,14 +688,14 @@ impl StatePhi for NotPhi {
         format!("¬ ({})", self.phi)
     }
 
-    fn evaluate_inner(&self, pctl_info: &PctlInfo) -> HashSet<NodeIndex> {
-        let phi_nodes = self.phi.evaluate_inner(pctl_info);
+    fn evaluate_inner(&self, pctl_info: &PctlInfo) -> Result<HashSet<NodeIndex>, McspError> {
+        let phi_nodes = self.phi.evaluate_inner(pctl_info)?;
         let all_nodes: HashSet<NodeIndex> = pctl_info
             .reach_graph
             .node_indices()
             .filter(|n| pctl_info.reach_graph[*n].is_state())
             .collect();
-        all_nodes.difference(&phi_nodes).copied().collect()
+        Ok(all_nodes.difference(&phi_nodes).copied().collect())
     }
 }
 
@@ -270,10 +710,66 @@ impl StatePhi for Prob {
         format!("P(({}), {} {})", self.phi, self.comp, self.probability)
     }
 
-    fn evaluate_inner(&self, pctl_info: &PctlInfo) -> HashSet<NodeIndex> {
+    fn evaluate_inner(&self, pctl_info: &PctlInfo) -> Result<HashSet<NodeIndex>, McspError> {
+        if let Some(probs) = self.phi.exact_probabilities(pctl_info, &self.comp)? {
+            return self.exact_bound(&probs);
+        }
         self.phi
             .evaluate_inner(pctl_info, &self.comp, self.probability)
     }
+
+    fn evaluate_probabilities(
+        &self,
+        pctl_info: &PctlInfo,
+    ) -> Result<(HashSet<NodeIndex>, HashMap<NodeIndex, Probability>), McspError> {
+        if let Some(min) = self.phi.exact_probabilities(pctl_info, &Comp::Geq)? {
+            let max = self
+                .phi
+                .exact_probabilities(pctl_info, &Comp::Leq)?
+                .unwrap();
+            let nodes = match self.comp.is_upper_bound() {
+                true => self.exact_bound(&max)?,
+                false => self.exact_bound(&min)?,
+            };
+            return Ok((nodes, min_max(&to_f64(&min), &to_f64(&max))));
+        }
+        let min = self.phi.probabilities(pctl_info, &Comp::Geq)?;
+        let max = self.phi.probabilities(pctl_info, &Comp::Leq)?;
+        let bound_probs = match self.comp.is_upper_bound() {
+            true => &max,
+            false => &min,
+        };
+        let nodes = bound_probs
+            .iter()
+            .filter(|(_, p)| self.comp.evaluate(**p, self.probability))
+            .map(|(n, _)| *n)
+            .collect();
+        Ok((nodes, min_max(&min, &max)))
+    }
+
+    fn counterexample(
+        &self,
+        pctl_info: &PctlInfo,
+        max_paths: usize,
+    ) -> Result<Option<Vec<Run>>, McspError> {
+        self.phi
+            .counterexample(pctl_info, &self.comp, self.probability, max_paths)
+    }
+}
+
+impl Prob {
+    /// States whose exact probability satisfies the bound
+    fn exact_bound(
+        &self,
+        probs: &HashMap<NodeIndex, Rational>,
+    ) -> Result<HashSet<NodeIndex>, McspError> {
+        let bound = Rational::from_f64(self.probability).ok_or(McspError::ExactOverflow)?;
+        Ok(probs
+            .iter()
+            .filter(|(_, p)| self.comp.evaluate(**p, bound))
+            .map(|(n, _)| *n)
+            .collect())
+    }
 }
 
 pub struct AP {
@@ -285,16 +781,12 @@ impl StatePhi for AP {
         self.value.clone()
     }
 
-    fn evaluate_inner(&self, pctl_info: &PctlInfo) -> HashSet<NodeIndex> {
-        if pctl_info.ap_map.contains_key(&self.value) {
-            pctl_info.ap_map[&self.value].clone()
-        } else {
-            error!(
-                "Formula contains an ap with the name \"{}\" but is not mapped to a marking",
-                &self.value
-            );
-            exit(0);
-        }
+    fn evaluate_inner(&self, pctl_info: &PctlInfo) -> Result<HashSet<NodeIndex>, McspError> {
+        pctl_info
+            .ap_map
+            .get(&self.value)
+            .cloned()
+            .ok_or_else(|| McspError::UnknownAp(self.value.clone()))
     }
 }
 
@@ -308,13 +800,13 @@ impl StatePhi for AndPhi {
         format!("({}) ∧ ({})", self.left_phi, self.right_phi)
     }
 
-    fn evaluate_inner(&self, pctl_info: &PctlInfo) -> HashSet<NodeIndex> {
-        let left_markings = self.left_phi.evaluate_inner(pctl_info);
-        let right_markings = self.right_phi.evaluate_inner(pctl_info);
-        left_markings
+    fn evaluate_inner(&self, pctl_info: &PctlInfo) -> Result<HashSet<NodeIndex>, McspError> {
+        let left_markings = self.left_phi.evaluate_inner(pctl_info)?;
+        let right_markings = self.right_phi.evaluate_inner(pctl_info)?;
+        Ok(left_markings
             .intersection(&right_markings)
             .copied()
-            .collect()
+            .collect())
     }
 }
 
@@ -337,58 +829,216 @@ impl PathPhi for Next {
         pctl_info: &PctlInfo,
         comp: &Comp,
         prob_bound: f64,
-    ) -> HashSet<NodeIndex> {
-        let graph = &pctl_info.reach_graph;
-        let phi_node_indices = self.phi.evaluate_inner(pctl_info);
-
-        // Create a hashmap which maps every action to a probability of satisfying phi
-        let mut action_prob: HashMap<NodeIndex, f64> = HashMap::new();
-        let mut state_prob: HashMap<NodeIndex, f64> = HashMap::new();
-        for phi_node_index in phi_node_indices {
-            let pre_actions = graph
-                .neighbors_directed(phi_node_index, Incoming)
-                .collect::<Vec<NodeIndex>>();
-            for pre_action in pre_actions {
-                let old_prob = action_prob.get(&pre_action).unwrap_or(&0.0);
-                let prob = graph
-                    .edges_connecting(pre_action, phi_node_index)
-                    .collect::<Vec<_>>()
-                    .first()
-                    .unwrap()
-                    .weight();
-                action_prob.insert(pre_action, old_prob + prob);
-            }
-        }
-
-        // Create a hashmap which maps all states to the max or min (depending on comp) probability
-        // of satisfying phi
-        for action in action_prob.keys() {
-            let pre_states = graph
-                .neighbors_directed(*action, Incoming)
-                .collect::<Vec<NodeIndex>>();
-            for pre_state in pre_states {
-                let old_prob = state_prob.get(&pre_state);
-                let new_prob = match old_prob {
-                    None => action_prob[action],
-                    Some(p) => {
-                        if comp.is_upper_bound() {
-                            p.max(action_prob[action])
-                        } else {
-                            p.min(action_prob[action])
-                        }
-                    }
-                };
-                state_prob.insert(pre_state, new_prob);
+    ) -> Result<HashSet<NodeIndex>, McspError> {
+        // Returns the states for which the comparison holds
+        Ok(self
+            .probabilities(pctl_info, comp)?
+            .into_iter()
+            .filter(|(_, p)| comp.evaluate(*p, prob_bound))
+            .map(|(n, _)| n)
+            .collect())
+    }
+
+    fn probabilities(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, f64>, McspError> {
+        let sparse = &pctl_info.sparse;
+        let phi = sparse.indicator(&self.phi.evaluate_inner(pctl_info)?);
+
+        // Every state gets the max or min (depending on comp) probability over its actions of
+        // reaching phi in one step
+        let state_prob: Vec<f64> = (0..sparse.state_count())
+            .into_par_iter()
+            .map(|state| {
+                optimum(
+                    sparse
+                        .rows(state)
+                        .map(|row| sparse.expected_value(row, &phi)),
+                    comp,
+                )
+            })
+            .collect();
+        Ok(sparse.to_map(&state_prob))
+    }
+
+    fn exact_probabilities(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<Option<HashMap<NodeIndex, Rational>>, McspError> {
+        let Some(weights) = &pctl_info.exact else {
+            return Ok(None);
+        };
+        let phi_node_indices = self.phi.evaluate_inner(pctl_info)?;
+        exact::next_probabilities(pctl_info, weights, &phi_node_indices, comp).map(Some)
+    }
+
+    fn scheduler(
+        &self,
+        pctl_info: &PctlInfo,
+        _values: &HashMap<NodeIndex, f64>,
+        comp: &Comp,
+        _tolerance: f64,
+    ) -> Result<Option<Scheduler>, McspError> {
+        let phi: HashMap<NodeIndex, f64> = self
+            .phi
+            .evaluate_inner(pctl_info)?
+            .into_iter()
+            .map(|n| (n, 1.0))
+            .collect();
+        Ok(Some(scheduler::greedy_scheduler(
+            &pctl_info.reach_graph,
+            &phi,
+            comp,
+            0.0,
+        )))
+    }
+
+    fn counterexample(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+        prob_bound: f64,
+        max_paths: usize,
+    ) -> Result<Option<Vec<Run>>, McspError> {
+        let Some(scheduler) = self.scheduler(pctl_info, &HashMap::new(), comp, 0.0)? else {
+            return Ok(None);
+        };
+        let phi = self.phi.evaluate_inner(pctl_info)?;
+        let initial = pctl_info.initial_marking;
+        let mut steps: Vec<Run> = successors(&pctl_info.reach_graph, &scheduler, initial)
+            .filter(|(_, target, _)| phi.contains(target) == comp.is_upper_bound())
+            .map(|(edge, target, p)| Run {
+                states: vec![initial, target],
+                edges: vec![edge],
+                probability: p,
+            })
+            .collect();
+        steps.sort_by(|r1, r2| r2.probability.total_cmp(&r1.probability));
+        let mut mass = 0.0;
+        let mut runs = Vec::new();
+        for run in steps.into_iter().take(max_paths) {
+            mass += run.probability;
+            runs.push(run);
+            let estimate = match comp.is_upper_bound() {
+                true => mass,
+                false => 1.0 - mass,
+            };
+            if !comp.evaluate(estimate, prob_bound) {
+                break;
             }
         }
+        Ok(Some(runs))
+    }
+}
 
-        // Returns the hashmap containing the states for which the comparison holds
-        pctl_info
-            .reach_graph
-            .node_indices()
-            .filter(|n| pctl_info.reach_graph[*n].is_state())
-            .filter(|n| comp.evaluate(state_prob.get(n).unwrap_or(&0.0), &prob_bound))
-            .collect()
+/// Always operator, computed as the complement of eventually reaching a state violating phi
+pub struct Globally {
+    eventually_not: Until,
+}
+
+impl Globally {
+    pub fn new(phi: Box<dyn StatePhi>) -> Self {
+        Globally {
+            eventually_not: Until {
+                prev: Box::new(True {}),
+                until: Box::new(NotPhi { phi }),
+            },
+        }
+    }
+}
+
+impl PathPhi for Globally {
+    fn fmt(&self) -> String {
+        format!("¬ ({})", self.eventually_not.fmt())
+    }
+
+    fn evaluate_inner(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+        prob_bound: f64,
+    ) -> Result<HashSet<NodeIndex>, McspError> {
+        self.eventually_not
+            .evaluate_inner(pctl_info, &comp.flipped(), 1.0 - prob_bound)
+    }
+
+    fn probabilities(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, f64>, McspError> {
+        Ok(self
+            .eventually_not
+            .probabilities(pctl_info, &comp.flipped())?
+            .into_iter()
+            .map(|(n, p)| (n, 1.0 - p))
+            .collect())
+    }
+
+    fn probability_bounds(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, Interval>, McspError> {
+        Ok(self
+            .eventually_not
+            .probability_bounds(pctl_info, &comp.flipped())?
+            .into_iter()
+            .map(|(n, i)| {
+                let interval = Interval {
+                    lo: 1.0 - i.hi,
+                    hi: 1.0 - i.lo,
+                };
+                (n, interval)
+            })
+            .collect())
+    }
+
+    fn exact_probabilities(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<Option<HashMap<NodeIndex, Rational>>, McspError> {
+        let Some(probs) = self
+            .eventually_not
+            .exact_probabilities(pctl_info, &comp.flipped())?
+        else {
+            return Ok(None);
+        };
+        probs
+            .into_iter()
+            .map(|(n, p)| match Rational::ONE.checked_sub(p) {
+                Some(p) => Ok((n, p)),
+                None => Err(McspError::ExactOverflow),
+            })
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+
+    fn scheduler(
+        &self,
+        pctl_info: &PctlInfo,
+        values: &HashMap<NodeIndex, f64>,
+        comp: &Comp,
+        tolerance: f64,
+    ) -> Result<Option<Scheduler>, McspError> {
+        let values: HashMap<NodeIndex, f64> = values.iter().map(|(n, p)| (*n, 1.0 - p)).collect();
+        self.eventually_not
+            .scheduler(pctl_info, &values, &comp.flipped(), tolerance)
+    }
+
+    fn counterexample(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+        prob_bound: f64,
+        max_paths: usize,
+    ) -> Result<Option<Vec<Run>>, McspError> {
+        self.eventually_not
+            .counterexample(pctl_info, &comp.flipped(), 1.0 - prob_bound, max_paths)
     }
 }
 
@@ -402,11 +1052,8 @@ impl Until {
     ) -> HashSet<NodeIndex> {
         let mut new = all_indices.clone();
         loop {
-            let not_new = all_indices.difference(&new);
-            let tmp2: HashSet<NodeIndex> = not_new
-                .flat_map(|i| graph.neighbors_directed(*i, Incoming))
-                .flat_map(|i| graph.neighbors_directed(i, Incoming))
-                .collect();
+            let not_new: Vec<NodeIndex> = all_indices.difference(&new).copied().collect();
+            let tmp2 = predecessors(graph, &not_new);
             let tmp3: HashSet<NodeIndex> = all_indices.difference(&tmp2).copied().collect();
             let tmp4: HashSet<NodeIndex> = left_tsi.intersection(&tmp3).copied().collect();
             let tmp5: HashSet<NodeIndex> = right_tsi.union(&tmp4).copied().collect();
@@ -428,17 +1075,10 @@ impl Until {
         graph: &MDP<NodeIndex>,
     ) -> HashSet<NodeIndex> {
         let mut new = right_tsi.clone();
-        let only_pre: HashSet<NodeIndex> = graph
-            .node_indices()
-            .flat_map(|i| graph.neighbors_directed(i, Incoming))
-            .flat_map(|i| graph.neighbors_directed(i, Incoming))
-            .collect();
+        let only_pre = predecessors(graph, &graph.node_indices().collect::<Vec<_>>());
         loop {
-            let tmp1 = all.difference(&new);
-            let tmp2: HashSet<NodeIndex> = tmp1
-                .flat_map(|i| graph.neighbors_directed(*i, Incoming))
-                .flat_map(|i| graph.neighbors_directed(i, Incoming))
-                .collect();
+            let tmp1: Vec<NodeIndex> = all.difference(&new).copied().collect();
+            let tmp2 = predecessors(graph, &tmp1);
             let tmp3: HashSet<NodeIndex> = all.difference(&tmp2).copied().collect();
             let tmp4: HashSet<NodeIndex> = left_tsi.intersection(&tmp3).copied().collect();
             let tmp5: HashSet<NodeIndex> = only_pre.intersection(&tmp4).copied().collect();
@@ -456,15 +1096,15 @@ impl Until {
         &self,
         pctl_info: &PctlInfo,
         prob_map: &mut HashMap<NodeIndex, f64>,
-    ) -> (HashSet<NodeIndex>, HashSet<NodeIndex>) {
+    ) -> Result<(HashSet<NodeIndex>, HashSet<NodeIndex>), McspError> {
         let all: HashSet<_> = pctl_info
             .reach_graph
             .node_indices()
             .filter(|i| matches!(pctl_info.reach_graph[*i], Node::State(_)))
             .collect();
 
-        let left_phi = self.prev.evaluate_inner(pctl_info);
-        let right_phi = self.until.evaluate_inner(pctl_info);
+        let left_phi = self.prev.evaluate_inner(pctl_info)?;
+        let right_phi = self.until.evaluate_inner(pctl_info)?;
 
         // not E(phi_1 U phi_2) = A(not phi_2 W (not phi_1 and not phi_2))
         let not_left_phi: HashSet<NodeIndex> = all.difference(&left_phi).copied().collect();
@@ -490,52 +1130,353 @@ impl Until {
         for node_index in &s_q {
             prob_map.insert(*node_index, 0.0);
         }
-        (s_1, s_q)
+        Ok((s_1, s_q))
     }
 
+    /// Computes the probabilities of the states of `s_q` with the solver of `pctl_info` and
+    /// writes them to `prob_map`. Returns the interval every probability is guaranteed to lie in
     pub fn iterate_prob(
+        pctl_info: &PctlInfo,
+        mut s_q: HashSet<NodeIndex>,
+        prob_map: &mut HashMap<NodeIndex, f64>,
+        mut s_1: HashSet<NodeIndex>,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, Interval>, McspError> {
+        // The probabilities of the states reaching s_1 almost surely follow from the graph. The
+        // solvers only approach them slowly or, on rarely left cycles, not at all
+        let allowed: HashSet<NodeIndex> = s_q.union(&s_1).copied().collect();
+        let almost_sure = match comp.is_upper_bound() {
+            true => prob1_exists(&pctl_info.reach_graph, &allowed, &s_1),
+            false => prob1_all(&pctl_info.reach_graph, &allowed, &s_1),
+        };
+        for state in almost_sure.intersection(&s_q) {
+            prob_map.insert(*state, 1.0);
+            s_1.insert(*state);
+        }
+        s_q.retain(|s| !almost_sure.contains(s));
+        let values = match pctl_info.solver {
+            Solver::Value | Solver::GaussSeidel | Solver::Topological => None,
+            Solver::Policy => {
+                let values = solver::policy_iteration(pctl_info, &s_1, &s_q, comp);
+                if values.is_none() {
+                    warn!("Policy evaluation failed, falling back to value iteration");
+                }
+                values
+            }
+            Solver::Lp => {
+                let values = solver::linear_program(pctl_info, &s_1, &s_q, comp);
+                if values.is_none() {
+                    warn!("Linear program has no optimum, falling back to value iteration");
+                }
+                values
+            }
+        };
+        match values {
+            // Both solvers compute the probabilities up to floating point errors
+            Some(values) => {
+                prob_map.extend(values);
+                Ok(prob_map
+                    .iter()
+                    .map(|(s, p)| (*s, Interval::point(*p)))
+                    .collect())
+            }
+            None => Self::interval_iteration(pctl_info, s_q, prob_map, s_1, comp),
+        }
+    }
+
+    /// Interval iteration: lower bounds rise from 0 and upper bounds fall from 1 until they are
+    /// less than twice `max_error` apart, so the midpoints written to `prob_map` are within
+    /// `max_error` of the real probabilities. Fails if floating point errors stop the bounds
+    /// before. The upper bounds only converge without end
+    /// components, so the minimum treats them as never reaching `s_1` and the maximum collapses
+    /// every maximal end component into a single state
+    fn interval_iteration(
         pctl_info: &PctlInfo,
         s_q: HashSet<NodeIndex>,
         prob_map: &mut HashMap<NodeIndex, f64>,
         s_1: HashSet<NodeIndex>,
         comp: &Comp,
-    ) {
+    ) -> Result<HashMap<NodeIndex, Interval>, McspError> {
         let graph = &pctl_info.reach_graph;
-        loop {
-            let mut max_error: f64 = 0.0;
-            for node_index in &s_q {
-                let actions = graph.neighbors_directed(*node_index, Outgoing);
-                let mut prob: Option<f64> = None;
-                for action in actions {
-                    let edges: Edges<_, _> = graph.edges_directed(action, Outgoing);
-                    let prob_to_sq: f64 = edges
-                        .clone()
-                        .filter(|i| s_q.contains(&i.target()))
-                        .map(|i| i.weight() * prob_map.get(&i.target()).unwrap())
-                        .sum();
-                    let prob_to_s1: f64 = edges
-                        .filter(|i| s_1.contains(&i.target()))
-                        .map(|i| i.weight())
-                        .sum();
-                    prob = match prob {
-                        None => Some(prob_to_sq + prob_to_s1),
-                        Some(p) => {
-                            if comp.is_upper_bound() {
-                                Some(p.max(prob_to_sq + prob_to_s1))
-                            } else {
-                                Some(p.min(prob_to_sq + prob_to_s1))
-                            }
+        let sparse = &pctl_info.sparse;
+        let mut lower = sparse.vector(prob_map, 0.0);
+        for state in sparse.numbers_of(&s_1) {
+            lower[state] = 1.0;
+        }
+        let mut s_q = s_q;
+        let mut mecs = maximal_end_components(graph, &s_q);
+        if !comp.is_upper_bound() {
+            for state in mecs.drain(..).flat_map(|mec| mec.states) {
+                s_q.remove(&state);
+                lower[sparse.number(state).unwrap()] = 0.0;
+            }
+        }
+        let mut upper = lower.clone();
+        for state in sparse.numbers_of(&s_q) {
+            upper[state] = 1.0;
+        }
+        // States of every end component together with the rows of the actions leaving it
+        let mecs: Vec<(Vec<usize>, Vec<usize>)> = mecs
+            .iter()
+            .map(|mec| {
+                let states: Vec<usize> = mec
+                    .states
+                    .iter()
+                    .map(|s| sparse.number(*s).unwrap())
+                    .collect();
+                let leaving = states
+                    .iter()
+                    .flat_map(|s| sparse.rows(*s))
+                    .filter(|row| !mec.actions.contains(&sparse.action(*row)))
+                    .collect();
+                (states, leaving)
+            })
+            .collect();
+        let mut in_mec = vec![false; sparse.state_count()];
+        for state in mecs.iter().flat_map(|(states, _)| states) {
+            in_mec[*state] = true;
+        }
+        // Every block only depends on the blocks before it, so they are solved one after the
+        // other. Gauss-Seidel sweeps profit from the same order
+        let blocks = match pctl_info.solver {
+            Solver::Topological => Self::topological_blocks(sparse, &s_q),
+            Solver::GaussSeidel => vec![Self::topological_blocks(sparse, &s_q).concat()],
+            _ => vec![sparse.numbers_of(&s_q)],
+        };
+        let mut stalled_width: f64 = 0.0;
+        for block in &blocks {
+            // Jacobi sweeps compute all bounds from the ones of the previous sweep, the others
+            // use new bounds as soon as they are known. Large components are swept in parallel
+            // by the topological solver, which only depends on their size and not on the number
+            // of threads
+            let in_place = match pctl_info.solver {
+                Solver::Value => false,
+                Solver::Topological => block.len() < PARALLEL_BLOCK_SIZE,
+                _ => true,
+            };
+            let block_states: HashSet<usize> = block.iter().copied().collect();
+            let block_mecs: Vec<&(Vec<usize>, Vec<usize>)> = mecs
+                .iter()
+                .filter(|(states, _)| block_states.contains(&states[0]))
+                .collect();
+            loop {
+                let previous: Vec<(f64, f64)> =
+                    block.iter().map(|s| (lower[*s], upper[*s])).collect();
+                let bounds = |state: usize, lower: &[f64], upper: &[f64]| {
+                    let rows = sparse.rows(state);
+                    let lo = optimum(
+                        rows.clone().map(|row| sparse.expected_value(row, lower)),
+                        comp,
+                    );
+                    let hi = match in_mec[state] {
+                        true => upper[state],
+                        false => optimum(rows.map(|row| sparse.expected_value(row, upper)), comp),
+                    };
+                    (lo.min(1.0), hi.min(1.0))
+                };
+                match in_place {
+                    true => {
+                        for state in block {
+                            (lower[*state], upper[*state]) = bounds(*state, &lower, &upper);
                         }
                     }
+                    // Every state only reads the bounds of the previous sweep, so the states are
+                    // updated in parallel with the same result for any number of threads
+                    false => {
+                        let updates: Vec<(f64, f64)> = block
+                            .par_iter()
+                            .map(|state| bounds(*state, &lower, &upper))
+                            .collect();
+                        for (state, (lo, hi)) in block.iter().zip(updates) {
+                            lower[*state] = lo;
+                            upper[*state] = hi;
+                        }
+                    }
+                }
+                // Staying in an end component forever never reaches s_1, so only the actions
+                // leaving it count
+                for (states, leaving) in &block_mecs {
+                    let prob = leaving
+                        .iter()
+                        .map(|row| sparse.expected_value(*row, &upper))
+                        .fold(0.0, f64::max);
+                    for state in states {
+                        upper[*state] = prob.min(1.0);
+                    }
+                }
+                let max_width = block
+                    .iter()
+                    .map(|s| upper[*s] - lower[*s])
+                    .fold(0.0, f64::max);
+                // The width of a block never drops below the widths of the blocks it leads to,
+                // so the bounds may reach a fixed point before getting close enough
+                let stalled = block
+                    .iter()
+                    .zip(&previous)
+                    .all(|(s, (lo, hi))| lower[*s] == *lo && upper[*s] == *hi);
+                if max_width < 2.0 * pctl_info.max_error {
+                    break;
+                }
+                if stalled {
+                    stalled_width = stalled_width.max(max_width);
+                    break;
                 }
-                max_error = max_error.max((prob_map[node_index] - prob.unwrap()).abs());
-                prob_map.insert(*node_index, prob.unwrap());
             }
-            if max_error < pctl_info.max_error {
+        }
+        if stalled_width > 0.0 {
+            return Err(McspError::PrecisionNotReached {
+                width: stalled_width,
+                max_error: pctl_info.max_error,
+            });
+        }
+        let intervals: Vec<Interval> = lower
+            .iter()
+            .zip(&upper)
+            .map(|(lo, hi)| Interval { lo: *lo, hi: *hi })
+            .collect();
+        let intervals = sparse.to_map(&intervals);
+        prob_map.extend(intervals.iter().map(|(s, i)| (*s, i.midpoint())));
+        Ok(intervals)
+    }
+
+    /// Strongly connected components of the states of `s_q`, ordered such that every component
+    /// only leads to components before it
+    fn topological_blocks(sparse: &SparseMdp, s_q: &HashSet<NodeIndex>) -> Vec<Vec<usize>> {
+        let states = sparse.numbers_of(s_q);
+        let mut state_graph: DiGraph<usize, ()> = DiGraph::new();
+        let indices: HashMap<usize, NodeIndex> = states
+            .iter()
+            .map(|state| (*state, state_graph.add_node(*state)))
+            .collect();
+        for state in &states {
+            for row in sparse.rows(*state) {
+                for (target, _) in sparse.entries(row) {
+                    if let Some(target_index) = indices.get(&target) {
+                        state_graph.add_edge(indices[state], *target_index, ());
+                    }
+                }
+            }
+        }
+        // The components are returned in reverse topological order
+        kosaraju_scc(&state_graph)
+            .into_iter()
+            .map(|scc| scc.into_iter().map(|n| state_graph[n]).collect())
+            .collect()
+    }
+}
+
+/// States with an action leading to one of the nodes. The nodes are split among the threads
+fn predecessors(graph: &MDP<NodeIndex>, nodes: &[NodeIndex]) -> HashSet<NodeIndex> {
+    nodes
+        .par_iter()
+        .flat_map_iter(|i| {
+            graph
+                .neighbors_directed(*i, Incoming)
+                .flat_map(|i| graph.neighbors_directed(i, Incoming))
+        })
+        .collect()
+}
+
+/// States from which some scheduler reaches the goal with probability 1 without leaving the
+/// states
+fn prob1_exists(
+    graph: &MDP<NodeIndex>,
+    states: &HashSet<NodeIndex>,
+    goal: &HashSet<NodeIndex>,
+) -> HashSet<NodeIndex> {
+    let mut remaining = states.clone();
+    loop {
+        // States which can reach the goal while never leaving the remaining states
+        let mut reaching = goal.clone();
+        loop {
+            let added: Vec<NodeIndex> = remaining
+                .iter()
+                .filter(|s| !reaching.contains(s))
+                .filter(|s| {
+                    graph.neighbors_directed(**s, Outgoing).any(|action| {
+                        graph
+                            .neighbors_directed(action, Outgoing)
+                            .all(|t| remaining.contains(&t))
+                            && graph
+                                .neighbors_directed(action, Outgoing)
+                                .any(|t| reaching.contains(&t))
+                    })
+                })
+                .copied()
+                .collect();
+            if added.is_empty() {
                 break;
             }
+            reaching.extend(added);
+        }
+        if reaching == remaining {
+            return remaining;
+        }
+        remaining = reaching;
+    }
+}
+
+/// States from which every scheduler reaches the goal with probability 1 without leaving the
+/// states
+fn prob1_all(
+    graph: &MDP<NodeIndex>,
+    states: &HashSet<NodeIndex>,
+    goal: &HashSet<NodeIndex>,
+) -> HashSet<NodeIndex> {
+    // States in which a scheduler can avoid the goal forever
+    let mut avoiding: HashSet<NodeIndex> = states.difference(goal).copied().collect();
+    loop {
+        let removed: Vec<NodeIndex> = avoiding
+            .iter()
+            .filter(|s| {
+                !graph.neighbors_directed(**s, Outgoing).any(|action| {
+                    graph
+                        .neighbors_directed(action, Outgoing)
+                        .all(|t| avoiding.contains(&t))
+                })
+            })
+            .copied()
+            .collect();
+        if removed.is_empty() {
+            break;
+        }
+        removed.iter().for_each(|s| {
+            avoiding.remove(s);
+        });
+    }
+    // States reaching the avoiding ones or leaving the states with a positive probability
+    // before the goal
+    let mut missing = avoiding;
+    loop {
+        let added: Vec<NodeIndex> = states
+            .iter()
+            .filter(|s| !missing.contains(s) && !goal.contains(s))
+            .filter(|s| {
+                graph.neighbors_directed(**s, Outgoing).any(|action| {
+                    graph
+                        .neighbors_directed(action, Outgoing)
+                        .any(|t| missing.contains(&t) || !states.contains(&t))
+                })
+            })
+            .copied()
+            .collect();
+        if added.is_empty() {
+            break;
         }
+        missing.extend(added);
     }
+    states.difference(&missing).copied().collect()
+}
+
+/// Maximum of the values for upper bounds (see `comp`), minimum for lower bounds and 0 without
+/// any value
+fn optimum(values: impl Iterator<Item = f64>, comp: &Comp) -> f64 {
+    values
+        .reduce(|p1, p2| match comp.is_upper_bound() {
+            true => p1.max(p2),
+            false => p1.min(p2),
+        })
+        .unwrap_or(0.0)
 }
 
 impl PathPhi for Until {
@@ -548,7 +1489,7 @@ impl PathPhi for Until {
         pctl_info: &PctlInfo,
         comp: &Comp,
         prob_bound: f64,
-    ) -> HashSet<NodeIndex> {
+    ) -> Result<HashSet<NodeIndex>, McspError> {
         let all: HashSet<_> = pctl_info
             .reach_graph
             .node_indices()
@@ -557,17 +1498,404 @@ impl PathPhi for Until {
         let geq_zero = *comp == Comp::Geq && prob_bound == 0.0;
         let leq_one = *comp == Comp::Leq && prob_bound == 1.0;
         if geq_zero || leq_one {
-            return all;
+            return Ok(all);
         }
 
+        Ok(self
+            .probabilities(pctl_info, comp)?
+            .into_iter()
+            .filter(|(_, v)| comp.evaluate(*v, prob_bound))
+            .map(|(k, _)| k)
+            .collect())
+    }
+
+    fn probabilities(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, f64>, McspError> {
         let mut prob_map: HashMap<NodeIndex, f64> = HashMap::new();
-        let (s_1, s_q) = self.s1_sq(pctl_info, &mut prob_map);
+        let (s_1, s_q) = self.s1_sq(pctl_info, &mut prob_map)?;
+        Self::iterate_prob(pctl_info, s_q, &mut prob_map, s_1, comp)?;
+        Ok(prob_map)
+    }
+
+    fn probability_bounds(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, Interval>, McspError> {
+        let mut prob_map: HashMap<NodeIndex, f64> = HashMap::new();
+        let (s_1, s_q) = self.s1_sq(pctl_info, &mut prob_map)?;
+        Self::iterate_prob(pctl_info, s_q, &mut prob_map, s_1, comp)
+    }
+
+    fn exact_probabilities(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<Option<HashMap<NodeIndex, Rational>>, McspError> {
+        let Some(weights) = &pctl_info.exact else {
+            return Ok(None);
+        };
+        let (s_1, s_q) = self.s1_sq(pctl_info, &mut HashMap::new())?;
+        exact::until_probabilities(pctl_info, weights, &s_1, &s_q, comp).map(Some)
+    }
+
+    fn scheduler(
+        &self,
+        pctl_info: &PctlInfo,
+        values: &HashMap<NodeIndex, f64>,
+        comp: &Comp,
+        tolerance: f64,
+    ) -> Result<Option<Scheduler>, McspError> {
+        let (s_1, s_q) = self.s1_sq(pctl_info, &mut HashMap::new())?;
+        Ok(Some(reachability_scheduler(
+            &pctl_info.reach_graph,
+            &s_1,
+            &s_q,
+            values,
+            comp,
+            tolerance,
+        )))
+    }
+
+    fn counterexample(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+        prob_bound: f64,
+        max_paths: usize,
+    ) -> Result<Option<Vec<Run>>, McspError> {
+        let graph = &pctl_info.reach_graph;
+        let mut values: HashMap<NodeIndex, f64> = HashMap::new();
+        let (s_1, s_q) = self.s1_sq(pctl_info, &mut values)?;
+        let bounds = Self::iterate_prob(pctl_info, s_q.clone(), &mut values, s_1.clone(), comp)?;
+        let tolerance = bounds.values().map(|i| i.hi - i.lo).fold(EPSILON, f64::max);
+        let scheduler = reachability_scheduler(graph, &s_1, &s_q, &values, comp, tolerance);
 
-        Self::iterate_prob(pctl_info, s_q, &mut prob_map, s_1, comp);
-        prob_map
+        let left_phi = self.prev.evaluate_inner(pctl_info)?;
+        let right_phi = self.until.evaluate_inner(pctl_info)?;
+        let stay: HashSet<NodeIndex> = left_phi.difference(&right_phi).copied().collect();
+        let initial = pctl_info.initial_marking;
+        let runs = match comp.is_upper_bound() {
+            true => most_probable_paths(
+                graph,
+                &scheduler,
+                initial,
+                &stay,
+                &right_phi,
+                |mass| !comp.evaluate(mass, prob_bound),
+                max_paths,
+            ),
+            // A run violates the formula once it reaches a state which can not reach phi_2
+            // under the scheduler anymore
+            false => {
+                let reaching = reaching(graph, &scheduler, &stay, &right_phi);
+                let violating: HashSet<NodeIndex> = graph
+                    .node_indices()
+                    .filter(|n| graph[*n].is_state() && !reaching.contains(n))
+                    .collect();
+                let stay: HashSet<NodeIndex> = stay.intersection(&reaching).copied().collect();
+                most_probable_paths(
+                    graph,
+                    &scheduler,
+                    initial,
+                    &stay,
+                    &violating,
+                    |mass| !comp.evaluate(1.0 - mass, prob_bound),
+                    max_paths,
+                )
+            }
+        };
+        Ok(Some(runs))
+    }
+}
+
+/// Until which has to reach `until` within `steps` transitions
+pub struct BoundedUntil {
+    pub prev: Box<dyn StatePhi>,
+    pub until: Box<dyn StatePhi>,
+    pub steps: usize,
+}
+
+impl PathPhi for BoundedUntil {
+    fn fmt(&self) -> String {
+        format!("({}) U<={} ({})", self.prev, self.steps, self.until)
+    }
+
+    fn evaluate_inner(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+        prob_bound: f64,
+    ) -> Result<HashSet<NodeIndex>, McspError> {
+        Ok(self
+            .probabilities(pctl_info, comp)?
             .into_iter()
             .filter(|(_, v)| comp.evaluate(*v, prob_bound))
             .map(|(k, _)| k)
-            .collect()
+            .collect())
+    }
+
+    fn probabilities(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, f64>, McspError> {
+        let graph = &pctl_info.reach_graph;
+        let left_phi = self.prev.evaluate_inner(pctl_info)?;
+        let right_phi = self.until.evaluate_inner(pctl_info)?;
+        // States satisfying phi_1 but not phi_2 are the only ones whose probability changes
+        let s_q: Vec<NodeIndex> = left_phi.difference(&right_phi).copied().collect();
+
+        let mut prob_map: HashMap<NodeIndex, f64> = graph
+            .node_indices()
+            .filter(|n| graph[*n].is_state())
+            .map(|n| (n, if right_phi.contains(&n) { 1.0 } else { 0.0 }))
+            .collect();
+        for _ in 0..self.steps {
+            let next: Vec<(NodeIndex, f64)> = s_q
+                .iter()
+                .map(|state| {
+                    let prob = graph
+                        .neighbors_directed(*state, Outgoing)
+                        .map(|action| {
+                            graph
+                                .edges_directed(action, Outgoing)
+                                .fold(0.0, |sum, e| sum + e.weight() * prob_map[&e.target()])
+                        })
+                        .reduce(|p1, p2| match comp.is_upper_bound() {
+                            true => p1.max(p2),
+                            false => p1.min(p2),
+                        })
+                        .unwrap_or(0.0);
+                    (*state, prob)
+                })
+                .collect();
+            prob_map.extend(next);
+        }
+        Ok(prob_map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::McspError;
+    use crate::input_graph::ParseImpl;
+    use crate::logic::{parse_formula, LogicType};
+    use crate::mcsp::{check, CheckOptions, CheckResult, Solver};
+    use crate::parser::dpn_parser::DPetriNetParser;
+    use crate::testing::{check_net, check_petri_net, CHOICE_NET, MAX_REACH_C, MIN_REACH_C};
+
+    const QUERIES: [(&str, f64); 2] =
+        [("Pmax=? [F c]", MAX_REACH_C), ("Pmin=? [F c]", MIN_REACH_C)];
+
+    fn check_query(query: &str, options: &CheckOptions) -> CheckResult<Vec<usize>> {
+        check_net(CHOICE_NET, LogicType::Pctl, query, options)
+    }
+
+    fn options(solver: Solver) -> CheckOptions {
+        CheckOptions {
+            max_error: 1e-4,
+            precision_digits: 6,
+            solver,
+            ..Default::default()
+        }
+    }
+
+    /// The tokens circle between A, B and C until X fires, the goal is reached once they are all
+    /// in C. The markings before X fires form one strongly connected component of 276 states,
+    /// which is large enough to be swept in parallel
+    const TOKEN_NET: &str = "
+P = {A, B, C, X, D}
+G = {
+        {A} -> t1 -> {B},
+        {B} -> t2 -> {C},
+        {C} -> t3 -> {A},
+        {A} -> t4 -> {C},
+        {X} -> t5 -> {D}
+}
+C = {t4}
+M = (22, 0, 0, 1, 0)
+L = (1.0, 1.0, 1.0, 1.0, 0.05)
+AP = {
+    (c, {(0,0,22,1,0)})
+}
+";
+
+    /// A fires one of three transitions with the same rate, whose probabilities are rounded to
+    /// 0.3 with a single digit. B is reached with probability 1/2 after normalising them
+    const ROUNDED_NET: &str = "
+P = {A, B, C}
+G = {
+        {A} -> t1 -> {A},
+        {A} -> t2 -> {B},
+        {A} -> t3 -> {C}
+}
+M = (1, 0, 0)
+L = (1.0, 1.0, 1.0)
+AP = {
+    (b, {(0,1,0)})
+}
+";
+
+    #[test]
+    fn rounded_probabilities_add_up_to_1_in_every_operator() {
+        let options = CheckOptions {
+            precision_digits: 1,
+            ..options(Solver::Topological)
+        };
+        let value = |phi| {
+            check_petri_net(ROUNDED_NET, LogicType::Pctl, phi, &options)
+                .value
+                .unwrap()
+        };
+        let unbounded = value("P=? [F b]");
+        assert!((unbounded - 0.5).abs() <= 1e-4);
+        assert!((value("P=? [F<=100 b]") - unbounded).abs() <= 1e-4);
+        assert!((value("S=? [b]") - 0.5).abs() <= 1e-9);
+        assert!((value("S=? [!b]") - 0.5).abs() <= 1e-9);
+    }
+
+    #[test]
+    fn interval_iteration_bounds_contain_the_probability() {
+        for (query, expected) in QUERIES {
+            let result = check_query(query, &options(Solver::Value));
+            let bounds = result.value_bounds.unwrap();
+            assert!(bounds.lo <= expected && expected <= bounds.hi, "{}", query);
+            assert!(bounds.hi - bounds.lo <= 2e-4, "{}", query);
+            assert_eq!(result.value, Some(bounds.midpoint()));
+        }
+    }
+
+    #[test]
+    fn interval_iteration_fails_below_the_floating_point_precision() {
+        let content = format!("{}PHI = Pmax=? [F c]", CHOICE_NET);
+        let model = DPetriNetParser::parse(&content).unwrap();
+        let formula = parse_formula(LogicType::Pctl, &content).unwrap();
+        let options = CheckOptions {
+            max_error: 1e-20,
+            ..options(Solver::Value)
+        };
+        match check(model.as_ref(), &formula, &options) {
+            Err(McspError::PrecisionNotReached { width, max_error }) => {
+                assert!(width >= 2e-20);
+                assert_eq!(max_error, 1e-20);
+            }
+            _ => panic!("Interval iteration did not fail"),
+        }
+    }
+
+    #[test]
+    fn gauss_seidel_and_topological_agree_with_jacobi_iteration() {
+        for (query, expected) in QUERIES {
+            let jacobi = check_query(query, &options(Solver::Value)).value.unwrap();
+            for solver in [Solver::GaussSeidel, Solver::Topological] {
+                let result = check_query(query, &options(solver));
+                let bounds = result.value_bounds.unwrap();
+                assert!(
+                    bounds.lo <= expected && expected <= bounds.hi,
+                    "{:?}",
+                    solver
+                );
+                assert!(
+                    (result.value.unwrap() - jacobi).abs() <= 2e-4,
+                    "{:?}",
+                    solver
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn policy_and_linear_program_match_the_exact_probability() {
+        for (query, expected) in QUERIES {
+            let exact = CheckOptions {
+                exact: true,
+                ..options(Solver::Policy)
+            };
+            assert_eq!(
+                check_query(query, &exact).value,
+                Some(expected),
+                "{}",
+                query
+            );
+            for solver in [Solver::Policy, Solver::Lp] {
+                let value = check_query(query, &options(solver)).value.unwrap();
+                assert!((value - expected).abs() < 1e-5, "{:?} {}", solver, query);
+            }
+        }
+    }
+
+    #[test]
+    fn threads_do_not_change_the_probabilities() {
+        for solver in [Solver::Value, Solver::Topological] {
+            for query in ["Pmax=? [F c]", "Pmin=? [F c]"] {
+                let [single, parallel] = [1, 4].map(|threads| {
+                    let options = CheckOptions {
+                        threads,
+                        ..options(solver)
+                    };
+                    check_net(TOKEN_NET, LogicType::Pctl, query, &options)
+                });
+                assert!(single.value.unwrap() > 0.0, "{:?} {}", solver, query);
+                assert_eq!(single.value, parallel.value, "{:?} {}", solver, query);
+                assert_eq!(single.probabilities, parallel.probabilities);
+            }
+        }
+    }
+
+    #[test]
+    fn quantitative_queries_report_the_probabilities_of_every_marking() {
+        let result = check_petri_net(
+            ROUNDED_NET,
+            LogicType::Pctl,
+            "P=? [F b]",
+            &options(Solver::Topological),
+        );
+        assert!(!result.satisfied);
+        assert!((result.value.unwrap() - 0.5).abs() <= 1e-4);
+        assert_eq!(result.probabilities.len(), 3);
+        let b = result.probabilities[&vec![0, 1, 0]];
+        assert_eq!((b.min, b.max), (1.0, 1.0));
+        let c = result.probabilities[&vec![0, 0, 1]];
+        assert_eq!((c.min, c.max), (0.0, 0.0));
+
+        let threshold = check_petri_net(
+            ROUNDED_NET,
+            LogicType::Pctl,
+            "P(F(b), >= 0.4)",
+            &options(Solver::Topological),
+        );
+        assert!(threshold.satisfied);
+        assert_eq!(threshold.value, None);
+    }
+
+    #[test]
+    fn quantitative_queries_need_min_or_max_with_choices() {
+        let content = format!("{}PHI = P=? [F c]", CHOICE_NET);
+        let model = DPetriNetParser::parse(&content).unwrap();
+        let formula = parse_formula(LogicType::Pctl, &content).unwrap();
+        let error = check(model.as_ref(), &formula, &options(Solver::Topological)).err();
+        assert_eq!(error, Some(McspError::NondeterministicQuery));
+    }
+
+    #[test]
+    fn bounded_until_counts_the_steps() {
+        let value = |query| {
+            check_query(query, &options(Solver::Topological))
+                .value
+                .unwrap()
+        };
+        // C is two steps away from A and reached from B with probability 1/2 at most and 1/4
+        // at least
+        assert_eq!(value("Pmax=? [F<=1 c]"), 0.0);
+        assert!((value("Pmax=? [F<=2 c]") - 0.25).abs() < 1e-6);
+        assert!((value("Pmin=? [F<=2 c]") - 0.125).abs() < 1e-6);
+        assert!((value("Pmax=? [!d U<=2 c]") - 0.25).abs() < 1e-6);
+        // A violates d, so the path fails in the first step
+        assert_eq!(value("Pmax=? [d U<=2 c]"), 0.0);
+        assert!((value("Pmax=? [F<=200 c]") - MAX_REACH_C).abs() < 1e-4);
     }
 }
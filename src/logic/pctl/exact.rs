@@ -0,0 +1,210 @@
+use super::solver::evaluate_policy;
+use crate::error::McspError;
+use crate::mcsp::PctlInfo;
+use crate::utils::common::Comp;
+use crate::utils::rational::Rational;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Outgoing;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Exact probabilities of the edges from actions to states, keyed by edge
+pub type ExactWeights = HashMap<EdgeIndex, Rational>;
+
+/// Exact probability of reaching a phi-state in one step. Upper bounds (see `comp`) use the
+/// maximal probability, lower bounds the minimal one
+pub fn next_probabilities(
+    pctl_info: &PctlInfo,
+    weights: &ExactWeights,
+    phi: &HashSet<NodeIndex>,
+    comp: &Comp,
+) -> Result<HashMap<NodeIndex, Rational>, McspError> {
+    let graph = &pctl_info.reach_graph;
+    let mut prob_map = HashMap::new();
+    for state in graph.node_indices().filter(|n| graph[*n].is_state()) {
+        let mut prob: Option<Rational> = None;
+        for action in graph.neighbors_directed(state, Outgoing) {
+            let mut sum = Rational::ZERO;
+            for edge in graph.edges_directed(action, Outgoing) {
+                if phi.contains(&edge.target()) {
+                    sum = add(sum, weights[&edge.id()])?;
+                }
+            }
+            prob = Some(optimum(prob, sum, comp));
+        }
+        prob_map.insert(state, prob.unwrap_or(Rational::ZERO));
+    }
+    Ok(prob_map)
+}
+
+/// Exact probability of reaching `s_1` while staying in `s_q`, computed by policy iteration.
+/// Every policy is evaluated by solving its sparse linear equation system exactly, which only
+/// works as long as the fractions fit into 128 bits
+pub fn until_probabilities(
+    pctl_info: &PctlInfo,
+    weights: &ExactWeights,
+    s_1: &HashSet<NodeIndex>,
+    s_q: &HashSet<NodeIndex>,
+    comp: &Comp,
+) -> Result<HashMap<NodeIndex, Rational>, McspError> {
+    let graph = &pctl_info.reach_graph;
+    let mut unknown: BTreeSet<NodeIndex> = s_q.iter().copied().collect();
+    if !comp.is_upper_bound() {
+        // States in which the scheduler can avoid s_1 forever have a minimal probability of 0.
+        // The remaining states can not stay in s_q forever under any policy
+        let avoiding = avoiding_states(pctl_info, s_1, s_q);
+        unknown.retain(|s| !avoiding.contains(s));
+    }
+    let mut policy: BTreeMap<NodeIndex, NodeIndex> = unknown
+        .iter()
+        .map(|s| (*s, graph.neighbors_directed(*s, Outgoing).min().unwrap()))
+        .collect();
+    loop {
+        let values = evaluate_policy(pctl_info, s_1, &policy, |_, _, edge| weights[&edge])
+            .ok_or(McspError::ExactOverflow)?;
+        let mut changed = false;
+        for state in &unknown {
+            let mut best = values[state];
+            for action in graph.neighbors_directed(*state, Outgoing) {
+                let value = action_value(pctl_info, weights, s_1, &values, action)?;
+                // Only strict improvements switch the action, otherwise the iteration may cycle
+                if optimum(Some(best), value, comp) != best {
+                    best = value;
+                    policy.insert(*state, action);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            let mut prob_map: HashMap<NodeIndex, Rational> = graph
+                .node_indices()
+                .filter(|n| graph[*n].is_state())
+                .map(|n| (n, Rational::ZERO))
+                .collect();
+            prob_map.extend(s_1.iter().map(|s| (*s, Rational::ONE)));
+            prob_map.extend(values);
+            return Ok(prob_map);
+        }
+    }
+}
+
+fn action_value(
+    pctl_info: &PctlInfo,
+    weights: &ExactWeights,
+    s_1: &HashSet<NodeIndex>,
+    values: &HashMap<NodeIndex, Rational>,
+    action: NodeIndex,
+) -> Result<Rational, McspError> {
+    let mut sum = Rational::ZERO;
+    for edge in pctl_info.reach_graph.edges_directed(action, Outgoing) {
+        let target_value = match s_1.contains(&edge.target()) {
+            true => Rational::ONE,
+            false => values
+                .get(&edge.target())
+                .copied()
+                .unwrap_or(Rational::ZERO),
+        };
+        let product = weights[&edge.id()]
+            .checked_mul(target_value)
+            .ok_or(McspError::ExactOverflow)?;
+        sum = add(sum, product)?;
+    }
+    Ok(sum)
+}
+
+/// States of `s_q` with an action which never leads to `s_1`, directly or later on
+fn avoiding_states(
+    pctl_info: &PctlInfo,
+    s_1: &HashSet<NodeIndex>,
+    s_q: &HashSet<NodeIndex>,
+) -> HashSet<NodeIndex> {
+    let graph = &pctl_info.reach_graph;
+    // States outside of s_1 and s_q never reach s_1
+    let mut avoiding: HashSet<NodeIndex> = s_q.clone();
+    loop {
+        let removed: Vec<NodeIndex> = avoiding
+            .iter()
+            .filter(|s| {
+                !graph.neighbors_directed(**s, Outgoing).any(|action| {
+                    graph
+                        .neighbors_directed(action, Outgoing)
+                        .all(|t| avoiding.contains(&t) || !(s_1.contains(&t) || s_q.contains(&t)))
+                })
+            })
+            .copied()
+            .collect();
+        if removed.is_empty() {
+            return avoiding;
+        }
+        removed.iter().for_each(|s| {
+            avoiding.remove(s);
+        });
+    }
+}
+
+fn add(left: Rational, right: Rational) -> Result<Rational, McspError> {
+    left.checked_add(right).ok_or(McspError::ExactOverflow)
+}
+
+fn optimum(current: Option<Rational>, value: Rational, comp: &Comp) -> Rational {
+    match (current, comp.is_upper_bound()) {
+        (None, _) => value,
+        (Some(p), true) => p.max(value),
+        (Some(p), false) => p.min(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::McspError;
+    use crate::input_graph::ParseImpl;
+    use crate::logic::{parse_formula, LogicType};
+    use crate::mcsp::{check, CheckOptions};
+    use crate::parser::petri_net_parser::PetriNetParser;
+    use crate::utils::rational::Rational;
+
+    /// A fires one of three transitions with the same rate. The probabilities 1/3 are rounded
+    /// to 0.3 with a single digit, which the exact mode never does
+    const NET: &str = "
+P = {A, B, C}
+G = {
+        {A} -> t1 -> {A},
+        {A} -> t2 -> {B},
+        {A} -> t3 -> {C}
+}
+M = (1, 0, 0)
+L = (1.0, 1.0, RATE)
+AP = {
+    (b, {(0,1,0)})
+}
+";
+
+    fn check_exact(rate: &str, phi: &str) -> Result<Option<Rational>, McspError> {
+        let content = format!("{}PHI = {}", NET.replace("RATE", rate), phi);
+        let model = PetriNetParser::parse(&content).unwrap();
+        let formula = parse_formula(LogicType::Pctl, &content).unwrap();
+        let options = CheckOptions {
+            exact: true,
+            precision_digits: 1,
+            ..Default::default()
+        };
+        check(model.as_ref(), &formula, &options).map(|result| result.exact_value)
+    }
+
+    #[test]
+    fn computes_fractions_of_the_rates() {
+        let fraction = |numer, denom| Some(Rational::new(numer, denom).unwrap());
+        assert_eq!(check_exact("1.0", "P=? [X b]"), Ok(fraction(1, 3)));
+        assert_eq!(check_exact("1.0", "P=? [F b]"), Ok(fraction(1, 2)));
+        assert_eq!(check_exact("0.5", "P=? [F b]"), Ok(fraction(2, 3)));
+    }
+
+    #[test]
+    fn fails_if_a_fraction_overflows() {
+        let rate = format!("1{}.0", "0".repeat(40));
+        assert_eq!(
+            check_exact(&rate, "P=? [F b]"),
+            Err(McspError::ExactOverflow)
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use super::{min_max, StatePhi};
+use crate::error::McspError;
+use crate::logic::mec::{maximal_end_components, EndComponent};
+use crate::mcsp::{PctlInfo, Probability};
+use crate::utils::common::Comp;
+use crate::utils::linear::solve;
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Outgoing;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Long-run (steady-state) operator comparing the fraction of time spent in phi-markings with a
+/// bound
+pub struct LongRun {
+    pub phi: Box<dyn StatePhi>,
+    pub comp: Comp,
+    pub probability: f64,
+}
+
+impl StatePhi for LongRun {
+    fn fmt(&self) -> String {
+        format!("S(({}), {} {})", self.phi, self.comp, self.probability)
+    }
+
+    fn evaluate_inner(&self, pctl_info: &PctlInfo) -> Result<HashSet<NodeIndex>, McspError> {
+        Ok(long_run_averages(pctl_info, self.phi.as_ref(), &self.comp)?
+            .into_iter()
+            .filter(|(_, p)| self.comp.evaluate(*p, self.probability))
+            .map(|(n, _)| n)
+            .collect())
+    }
+
+    fn evaluate_probabilities(
+        &self,
+        pctl_info: &PctlInfo,
+    ) -> Result<(HashSet<NodeIndex>, HashMap<NodeIndex, Probability>), McspError> {
+        let min = long_run_averages(pctl_info, self.phi.as_ref(), &Comp::Geq)?;
+        let max = long_run_averages(pctl_info, self.phi.as_ref(), &Comp::Leq)?;
+        let bound_probs = match self.comp.is_upper_bound() {
+            true => &max,
+            false => &min,
+        };
+        let nodes = bound_probs
+            .iter()
+            .filter(|(_, p)| self.comp.evaluate(**p, self.probability))
+            .map(|(n, _)| *n)
+            .collect();
+        Ok((nodes, min_max(&min, &max)))
+    }
+}
+
+/// Expected long-run fraction of time spent in phi-markings for every state. Upper bounds (see
+/// `comp`) use the maximum over all schedulers, lower bounds the minimum
+pub fn long_run_averages(
+    pctl_info: &PctlInfo,
+    phi: &dyn StatePhi,
+    comp: &Comp,
+) -> Result<HashMap<NodeIndex, f64>, McspError> {
+    let graph = &pctl_info.reach_graph;
+    let states: HashSet<NodeIndex> = graph
+        .node_indices()
+        .filter(|n| graph[*n].is_state())
+        .collect();
+    let phi_states = phi.evaluate_inner(pctl_info)?;
+    Ok(optimal_long_run_averages(
+        pctl_info,
+        &states,
+        &phi_states,
+        comp.is_upper_bound(),
+    ))
+}
+
+/// Improvements smaller than this do not switch the choice of a policy
+const EPSILON: f64 = 1e-12;
+
+/// Every path eventually stays in a maximal end component, in which the scheduler can achieve
+/// its optimal average from every state. The value of a state is the best expected average of
+/// the end component a scheduler can steer the path into, the largest one if `maximize` is set
+/// and the smallest one otherwise. It is computed by policy iteration, in which staying in the
+/// end component with its average is one more choice of its states
+fn optimal_long_run_averages(
+    pctl_info: &PctlInfo,
+    states: &HashSet<NodeIndex>,
+    targets: &HashSet<NodeIndex>,
+    maximize: bool,
+) -> HashMap<NodeIndex, f64> {
+    let graph = &pctl_info.reach_graph;
+    let mut mec_values: HashMap<NodeIndex, f64> = HashMap::new();
+    for mec in maximal_end_components(graph, states) {
+        let value = mec_average(pctl_info, &mec, targets, maximize);
+        mec_values.extend(mec.states.iter().map(|s| (*s, value)));
+    }
+    // None stays in the end component of the state. Every other state starts with an action, and
+    // any action leads towards the end components, so the first policy leaves no state forever
+    let mut policy: BTreeMap<NodeIndex, Option<NodeIndex>> = states
+        .iter()
+        .map(|s| match mec_values.contains_key(s) {
+            true => (*s, None),
+            false => (*s, graph.neighbors_directed(*s, Outgoing).min()),
+        })
+        .collect();
+    loop {
+        let values = evaluate_policy(pctl_info, &policy, &mec_values);
+        let mut changed = false;
+        for (state, choice) in policy.iter_mut() {
+            let mut best = match choice {
+                Some(action) => action_value(pctl_info, *action, &values),
+                None => values[state],
+            };
+            let stay = mec_values.get(state).map(|v| (None, *v));
+            let actions = graph
+                .neighbors_directed(*state, Outgoing)
+                .map(|action| (Some(action), action_value(pctl_info, action, &values)));
+            for (option, value) in stay.into_iter().chain(actions) {
+                let improves = match maximize {
+                    true => value > best + EPSILON,
+                    false => value < best - EPSILON,
+                };
+                if improves {
+                    best = value;
+                    *choice = option;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return values;
+        }
+    }
+}
+
+/// Values of the states under the policy, solving the linear equation system of the induced
+/// chain
+fn evaluate_policy(
+    pctl_info: &PctlInfo,
+    policy: &BTreeMap<NodeIndex, Option<NodeIndex>>,
+    mec_values: &HashMap<NodeIndex, f64>,
+) -> HashMap<NodeIndex, f64> {
+    let graph = &pctl_info.reach_graph;
+    let index: HashMap<NodeIndex, usize> =
+        policy.keys().enumerate().map(|(i, s)| (*s, i)).collect();
+    let mut rows = Vec::with_capacity(policy.len());
+    let mut rhs = Vec::with_capacity(policy.len());
+    for (state, choice) in policy {
+        let mut row = BTreeMap::from([(index[state], 1.0)]);
+        match choice {
+            Some(action) => {
+                for edge in graph.edges_directed(*action, Outgoing) {
+                    *row.entry(index[&edge.target()]).or_insert(0.0) -= edge.weight();
+                }
+                rhs.push(0.0);
+            }
+            None => rhs.push(mec_values.get(state).copied().unwrap_or(0.0)),
+        }
+        rows.push(row);
+    }
+    // Policies only improve on one that leaves every state for an end component, so they stay
+    // in end components almost surely and the system is a nonsingular M-matrix
+    let values = solve(rows, rhs).expect("The policy does not reach an end component");
+    policy.keys().zip(values).map(|(s, v)| (*s, v)).collect()
+}
+
+fn action_value(pctl_info: &PctlInfo, action: NodeIndex, values: &HashMap<NodeIndex, f64>) -> f64 {
+    pctl_info
+        .reach_graph
+        .edges_directed(action, Outgoing)
+        .fold(0.0, |sum, e| sum + e.weight() * values[&e.target()])
+}
+
+/// Optimal long-run fraction of time spent in the targets within the end component. Without a
+/// choice inside the end component it is a Markov chain, whose average follows exactly from the
+/// stationary distribution
+fn mec_average(
+    pctl_info: &PctlInfo,
+    mec: &EndComponent,
+    targets: &HashSet<NodeIndex>,
+    maximize: bool,
+) -> f64 {
+    let graph = &pctl_info.reach_graph;
+    let chain: Option<Vec<(NodeIndex, NodeIndex)>> = mec
+        .states
+        .iter()
+        .map(|state| {
+            let mut actions = mec.actions_of(graph, *state);
+            match (actions.next(), actions.next()) {
+                (Some(action), None) => Some((*state, action)),
+                _ => None,
+            }
+        })
+        .collect();
+    match chain {
+        Some(chain) => stationary_average(pctl_info, &chain, targets),
+        None => mean_payoff(pctl_info, mec, targets, maximize),
+    }
+}
+
+/// Long-run fraction of time the Markov chain of the states and their only actions spends in the
+/// targets. Equation j of the system states that the stationary probability of state j is what
+/// flows into it, the last one is replaced by the probabilities summing up to 1
+fn stationary_average(
+    pctl_info: &PctlInfo,
+    chain: &[(NodeIndex, NodeIndex)],
+    targets: &HashSet<NodeIndex>,
+) -> f64 {
+    let graph = &pctl_info.reach_graph;
+    let index: HashMap<NodeIndex, usize> = chain
+        .iter()
+        .enumerate()
+        .map(|(i, (s, _))| (*s, i))
+        .collect();
+    let mut rows: Vec<BTreeMap<usize, f64>> = (0..chain.len())
+        .map(|i| BTreeMap::from([(i, 1.0)]))
+        .collect();
+    for (i, (_, action)) in chain.iter().enumerate() {
+        // Edges with probability 0 may leave the end component
+        for edge in graph
+            .edges_directed(*action, Outgoing)
+            .filter(|e| *e.weight() > 0.0)
+        {
+            *rows[index[&edge.target()]].entry(i).or_insert(0.0) -= edge.weight();
+        }
+    }
+    let last = chain.len() - 1;
+    rows[last] = (0..chain.len()).map(|i| (i, 1.0)).collect();
+    let mut rhs = vec![0.0; chain.len()];
+    rhs[last] = 1.0;
+    // Every proper subset of an irreducible chain is left almost surely, so all leading pivots
+    // belong to nonsingular M-matrices, and the full system has a unique solution
+    let distribution = solve(rows, rhs).expect("The end component is not strongly connected");
+    chain
+        .iter()
+        .zip(distribution)
+        .filter(|((s, _), _)| targets.contains(s))
+        .map(|(_, p)| p)
+        .sum()
+}
+
+/// Optimal long-run fraction of time spent in the targets within the end component with choices,
+/// computed by relative value iteration. Every step stays in the current state with probability
+/// 1/2, which makes the iteration converge without changing the average
+fn mean_payoff(
+    pctl_info: &PctlInfo,
+    mec: &EndComponent,
+    targets: &HashSet<NodeIndex>,
+    maximize: bool,
+) -> f64 {
+    let graph = &pctl_info.reach_graph;
+    let mut values: HashMap<NodeIndex, f64> = mec.states.iter().map(|s| (*s, 0.0)).collect();
+    loop {
+        let next: HashMap<NodeIndex, f64> = mec
+            .states
+            .iter()
+            .map(|state| {
+                let reward = if targets.contains(state) { 1.0 } else { 0.0 };
+                let successors = mec.actions_of(graph, *state).map(|action| {
+                    // Edges with probability 0 may leave the end component
+                    graph
+                        .edges_directed(action, Outgoing)
+                        .filter(|e| *e.weight() > 0.0)
+                        .fold(0.0, |sum, e| sum + e.weight() * values[&e.target()])
+                });
+                let successors = match maximize {
+                    true => successors.fold(f64::NEG_INFINITY, f64::max),
+                    false => successors.fold(f64::INFINITY, f64::min),
+                };
+                (*state, reward + 0.5 * values[state] + 0.5 * successors)
+            })
+            .collect();
+        let differences = mec.states.iter().map(|s| next[s] - values[s]);
+        let lower = differences.clone().fold(f64::INFINITY, f64::min);
+        let upper = differences.fold(f64::NEG_INFINITY, f64::max);
+        // The average lies between the smallest and the largest difference
+        if upper - lower < pctl_info.max_error {
+            return (lower + upper) / 2.0;
+        }
+        values = next.iter().map(|(s, v)| (*s, v - lower)).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::LogicType;
+    use crate::mcsp::CheckOptions;
+    use crate::testing::{check_net, check_petri_net};
+
+    /// The chain leaves P1 and P2 for the cycle of P3 and P4, in which it spends a quarter of the
+    /// steps in P3
+    const CHAIN: &str = "
+P = {P1, P2, P3, P4}
+G = {
+        {P1} -> t1 -> {P2},
+        {P2} -> t2 -> {P1},
+        {P2} -> t3 -> {P3},
+        {P3} -> t4 -> {P4},
+        {P4} -> t5 -> {P3},
+        {P4} -> t6 -> {P4}
+}
+M = (1, 0, 0, 0)
+L = (1.0, 3.0, 0.1, 1.0, 1.0, 2.0)
+AP = {
+    (a, {(1,0,0,0)}),
+    (c, {(0,0,1,0)})
+}
+";
+
+    /// Enabling t2 keeps the net in P1 for half of the steps from there, so it spends between
+    /// 1/2 and 2/3 of the time in P1
+    const CHOICE: &str = "
+P = {P1, P2}
+G = {
+        {P1} -> t1 -> {P2},
+        {P1} -> t2 -> {P1},
+        {P2} -> t3 -> {P1}
+}
+C = {t2}
+M = (1, 0)
+L = (1.0, 1.0, 1.0)
+AP = {
+    (a, {(1,0)})
+}
+";
+
+    fn chain_value(phi: &str) -> f64 {
+        let options = CheckOptions {
+            precision_digits: 15,
+            ..Default::default()
+        };
+        check_petri_net(CHAIN, LogicType::Pctl, phi, &options)
+            .value
+            .unwrap()
+    }
+
+    #[test]
+    fn long_run_averages_of_chains_are_exact() {
+        assert_eq!(chain_value("S=? [a]"), 0.0);
+        assert_eq!(chain_value("Smin=? [a]"), 0.0);
+        assert_eq!(chain_value("Smax=? [a]"), 0.0);
+        assert!((chain_value("S=? [c]") - 0.25).abs() < 1e-12);
+        assert_eq!(chain_value("Smin=? [c]"), chain_value("Smax=? [c]"));
+    }
+
+    #[test]
+    fn long_run_averages_depend_on_the_scheduler() {
+        let options = CheckOptions {
+            max_error: 1e-6,
+            ..Default::default()
+        };
+        let value = |phi| {
+            check_net(CHOICE, LogicType::Pctl, phi, &options)
+                .value
+                .unwrap()
+        };
+        assert!((value("Smin=? [a]") - 0.5).abs() < 1e-4);
+        assert!((value("Smax=? [a]") - 2.0 / 3.0).abs() < 1e-4);
+    }
+}
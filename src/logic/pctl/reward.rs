@@ -0,0 +1,381 @@
+use super::{min_max, prob1_all, prob1_exists, StatePhi};
+use crate::error::McspError;
+use crate::input_graph::MDP;
+use crate::mcsp::{PctlInfo, Probability};
+use crate::utils::common::Comp;
+use crate::utils::linear::solve;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Outgoing;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Display;
+
+/// Reward formula of the R operator
+pub trait RewardPhi: Send + Sync {
+    fn fmt(&self) -> String;
+
+    /// Expected reward of every state. Upper bounds (see `comp`) use the maximal reward, lower
+    /// bounds the minimal one
+    fn rewards(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, f64>, McspError>;
+}
+
+impl Display for dyn RewardPhi {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.fmt())
+    }
+}
+
+/// R operator comparing the expected reward with a bound
+pub struct Reward {
+    pub phi: Box<dyn RewardPhi>,
+    pub comp: Comp,
+    pub bound: f64,
+}
+
+impl StatePhi for Reward {
+    fn fmt(&self) -> String {
+        format!("R(({}), {} {})", self.phi, self.comp, self.bound)
+    }
+
+    fn evaluate_inner(&self, pctl_info: &PctlInfo) -> Result<HashSet<NodeIndex>, McspError> {
+        Ok(self
+            .phi
+            .rewards(pctl_info, &self.comp)?
+            .into_iter()
+            .filter(|(_, r)| self.comp.evaluate(*r, self.bound))
+            .map(|(n, _)| n)
+            .collect())
+    }
+
+    fn evaluate_probabilities(
+        &self,
+        pctl_info: &PctlInfo,
+    ) -> Result<(HashSet<NodeIndex>, HashMap<NodeIndex, Probability>), McspError> {
+        let min = self.phi.rewards(pctl_info, &Comp::Geq)?;
+        let max = self.phi.rewards(pctl_info, &Comp::Leq)?;
+        let bound_rewards = match self.comp.is_upper_bound() {
+            true => &max,
+            false => &min,
+        };
+        let nodes = bound_rewards
+            .iter()
+            .filter(|(_, r)| self.comp.evaluate(**r, self.bound))
+            .map(|(n, _)| *n)
+            .collect();
+        Ok((nodes, min_max(&min, &max)))
+    }
+}
+
+/// Reward accumulated until a goal state is reached. Infinite if the goal is missed with a
+/// positive probability
+pub struct ReachReward {
+    pub goal: Box<dyn StatePhi>,
+}
+
+impl RewardPhi for ReachReward {
+    fn fmt(&self) -> String {
+        format!("F ({})", self.goal)
+    }
+
+    fn rewards(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, f64>, McspError> {
+        let graph = &pctl_info.reach_graph;
+        let goal = self.goal.evaluate_inner(pctl_info)?;
+        let states: HashSet<NodeIndex> = graph
+            .node_indices()
+            .filter(|n| graph[*n].is_state())
+            .collect();
+        // The maximizing scheduler avoids the goal whenever it can, the minimizing one only
+        // needs one way of reaching it almost surely
+        let finite = match comp.is_upper_bound() {
+            true => prob1_all(graph, &states, &goal),
+            false => prob1_exists(graph, &states, &goal),
+        };
+        let mut s_q: Vec<NodeIndex> = finite.difference(&goal).copied().collect();
+        s_q.sort();
+        // Maximal rewards rise from 0. Minimal ones would get stuck at 0 in cycles without any
+        // reward, although staying in them never reaches the goal, so they fall from the rewards
+        // of a scheduler reaching the goal almost surely instead
+        let initial = match comp.is_upper_bound() {
+            true => vec![0.0; s_q.len()],
+            false => {
+                let scheduler = proper_scheduler(graph, &finite, &goal);
+                scheduler_rewards(pctl_info, &s_q, &scheduler)
+            }
+        };
+        let mut reward_map: HashMap<NodeIndex, f64> = states
+            .iter()
+            .map(|n| match finite.contains(n) {
+                true => (*n, 0.0),
+                false => (*n, f64::INFINITY),
+            })
+            .collect();
+        reward_map.extend(s_q.iter().copied().zip(initial));
+        loop {
+            let mut max_error: f64 = 0.0;
+            for state in &s_q {
+                // Actions which may leave the finite states have an infinite reward
+                let reward = optimum(
+                    graph
+                        .neighbors_directed(*state, Outgoing)
+                        .filter(|action| successors(graph, *action).all(|t| finite.contains(&t)))
+                        .map(|action| {
+                            pctl_info.rewards.states[state]
+                                + expected_reward(pctl_info, action, &reward_map)
+                        }),
+                    comp,
+                );
+                max_error = max_error.max((reward_map[state] - reward).abs());
+                reward_map.insert(*state, reward);
+            }
+            if max_error < pctl_info.max_error {
+                break;
+            }
+        }
+        Ok(reward_map)
+    }
+}
+
+/// Reward accumulated within the first `steps` transitions
+pub struct CumulativeReward {
+    pub steps: usize,
+}
+
+impl RewardPhi for CumulativeReward {
+    fn fmt(&self) -> String {
+        format!("C<={}", self.steps)
+    }
+
+    fn rewards(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, f64>, McspError> {
+        let graph = &pctl_info.reach_graph;
+        let mut reward_map: HashMap<NodeIndex, f64> =
+            pctl_info.rewards.states.keys().map(|n| (*n, 0.0)).collect();
+        for _ in 0..self.steps {
+            reward_map = reward_map
+                .keys()
+                .map(|state| {
+                    let reward = optimum(
+                        graph
+                            .neighbors_directed(*state, Outgoing)
+                            .map(|action| expected_reward(pctl_info, action, &reward_map)),
+                        comp,
+                    );
+                    (*state, pctl_info.rewards.states[state] + reward)
+                })
+                .collect();
+        }
+        Ok(reward_map)
+    }
+}
+
+/// State reward of the marking reached after exactly `steps` transitions
+pub struct InstantaneousReward {
+    pub steps: usize,
+}
+
+impl RewardPhi for InstantaneousReward {
+    fn fmt(&self) -> String {
+        format!("I={}", self.steps)
+    }
+
+    fn rewards(
+        &self,
+        pctl_info: &PctlInfo,
+        comp: &Comp,
+    ) -> Result<HashMap<NodeIndex, f64>, McspError> {
+        let graph = &pctl_info.reach_graph;
+        let mut reward_map = pctl_info.rewards.states.clone();
+        for _ in 0..self.steps {
+            reward_map = reward_map
+                .keys()
+                .map(|state| {
+                    let reward = optimum(
+                        graph.neighbors_directed(*state, Outgoing).map(|action| {
+                            graph
+                                .edges_directed(action, Outgoing)
+                                .fold(0.0, |sum, e| sum + e.weight() * reward_map[&e.target()])
+                        }),
+                        comp,
+                    );
+                    (*state, reward)
+                })
+                .collect();
+        }
+        Ok(reward_map)
+    }
+}
+
+/// Scheduler reaching the goal almost surely from every state of `finite`. Every state takes an
+/// action staying in `finite` which leads closer to the goal with a positive probability
+fn proper_scheduler(
+    graph: &MDP<NodeIndex>,
+    finite: &HashSet<NodeIndex>,
+    goal: &HashSet<NodeIndex>,
+) -> HashMap<NodeIndex, NodeIndex> {
+    let mut scheduler = HashMap::new();
+    let mut reaching = goal.clone();
+    loop {
+        let added: Vec<(NodeIndex, NodeIndex)> = finite
+            .iter()
+            .filter(|s| !reaching.contains(s))
+            .filter_map(|s| {
+                graph
+                    .neighbors_directed(*s, Outgoing)
+                    .find(|action| {
+                        successors(graph, *action).all(|t| finite.contains(&t))
+                            && successors(graph, *action).any(|t| reaching.contains(&t))
+                    })
+                    .map(|action| (*s, action))
+            })
+            .collect();
+        if added.is_empty() {
+            return scheduler;
+        }
+        reaching.extend(added.iter().map(|(s, _)| *s));
+        scheduler.extend(added);
+    }
+}
+
+/// Expected rewards of the states of `s_q` under the scheduler, solving the linear equation
+/// system of the induced chain
+fn scheduler_rewards(
+    pctl_info: &PctlInfo,
+    s_q: &[NodeIndex],
+    scheduler: &HashMap<NodeIndex, NodeIndex>,
+) -> Vec<f64> {
+    let graph = &pctl_info.reach_graph;
+    let index: HashMap<NodeIndex, usize> = s_q.iter().enumerate().map(|(i, s)| (*s, i)).collect();
+    let mut rows = Vec::with_capacity(s_q.len());
+    let mut rhs = Vec::with_capacity(s_q.len());
+    for state in s_q {
+        let action = scheduler[state];
+        let mut row = BTreeMap::from([(index[state], 1.0)]);
+        let mut reward = pctl_info.rewards.states[state];
+        for edge in graph.edges_directed(action, Outgoing) {
+            let p = *edge.weight();
+            reward += p * transition_reward(pctl_info, edge.id());
+            if let Some(target) = index.get(&edge.target()) {
+                *row.entry(*target).or_insert(0.0) -= p;
+            }
+        }
+        rows.push(row);
+        rhs.push(reward);
+    }
+    // The scheduler leaves s_q almost surely, so the system is a nonsingular M-matrix whose
+    // pivots stay positive
+    solve(rows, rhs).expect("The scheduler does not reach the goal almost surely")
+}
+
+/// Expected transition reward of the action plus the expected reward of its successors
+fn expected_reward(
+    pctl_info: &PctlInfo,
+    action: NodeIndex,
+    reward_map: &HashMap<NodeIndex, f64>,
+) -> f64 {
+    let graph = &pctl_info.reach_graph;
+    graph.edges_directed(action, Outgoing).fold(0.0, |sum, e| {
+        let reward = transition_reward(pctl_info, e.id()) + reward_map[&e.target()];
+        sum + e.weight() * reward
+    })
+}
+
+fn transition_reward(pctl_info: &PctlInfo, edge: EdgeIndex) -> f64 {
+    pctl_info
+        .rewards
+        .transitions
+        .get(&edge)
+        .copied()
+        .unwrap_or(0.0)
+}
+
+fn optimum(rewards: impl Iterator<Item = f64>, comp: &Comp) -> f64 {
+    rewards
+        .reduce(|r1, r2| match comp.is_upper_bound() {
+            true => r1.max(r2),
+            false => r1.min(r2),
+        })
+        .unwrap_or(0.0)
+}
+
+fn successors(graph: &MDP<NodeIndex>, action: NodeIndex) -> impl Iterator<Item = NodeIndex> + '_ {
+    graph.neighbors_directed(action, Outgoing)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::LogicType;
+    use crate::mcsp::CheckOptions;
+    use crate::testing::check_net;
+
+    /// Cycling between P1 and P2 without reward never reaches the goal, so the minimum is the
+    /// reward of t3 instead of 0
+    const ZERO_REWARD_CYCLE: &str = "
+P = {P1, P2, P3}
+G = {
+        {P1} -> t1 -> {P2},
+        {P2} -> t2 -> {P1},
+        {P1} -> t3 -> {P3},
+        {P3} -> t4 -> {P3}
+}
+C = {t1, t2, t3, t4}
+M = (1, 0, 0)
+L = (0.5, 0.5, 0.5, 0.5)
+AP = {
+    (goal, {(0,0,1)})
+}
+RT = (0, 0, 5, 0)
+";
+
+    /// Leaving P4 costs 1 and reaches the goal with probability 1/2, so going through P4 until
+    /// the goal is reached costs 2 in expectation, less than the 5 of t3
+    const MIXED_CYCLES: &str = "
+P = {P1, P2, P3, P4}
+G = {
+        {P1} -> t1 -> {P2},
+        {P2} -> t2 -> {P1},
+        {P1} -> t3 -> {P3},
+        {P1} -> t5 -> {P4},
+        {P4} -> t6 -> {P3},
+        {P4} -> t7 -> {P1},
+        {P3} -> t4 -> {P3}
+}
+C = {t1, t2, t3, t4, t5}
+M = (1, 0, 0, 0)
+L = (0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5)
+AP = {
+    (goal, {(0,0,1,0)})
+}
+RT = (0, 0, 5, 0, 1, 1, 0)
+";
+
+    fn minimal_reward(net: &str) -> f64 {
+        let options = CheckOptions {
+            max_error: 1e-6,
+            ..Default::default()
+        };
+        check_net(net, LogicType::Pctl, "Rmin=? [F goal]", &options)
+            .value
+            .unwrap()
+    }
+
+    #[test]
+    fn minimal_reward_leaves_zero_reward_cycles() {
+        assert!((minimal_reward(ZERO_REWARD_CYCLE) - 5.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn minimal_reward_mixes_with_zero_reward_cycles() {
+        assert!((minimal_reward(MIXED_CYCLES) - 2.0).abs() < 1e-4);
+    }
+}
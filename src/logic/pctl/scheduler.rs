@@ -0,0 +1,252 @@
+use crate::input_graph::dpnet::disabled_transitions;
+use crate::input_graph::{Node, MDP};
+use crate::mcsp::Decision;
+use crate::utils::common::Comp;
+use petgraph::stable_graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::{Incoming, Outgoing};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Action a scheduler chooses in every state
+pub type Scheduler = HashMap<NodeIndex, NodeIndex>;
+
+/// Scheduler choosing the first action of every state
+pub fn default_scheduler(graph: &MDP<NodeIndex>) -> Scheduler {
+    graph
+        .node_indices()
+        .filter(|n| graph[*n].is_state())
+        .filter_map(|s| Some((s, graph.neighbors_directed(s, Outgoing).min()?)))
+        .collect()
+}
+
+/// Scheduler choosing an action with the best expected value (see `comp`) in every state.
+/// Expected values within `tolerance` of the best one count as optimal, the first of these
+/// actions is chosen
+pub fn greedy_scheduler(
+    graph: &MDP<NodeIndex>,
+    values: &HashMap<NodeIndex, f64>,
+    comp: &Comp,
+    tolerance: f64,
+) -> Scheduler {
+    let states: HashSet<NodeIndex> = graph
+        .node_indices()
+        .filter(|n| graph[*n].is_state())
+        .collect();
+    let mut scheduler = default_scheduler(graph);
+    for (state, actions) in optimal_actions(graph, &states, values, comp, tolerance) {
+        if let Some(action) = actions.first() {
+            scheduler.insert(state, *action);
+        }
+    }
+    scheduler
+}
+
+/// Memoryless scheduler attaining the probabilities `values` of reaching `s_1` while staying in
+/// `s_q`. Any optimal action attains the minimal probabilities. An optimal action for the
+/// maximal ones may circle in an end component forever though, so every state chooses an
+/// optimal action leading closer to `s_1` instead
+pub fn reachability_scheduler(
+    graph: &MDP<NodeIndex>,
+    s_1: &HashSet<NodeIndex>,
+    s_q: &HashSet<NodeIndex>,
+    values: &HashMap<NodeIndex, f64>,
+    comp: &Comp,
+    tolerance: f64,
+) -> Scheduler {
+    let optimal = optimal_actions(graph, s_q, values, comp, tolerance);
+    let mut scheduler = default_scheduler(graph);
+    if comp.is_upper_bound() {
+        // Backward search from s_1 over the optimal actions
+        let mut reached: HashSet<NodeIndex> = s_1.clone();
+        let mut queue: VecDeque<NodeIndex> = sorted(s_1).into();
+        while let Some(state) = queue.pop_front() {
+            let mut predecessors: Vec<(NodeIndex, NodeIndex)> = graph
+                .neighbors_directed(state, Incoming)
+                .flat_map(|a| graph.neighbors_directed(a, Incoming).map(move |s| (s, a)))
+                .collect();
+            predecessors.sort();
+            for (predecessor, action) in predecessors {
+                let is_optimal = optimal
+                    .get(&predecessor)
+                    .is_some_and(|actions| actions.contains(&action));
+                if is_optimal && reached.insert(predecessor) {
+                    scheduler.insert(predecessor, action);
+                    queue.push_back(predecessor);
+                }
+            }
+        }
+        // The remaining states have the probability 0 under every scheduler
+        for state in s_q.difference(&reached) {
+            if let Some(action) = optimal.get(state).and_then(|actions| actions.first()) {
+                scheduler.insert(*state, *action);
+            }
+        }
+    } else {
+        for (state, actions) in optimal {
+            if let Some(action) = actions.first() {
+                scheduler.insert(state, *action);
+            }
+        }
+    }
+    scheduler
+}
+
+/// Scheduler staying in the end component `nodes` forever and visiting `targets` infinitely
+/// often. Every state of the component chooses an action of it whose successors all belong to
+/// the component and which leads closer to the targets
+pub fn staying_scheduler(
+    graph: &MDP<NodeIndex>,
+    nodes: &HashSet<NodeIndex>,
+    targets: &HashSet<NodeIndex>,
+) -> Scheduler {
+    let staying = |action: NodeIndex| {
+        nodes.contains(&action)
+            && graph
+                .neighbors_directed(action, Outgoing)
+                .all(|s| nodes.contains(&s))
+    };
+    let mut scheduler = Scheduler::new();
+    for state in sorted(nodes).into_iter().filter(|n| graph[*n].is_state()) {
+        let mut actions: Vec<NodeIndex> = graph.neighbors_directed(state, Outgoing).collect();
+        actions.sort();
+        if let Some(action) = actions.into_iter().find(|a| staying(*a)) {
+            scheduler.insert(state, action);
+        }
+    }
+    let mut reached: HashSet<NodeIndex> = targets.clone();
+    let mut queue: VecDeque<NodeIndex> = sorted(targets).into();
+    while let Some(state) = queue.pop_front() {
+        let mut predecessors: Vec<(NodeIndex, NodeIndex)> = graph
+            .neighbors_directed(state, Incoming)
+            .filter(|a| staying(*a))
+            .flat_map(|a| graph.neighbors_directed(a, Incoming).map(move |s| (s, a)))
+            .collect();
+        predecessors.sort();
+        for (predecessor, action) in predecessors {
+            if reached.insert(predecessor) {
+                scheduler.insert(predecessor, action);
+                queue.push_back(predecessor);
+            }
+        }
+    }
+    scheduler
+}
+
+/// Decisions of the scheduler sorted by marking and memory. `state` returns the marking and the
+/// memory of the scheduler every state stands for
+pub fn decisions<K: Ord>(
+    graph: &MDP<NodeIndex>,
+    scheduler: &Scheduler,
+    state: impl Fn(NodeIndex) -> (K, Option<String>),
+) -> Vec<Decision<K>> {
+    let mut decisions: Vec<Decision<K>> = scheduler
+        .iter()
+        .map(|(s, action)| {
+            let (marking, memory) = state(*s);
+            let Node::Action(name) = &graph[*action] else {
+                unreachable!()
+            };
+            Decision {
+                marking,
+                memory,
+                disabled: disabled_transitions(name),
+            }
+        })
+        .collect();
+    decisions.sort_by(|d1, d2| (&d1.marking, &d1.memory).cmp(&(&d2.marking, &d2.memory)));
+    decisions
+}
+
+/// Actions of every state with an expected value within `tolerance` of the best one, in
+/// ascending order
+fn optimal_actions(
+    graph: &MDP<NodeIndex>,
+    states: &HashSet<NodeIndex>,
+    values: &HashMap<NodeIndex, f64>,
+    comp: &Comp,
+    tolerance: f64,
+) -> HashMap<NodeIndex, Vec<NodeIndex>> {
+    let expected_value = |action: NodeIndex| {
+        graph.edges_directed(action, Outgoing).fold(0.0, |sum, e| {
+            sum + e.weight() * values.get(&e.target()).unwrap_or(&0.0)
+        })
+    };
+    states
+        .iter()
+        .filter_map(|state| {
+            let mut actions: Vec<(NodeIndex, f64)> = graph
+                .neighbors_directed(*state, Outgoing)
+                .map(|a| (a, expected_value(a)))
+                .collect();
+            actions.sort_by_key(|(a, _)| *a);
+            let best =
+                actions
+                    .iter()
+                    .map(|(_, v)| *v)
+                    .reduce(|v1, v2| match comp.is_upper_bound() {
+                        true => v1.max(v2),
+                        false => v1.min(v2),
+                    })?;
+            let optimal = actions
+                .into_iter()
+                .filter(|(_, v)| (v - best).abs() <= tolerance)
+                .map(|(a, _)| a)
+                .collect();
+            Some((*state, optimal))
+        })
+        .collect()
+}
+
+fn sorted(set: &HashSet<NodeIndex>) -> Vec<NodeIndex> {
+    let mut nodes: Vec<NodeIndex> = set.iter().copied().collect();
+    nodes.sort();
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::LogicType;
+    use crate::mcsp::{CheckOptions, Decision};
+    use crate::report::{format_scheduler, OutputFormat};
+    use crate::testing::{check_net, CHOICE_NET};
+
+    fn scheduler(logic_type: LogicType, phi: &str) -> Vec<Decision<Vec<usize>>> {
+        let options = CheckOptions {
+            scheduler: true,
+            ..Default::default()
+        };
+        check_net(CHOICE_NET, logic_type, phi, &options)
+            .scheduler
+            .unwrap()
+    }
+
+    #[test]
+    fn pctl_schedulers_decide_per_marking() {
+        // t6 leads from B to C, so only the minimizing scheduler disables it
+        let max = scheduler(LogicType::Pctl, "Pmax=? [F c]");
+        assert!(max
+            .iter()
+            .all(|d| d.disabled.is_empty() && d.memory.is_none()));
+        let min = scheduler(LogicType::Pctl, "Pmin=? [F c]");
+        assert_eq!(
+            format_scheduler(&min, OutputFormat::Text),
+            "Marking [0, 0, 0, 1]: disable nothing\n\
+            Marking [0, 0, 1, 0]: disable nothing\n\
+            Marking [0, 1, 0, 0]: disable t6\n\
+            Marking [1, 0, 0, 0]: disable nothing\n"
+        );
+    }
+
+    #[test]
+    fn ltl_schedulers_remember_the_automaton_state() {
+        let decisions = scheduler(LogicType::LTL, "G(!c)");
+        assert!(decisions.iter().all(|d| d.memory.is_some()));
+        // B avoids C by disabling t6, whatever state the automaton is in
+        let b: Vec<&Decision<Vec<usize>>> = decisions
+            .iter()
+            .filter(|d| d.marking == vec![0, 1, 0, 0])
+            .collect();
+        assert!(!b.is_empty());
+        assert!(b.iter().all(|d| d.disabled == vec!["t6".to_string()]));
+    }
+}
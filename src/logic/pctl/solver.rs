@@ -0,0 +1,205 @@
+use crate::input_graph::MDP;
+use crate::logic::mec::maximal_end_components;
+use crate::logic::sparse::SparseMdp;
+use crate::mcsp::PctlInfo;
+use crate::utils::common::Comp;
+use crate::utils::linear::{solve, Scalar};
+use crate::utils::simplex::maximize;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+use petgraph::{Incoming, Outgoing};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Improvements smaller than this do not switch the action of a policy
+const EPSILON: f64 = 1e-12;
+
+/// Probabilities of reaching `s_1` from the states of `s_q` by policy iteration. Every policy is
+/// evaluated by solving its linear equation system directly, which does not suffer from the slow
+/// convergence of value iteration on self-loops with probabilities close to 1. None if rounding
+/// errors make an equation system singular
+pub fn policy_iteration(
+    pctl_info: &PctlInfo,
+    s_1: &HashSet<NodeIndex>,
+    s_q: &HashSet<NodeIndex>,
+    comp: &Comp,
+) -> Option<HashMap<NodeIndex, f64>> {
+    let graph = &pctl_info.reach_graph;
+    let sparse = &pctl_info.sparse;
+    let (unknown, mut prob_map) = unknown_states(graph, s_q, comp);
+    let mut policy: BTreeMap<NodeIndex, NodeIndex> = unknown
+        .iter()
+        .map(|s| (*s, graph.neighbors_directed(*s, Outgoing).min().unwrap()))
+        .collect();
+    prob_map.extend(s_1.iter().map(|s| (*s, 1.0)));
+    loop {
+        let values = evaluate_policy(pctl_info, s_1, &policy, |_, p, _| p)?;
+        prob_map.extend(values.into_iter().map(|(s, p)| (s, p.clamp(0.0, 1.0))));
+        let mut changed = false;
+        for state in &unknown {
+            let rows = sparse.rows(sparse.number(*state).unwrap());
+            let current = rows
+                .clone()
+                .find(|row| sparse.action(*row) == policy[state])
+                .unwrap();
+            // Comparing with the current action instead of the value of the state keeps
+            // rounding errors of the evaluation from switching to an action of the same value
+            let mut best = expected_value(sparse, current, &prob_map);
+            for row in rows {
+                let value = expected_value(sparse, row, &prob_map);
+                let improves = match comp.is_upper_bound() {
+                    true => value > best + EPSILON,
+                    false => value < best - EPSILON,
+                };
+                if improves {
+                    best = value;
+                    policy.insert(*state, sparse.action(row));
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return Some(prob_map);
+        }
+    }
+}
+
+/// Probabilities of reaching `s_1` from the states of `s_q` as the solution of a linear
+/// program. The maximal probabilities are the smallest values satisfying
+/// `x_s >= sum_t p(a, t) x_t` for every action a of s, the minimal ones the largest values
+/// satisfying `x_s <= sum_t p(a, t) x_t`. None if the simplex algorithm finds no optimum
+pub fn linear_program(
+    pctl_info: &PctlInfo,
+    s_1: &HashSet<NodeIndex>,
+    s_q: &HashSet<NodeIndex>,
+    comp: &Comp,
+) -> Option<HashMap<NodeIndex, f64>> {
+    let sparse = &pctl_info.sparse;
+    let (unknown, mut prob_map) = unknown_states(&pctl_info.reach_graph, s_q, comp);
+    let indices: BTreeMap<NodeIndex, usize> =
+        unknown.iter().enumerate().map(|(i, s)| (*s, i)).collect();
+    // One row per action: x_s - sum_t p(a, t) x_t compared with the probability of reaching s_1
+    // in one step
+    let mut rows: Vec<BTreeMap<usize, f64>> = Vec::new();
+    let mut bounds: Vec<f64> = Vec::new();
+    for (state, i) in &indices {
+        for action_row in sparse.rows(sparse.number(*state).unwrap()) {
+            let mut row = BTreeMap::from([(*i, 1.0)]);
+            let mut bound = 0.0;
+            for (target, p) in sparse.entries(action_row) {
+                let target = sparse.state(target);
+                if s_1.contains(&target) {
+                    bound += p;
+                } else if let Some(j) = indices.get(&target) {
+                    *row.entry(*j).or_insert(0.0) -= p;
+                }
+            }
+            rows.push(row);
+            bounds.push(bound);
+        }
+    }
+    let values = match comp.is_upper_bound() {
+        // Minimizing sum_s x_s subject to rows x >= bounds is the dual of maximizing
+        // bounds y subject to rows^T y <= 1
+        true => {
+            let mut transposed = vec![BTreeMap::new(); indices.len()];
+            for (a, row) in rows.iter().enumerate() {
+                for (s, value) in row {
+                    transposed[*s].insert(a, *value);
+                }
+            }
+            maximize(&bounds, &transposed, &vec![1.0; indices.len()])?.duals
+        }
+        false => maximize(&vec![1.0; indices.len()], &rows, &bounds)?.values,
+    };
+    prob_map.extend(
+        indices
+            .iter()
+            .map(|(s, i)| (*s, values[*i].clamp(0.0, 1.0))),
+    );
+    Some(prob_map)
+}
+
+/// States of `s_q` whose probability has to be computed. The minimizing scheduler stays in end
+/// components forever, so their states get the probability 0 and the remaining states can not
+/// avoid leaving `s_q` under any policy
+fn unknown_states(
+    graph: &MDP<NodeIndex>,
+    s_q: &HashSet<NodeIndex>,
+    comp: &Comp,
+) -> (BTreeSet<NodeIndex>, HashMap<NodeIndex, f64>) {
+    let mut unknown: BTreeSet<NodeIndex> = s_q.iter().copied().collect();
+    let mut prob_map: HashMap<NodeIndex, f64> = HashMap::new();
+    if !comp.is_upper_bound() {
+        for state in maximal_end_components(graph, s_q)
+            .into_iter()
+            .flat_map(|mec| mec.states)
+        {
+            unknown.remove(&state);
+            prob_map.insert(state, 0.0);
+        }
+    }
+    (unknown, prob_map)
+}
+
+/// Probabilities of reaching `s_1` from the states of the policy when always choosing its
+/// action. The equation system is built from the rows of the sparse matrix with the numbers
+/// `weight` assigns to their entries, given the row, its probability and its edge, so the
+/// same evaluation serves floats and exact fractions. None if the arithmetic fails
+pub fn evaluate_policy<T: Scalar>(
+    pctl_info: &PctlInfo,
+    s_1: &HashSet<NodeIndex>,
+    policy: &BTreeMap<NodeIndex, NodeIndex>,
+    weight: impl Fn(usize, f64, EdgeIndex) -> T,
+) -> Option<HashMap<NodeIndex, T>> {
+    let graph = &pctl_info.reach_graph;
+    let sparse = &pctl_info.sparse;
+    // States which never reach s_1 under the policy have probability 0. Leaving them out makes
+    // the equation system uniquely solvable
+    let mut reaching: HashSet<NodeIndex> = HashSet::new();
+    let mut stack: Vec<NodeIndex> = s_1.iter().copied().collect();
+    while let Some(target) = stack.pop() {
+        for action in graph.neighbors_directed(target, Incoming) {
+            let state = graph.neighbors_directed(action, Incoming).next().unwrap();
+            if policy.get(&state) == Some(&action) && reaching.insert(state) {
+                stack.push(state);
+            }
+        }
+    }
+    let indices: BTreeMap<NodeIndex, usize> = policy
+        .keys()
+        .filter(|s| reaching.contains(s))
+        .enumerate()
+        .map(|(i, s)| (*s, i))
+        .collect();
+    let mut rows: Vec<BTreeMap<usize, T>> = Vec::with_capacity(indices.len());
+    let mut rhs: Vec<T> = Vec::with_capacity(indices.len());
+    for (state, i) in &indices {
+        let mut row = BTreeMap::from([(*i, T::ONE)]);
+        let mut bound = T::ZERO;
+        let number = sparse.number(*state).unwrap();
+        let action_row = sparse
+            .rows(number)
+            .find(|r| sparse.action(*r) == policy[state])
+            .unwrap();
+        for (target, p, edge) in sparse.edges(action_row) {
+            let target = sparse.state(target);
+            if s_1.contains(&target) {
+                bound = bound.checked_add(weight(action_row, p, edge))?;
+            } else if let Some(column) = indices.get(&target) {
+                let entry = row.entry(*column).or_insert(T::ZERO);
+                *entry = entry.checked_sub(weight(action_row, p, edge))?;
+            }
+        }
+        rows.push(row);
+        rhs.push(bound);
+    }
+    let solution = solve(rows, rhs)?;
+    let mut values: HashMap<NodeIndex, T> = policy.keys().map(|s| (*s, T::ZERO)).collect();
+    values.extend(indices.iter().map(|(s, i)| (*s, solution[*i])));
+    Some(values)
+}
+
+fn expected_value(sparse: &SparseMdp, row: usize, values: &HashMap<NodeIndex, f64>) -> f64 {
+    sparse.entries(row).fold(0.0, |sum, (target, p)| {
+        sum + p * values.get(&sparse.state(target)).unwrap_or(&0.0)
+    })
+}
@@ -0,0 +1,146 @@
+use crate::input_graph::MDP;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::{EdgeRef, NodeIndexable};
+use petgraph::Outgoing;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+/// MDP in compressed sparse row form for the numerical engines. States are numbered in the
+/// order of their node indices, every state owns a group of consecutive rows (one per action)
+/// and every row holds the probabilities of reaching the successor states. Values of states are
+/// kept in dense vectors indexed by these numbers
+#[derive(Debug, Clone, Default)]
+pub struct SparseMdp {
+    /// Graph node of every state
+    states: Vec<NodeIndex>,
+    /// Number of the state of every graph node, None for actions
+    numbers: Vec<Option<usize>>,
+    /// The rows of state s are `row_groups[s]..row_groups[s + 1]`
+    row_groups: Vec<usize>,
+    /// Graph node of the action of every row
+    actions: Vec<NodeIndex>,
+    /// The entries of row r are `row_starts[r]..row_starts[r + 1]`
+    row_starts: Vec<usize>,
+    columns: Vec<usize>,
+    probabilities: Vec<f64>,
+    /// Graph edge of every entry
+    edges: Vec<EdgeIndex>,
+}
+
+impl SparseMdp {
+    pub fn from_graph(graph: &MDP<NodeIndex>) -> Self {
+        let mut states: Vec<NodeIndex> = graph
+            .node_indices()
+            .filter(|n| graph[*n].is_state())
+            .collect();
+        states.sort();
+        let mut numbers = vec![None; graph.node_bound()];
+        for (number, state) in states.iter().enumerate() {
+            numbers[state.index()] = Some(number);
+        }
+        let mut sparse = SparseMdp {
+            states,
+            numbers,
+            row_groups: vec![0],
+            ..Default::default()
+        };
+        sparse.row_starts.push(0);
+        for state in sparse.states.clone() {
+            let mut actions: Vec<NodeIndex> = graph.neighbors_directed(state, Outgoing).collect();
+            actions.sort();
+            for action in actions {
+                for edge in graph.edges_directed(action, Outgoing) {
+                    sparse
+                        .columns
+                        .push(sparse.numbers[edge.target().index()].unwrap());
+                    sparse.probabilities.push(*edge.weight());
+                    sparse.edges.push(edge.id());
+                }
+                sparse.actions.push(action);
+                sparse.row_starts.push(sparse.columns.len());
+            }
+            sparse.row_groups.push(sparse.actions.len());
+        }
+        sparse
+    }
+
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Graph node of the state with the given number
+    pub fn state(&self, number: usize) -> NodeIndex {
+        self.states[number]
+    }
+
+    /// Number of the state, None if the node is not a state
+    pub fn number(&self, state: NodeIndex) -> Option<usize> {
+        self.numbers.get(state.index()).copied().flatten()
+    }
+
+    /// Rows of the actions of the state
+    pub fn rows(&self, state: usize) -> Range<usize> {
+        self.row_groups[state]..self.row_groups[state + 1]
+    }
+
+    /// Graph node of the action of the row
+    pub fn action(&self, row: usize) -> NodeIndex {
+        self.actions[row]
+    }
+
+    /// Successor states of the row together with their probabilities
+    pub fn entries(&self, row: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let range = self.row_starts[row]..self.row_starts[row + 1];
+        self.columns[range.clone()]
+            .iter()
+            .copied()
+            .zip(self.probabilities[range].iter().copied())
+    }
+
+    /// Successor states of the row together with their probabilities and the graph edges
+    /// leading to them
+    pub fn edges(&self, row: usize) -> impl Iterator<Item = (usize, f64, EdgeIndex)> + '_ {
+        let range = self.row_starts[row]..self.row_starts[row + 1];
+        self.entries(row)
+            .zip(self.edges[range].iter().copied())
+            .map(|((column, p), edge)| (column, p, edge))
+    }
+
+    /// Expected value of the successors of the row
+    pub fn expected_value(&self, row: usize, values: &[f64]) -> f64 {
+        self.entries(row)
+            .fold(0.0, |sum, (column, p)| sum + p * values[column])
+    }
+
+    /// Dense vector with the values of the map, states missing in it get the default
+    pub fn vector(&self, map: &HashMap<NodeIndex, f64>, default: f64) -> Vec<f64> {
+        self.states
+            .iter()
+            .map(|s| map.get(s).copied().unwrap_or(default))
+            .collect()
+    }
+
+    /// Vector which is 1 for the states of the set and 0 for all others
+    pub fn indicator(&self, set: &HashSet<NodeIndex>) -> Vec<f64> {
+        self.states
+            .iter()
+            .map(|s| if set.contains(s) { 1.0 } else { 0.0 })
+            .collect()
+    }
+
+    /// Numbers of the states of the set in ascending order
+    pub fn numbers_of(&self, set: &HashSet<NodeIndex>) -> Vec<usize> {
+        let mut numbers: Vec<usize> = set.iter().filter_map(|s| self.number(*s)).collect();
+        numbers.sort();
+        numbers
+    }
+
+    /// Map from the graph node of every state to its value
+    pub fn to_map<T: Copy>(&self, values: &[T]) -> HashMap<NodeIndex, T> {
+        self.states
+            .iter()
+            .copied()
+            .zip(values.iter().copied())
+            .collect()
+    }
+}
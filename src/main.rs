@@ -1,18 +1,16 @@
-mod common;
-mod input_graph;
-mod logic;
-mod mcsp;
-mod parser;
-mod utils;
-
-use crate::input_graph::dpnet::DPetriNet;
-use crate::input_graph::pnet::PetriNet;
-use crate::input_graph::InputGraphType;
-use crate::mcsp::ModelCheck;
-use crate::parser::dpn_parser::DPetriNetParser;
-use crate::parser::petri_net_parser::PetriNetParser;
-use clap::Parser;
-use log::info;
+use clap::{Parser, ValueEnum};
+use log::{error, info, warn};
+use mcsp::input_graph::InputGraphType;
+use mcsp::utils::file::{read_file, write_file};
+use mcsp::{
+    analyze_behaviour, analyze_boundedness, analyze_structure, check, format_behaviour,
+    format_boundedness, format_result, format_scheduler, format_structure, parse_formula,
+    AnalysisType, AutomatonStage, AutomatonType, CheckOptions, DPetriNet, DPetriNetParser,
+    InputGraph, LogicType, McspError, OutputFormat, ParseImpl, PetriNet, PetriNetParser, Solver,
+};
+use petgraph::dot::Dot;
+use std::num::NonZeroUsize;
+use std::process::exit;
 
 #[derive(Parser)]
 pub struct Args {
@@ -20,8 +18,8 @@ pub struct Args {
     #[arg(short, long)]
     input_file: String,
 
-    /// Max error used by the value iteration algorithm to compute the
-    /// 'UNTIL' pctl statement. Must be greater than 0
+    /// Guaranteed bound on the error of the probabilities of unbounded
+    /// 'UNTIL' statements. Must be greater than 0
     #[arg(long("max-error"), default_value_t = 0.01)]
     max_error: f64,
 
@@ -36,22 +34,150 @@ pub struct Args {
 
     #[arg(short, long("show-graph"), default_value_t = false)]
     show_graph: bool,
-}
-#[derive(clap::ValueEnum, Clone, Default)]
-pub enum LogicType {
-    #[default]
-    Pctl,
-    LTL,
+
+    /// Runs the coverability analysis before building the reachability graph
+    #[arg(long("check-boundedness"), default_value_t = false)]
+    check_boundedness: bool,
+
+    /// Uses exact fractions of the fire rates as transition probabilities instead of rounding
+    /// them and solves unbounded probabilities exactly
+    #[arg(long, default_value_t = false)]
+    exact: bool,
+
+    /// Algorithm computing the probabilities of unbounded 'UNTIL' statements
+    #[arg(long, default_value_t, value_enum)]
+    solver: Solver,
+
+    /// Automaton LTL formulas are translated to
+    #[arg(long, default_value_t, value_enum)]
+    automaton: AutomatonType,
+
+    /// Number of threads evaluating the formula. Interval iteration only runs in parallel with
+    /// --solver value and topological
+    #[arg(long, default_value = "1")]
+    threads: NonZeroUsize,
+
+    /// Writes the scheduler attaining the value of a Pmin=? or Pmax=? query (or the maximal
+    /// probability of an LTL formula) to the file, in the output format
+    #[arg(long)]
+    scheduler: Option<String>,
+
+    /// Prints a counterexample if the initial marking violates the formula: at most this many of
+    /// the most probable paths violating the bound of a P operator or a lasso-shaped run
+    /// violating an LTL formula
+    #[arg(long, num_args = 0..=1, default_missing_value = "100")]
+    counterexample: Option<NonZeroUsize>,
+
+    /// Writes the automaton of a stage of the LTL translation (vwaa, gba, ba, dra, dpa or ldba)
+    /// to the file in HOA format. Can be repeated
+    #[arg(long("dump-automaton"), value_name = "STAGE=PATH", value_parser = parse_dump)]
+    dump_automaton: Vec<(AutomatonStage, String)>,
+
+    /// Analyses the net instead of checking the formula
+    #[arg(short, long, value_enum)]
+    analyze: Option<AnalysisType>,
+
+    /// Format the result of the check is printed in
+    #[arg(short, long("output-format"), default_value_t, value_enum)]
+    output_format: OutputFormat,
 }
 
 fn main() {
     init();
     let args = Args::parse();
     info!("Starting MCSP...");
-    match args.graph_type {
-        InputGraphType::Petri => ModelCheck::<PetriNet, PetriNetParser>::start(args),
-        InputGraphType::DecisionPetri => ModelCheck::<DPetriNet, DPetriNetParser>::start(args),
+    let result = match args.graph_type {
+        InputGraphType::Petri => run::<PetriNet, PetriNetParser>(args),
+        InputGraphType::DecisionPetri => run::<DPetriNet, DPetriNetParser>(args),
     };
+    if let Err(err) = result {
+        error!("{}", err);
+        exit(err.exit_code());
+    }
+}
+
+fn run<T, P>(args: Args) -> Result<(), McspError>
+where
+    T: InputGraph,
+    T::S: Ord + Send + Sync + AsRef<[usize]>,
+    P: ParseImpl<T>,
+{
+    info!("Parsing input petri net");
+    let content = read_file(&args.input_file)?;
+    let input_graph: Box<T> = P::parse(&content)?;
+    info!("Petri net parsed successfully");
+
+    // Show graph if user requests
+    if args.show_graph {
+        info!("Dot graph as requested...");
+        let (reach_graph, _) = input_graph.to_mdp(args.precision_digits)?;
+        println!("{:?}", Dot::new(&reach_graph));
+    }
+
+    if let Some(analysis) = args.analyze {
+        info!("Analyzing petri net...");
+        let net = input_graph.net_structure();
+        let report = match analysis {
+            AnalysisType::Boundedness => {
+                format_boundedness(&analyze_boundedness(&net), args.output_format)
+            }
+            AnalysisType::Structure => {
+                format_structure(&net, &analyze_structure(&net), args.output_format)
+            }
+            AnalysisType::Behaviour => {
+                let (reach_graph, initial_marking, labels) =
+                    input_graph.to_labelled_mdp(args.precision_digits)?;
+                let result = analyze_behaviour(&reach_graph, &initial_marking, &labels, &net);
+                format_behaviour(&result, args.output_format)
+            }
+        };
+        print!("{}", report);
+        return Ok(());
+    }
+
+    info!("Parsing formula...");
+    let formula = parse_formula(args.logic_type, &content)?;
+    info!("Formula parsed successfully");
+    let options = CheckOptions {
+        max_error: args.max_error,
+        precision_digits: args.precision_digits,
+        check_boundedness: args.check_boundedness,
+        exact: args.exact,
+        solver: args.solver,
+        automaton: args.automaton,
+        threads: args.threads.get(),
+        scheduler: args.scheduler.is_some(),
+        counterexample: args.counterexample.map(NonZeroUsize::get),
+        dump_automata: args
+            .dump_automaton
+            .iter()
+            .map(|(stage, _)| *stage)
+            .collect(),
+    };
+    let result = check(input_graph.as_ref(), &formula, &options)?;
+    print!("{}", format_result(&result, args.output_format));
+    if let (Some(path), Some(decisions)) = (&args.scheduler, &result.scheduler) {
+        info!("Writing the scheduler to {}", path);
+        write_file(path, &format_scheduler(decisions, args.output_format))?;
+    }
+    for (stage, path) in &args.dump_automaton {
+        let name = stage.to_possible_value().unwrap().get_name().to_string();
+        match result.automata.iter().find(|(s, _)| s == stage) {
+            Some((_, hoa)) => {
+                info!("Writing the {} to {}", name, path);
+                write_file(path, hoa)?;
+            }
+            None => warn!("No {} is built for the formula", name),
+        }
+    }
+    Ok(())
+}
+
+fn parse_dump(value: &str) -> Result<(AutomatonStage, String), String> {
+    let (stage, path) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected STAGE=PATH, found '{}'", value))?;
+    Ok((AutomatonStage::from_str(stage, true)?, path.into()))
 }
 
 fn init() {
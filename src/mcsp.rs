@@ -1,23 +1,43 @@
+use crate::analysis::coverability::{analyze_boundedness, PlaceBound};
+use crate::analysis::NetStructure;
 use crate::common::rename_map;
+use crate::error::McspError;
 use crate::input_graph::Node::{Action, State};
-use crate::input_graph::{ApMap, InputGraph, Node, ParseImpl, MDP};
-use crate::logic::{parse_formula, Formula};
+use crate::input_graph::{ApMap, InputGraph, Node, TransitionLabels, MDP};
+use crate::logic::csl::uniformise;
+use crate::logic::pctl::ExactWeights;
+use crate::logic::sparse::SparseMdp;
+use crate::logic::Formula;
 use crate::utils::common::reverse_btree_map;
-use crate::utils::file::read_file;
-use crate::Args;
+use crate::utils::rational::Rational;
 use log::info;
-use petgraph::dot::Dot;
-use petgraph::graph::NodeIndex;
-use std::collections::{HashMap, HashSet};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Outgoing;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::Debug;
-use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+/// Stack size of the evaluation threads. The formulas and the automata translations are
+/// evaluated recursively, which the 2 MiB default of rayon is too small for on deeply nested
+/// formulas
+const EVALUATION_STACK_SIZE: usize = 64 * 1024 * 1024;
 
 pub struct ModelCheckInfo<'a, T> {
     pub initial_marking: T,
     pub reach_graph: MDP<T>,
     pub ap_map: &'a ApMap<T>,
-    pub formula: Formula,
+    pub formula: &'a Formula,
     pub max_error: f64,
+    pub rewards: RewardMaps,
+    pub exact: Option<ExactWeights>,
+    pub solver: Solver,
+    pub automaton: AutomatonType,
+    pub scheduler: bool,
+    pub labels: TransitionLabels,
+    pub transition_names: Vec<String>,
+    pub counterexample: Option<usize>,
+    pub dump_automata: Vec<AutomatonStage>,
 }
 
 pub struct PctlInfo {
@@ -25,84 +45,487 @@ pub struct PctlInfo {
     pub reach_graph: MDP<NodeIndex>,
     pub ap_map: HashMap<String, HashSet<NodeIndex>>,
     pub max_error: f64,
+    pub rewards: RewardMaps,
+    /// Rate the continuous-time model was uniformised with. None for discrete-time models
+    pub uniformisation_rate: Option<f64>,
+    /// Exact probabilities of the graph if it was built in `--exact` mode
+    pub exact: Option<ExactWeights>,
+    /// Algorithm computing the probabilities of unbounded until formulas
+    pub solver: Solver,
+    /// Automaton LTL formulas are translated to
+    pub automaton: AutomatonType,
+    /// Sparse form of `reach_graph` used by the numerical engines
+    pub sparse: SparseMdp,
+    /// Whether the optimal scheduler of the query should be extracted
+    pub scheduler: bool,
+    /// Index of the transition fired along every edge from an action to a state
+    pub labels: TransitionLabels,
+    pub transition_names: Vec<String>,
+    /// Maximal number of paths of a counterexample, None if none should be generated
+    pub counterexample: Option<usize>,
+    /// Stages of the translation of an LTL formula whose automata are returned in HOA format
+    pub dump_automata: Vec<AutomatonStage>,
+}
+
+/// Rewards of the states and transitions of a reachability graph
+#[derive(Default)]
+pub struct RewardMaps {
+    /// Reward gained for every step spent in a state
+    pub states: HashMap<NodeIndex, f64>,
+    /// Reward gained when an edge from an action to a state is taken
+    pub transitions: HashMap<EdgeIndex, f64>,
 }
 
-pub struct ModelCheck<T: InputGraph, P: ParseImpl<T>> {
-    p1: PhantomData<T>,
-    p2: PhantomData<P>,
+impl RewardMaps {
+    fn of<T: InputGraph>(model: &T, reach_graph: &MDP<T::S>, labels: &TransitionLabels) -> Self {
+        let states = reach_graph
+            .node_indices()
+            .filter_map(|n| match &reach_graph[n] {
+                State(state) => Some((n, model.state_reward(state))),
+                Action(_) => None,
+            })
+            .collect();
+        let transitions = labels
+            .iter()
+            .map(|(edge, transition)| (*edge, model.transition_reward(*transition)))
+            .collect();
+        RewardMaps {
+            states,
+            transitions,
+        }
+    }
 }
 
-impl<T, P> ModelCheck<T, P>
+/// Options controlling how a model is checked
+pub struct CheckOptions {
+    /// Bound on the error of the probabilities of unbounded until and globally formulas and on
+    /// the truncation error of CSL time bounds. Rewards and long-run averages stop iterating
+    /// once successive values differ by less. Must be greater than 0
+    pub max_error: f64,
+    /// Number of decimal digits the transition probabilities of the reachability graph are
+    /// rounded to
+    pub precision_digits: i32,
+    /// Runs the coverability analysis before building the reachability graph and reports all
+    /// unbounded places instead of the first unbounded path found while building the graph
+    pub check_boundedness: bool,
+    /// Computes the transition probabilities as fractions of the fire rates instead of rounding
+    /// them and solves unbounded P operators of PCTL exactly. Other operators are evaluated
+    /// numerically on the unrounded probabilities
+    pub exact: bool,
+    pub solver: Solver,
+    pub automaton: AutomatonType,
+    /// Number of threads evaluating the formula. The results do not depend on it. Interval
+    /// iteration only runs in parallel with the value and topological solvers
+    pub threads: usize,
+    /// Extracts the scheduler attaining the value of a `Pmin=?` or `Pmax=?` query over an
+    /// unbounded path formula or the maximal probability of an LTL formula
+    pub scheduler: bool,
+    /// Generates a counterexample with at most this many paths if the initial marking violates
+    /// a P operator bound or an LTL formula
+    pub counterexample: Option<usize>,
+    /// Stages of the translation of an LTL formula whose automata are returned in HOA format
+    pub dump_automata: Vec<AutomatonStage>,
+}
+
+/// Algorithm computing the probabilities of unbounded until formulas
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, PartialEq)]
+pub enum Solver {
+    /// Interval iteration with Jacobi sweeps, approximates the probabilities within
+    /// `--max-error`
+    Value,
+    /// Interval iteration with Gauss-Seidel sweeps in topological order of the strongly
+    /// connected components
+    GaussSeidel,
+    /// Interval iteration solving one strongly connected component after the other with
+    /// Gauss-Seidel sweeps. Every component only waits for the precision of its own states, so
+    /// this converges fastest. Large components are solved with Jacobi sweeps instead, which
+    /// use the threads of `--threads`
+    #[default]
+    Topological,
+    /// Policy iteration, solving a linear equation system for every policy
+    Policy,
+    /// Linear program solved with the simplex algorithm
+    Lp,
+}
+
+/// Automaton LTL formulas are translated to before building the product with the model. The
+/// maximal probability comes from the automaton of the formula, the minimal one is the complement
+/// of the maximal one of the negated formula
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, PartialEq)]
+pub enum AutomatonType {
+    /// Deterministic Rabin automaton from Safra's construction
+    #[default]
+    Dra,
+    /// Deterministic parity automaton converted from the DRA with index appearance records
+    Dpa,
+    /// Limit-deterministic Büchi automaton built from the generalized Büchi automaton
+    Ldba,
+}
+
+/// Stage of the translation of LTL formulas to automata
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AutomatonStage {
+    /// Very weak alternating automaton of the formula
+    Vwaa,
+    /// Generalized Büchi automaton with accepting transitions
+    Gba,
+    /// Büchi automaton of the GBA
+    Ba,
+    /// Deterministic Rabin automaton from Safra's construction
+    Dra,
+    /// Deterministic parity automaton of the DRA
+    Dpa,
+    /// Limit-deterministic Büchi automaton of the GBA
+    Ldba,
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        CheckOptions {
+            max_error: 0.01,
+            precision_digits: 2,
+            check_boundedness: false,
+            exact: false,
+            solver: Solver::Topological,
+            automaton: AutomatonType::Dra,
+            threads: 1,
+            scheduler: false,
+            counterexample: None,
+            dump_automata: Vec::new(),
+        }
+    }
+}
+
+/// Minimal and maximal probability (or expected reward) of a marking over all schedulers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Probability {
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Interval the real value of an approximated probability is guaranteed to lie in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub lo: f64,
+    pub hi: f64,
+}
+
+impl Interval {
+    /// Interval of a value computed without approximation
+    pub fn point(value: f64) -> Self {
+        Interval {
+            lo: value,
+            hi: value,
+        }
+    }
+
+    pub fn midpoint(&self) -> f64 {
+        (self.lo + self.hi) / 2.0
+    }
+}
+
+/// Number of nodes and edges of an MDP
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GraphSize {
+    pub states: usize,
+    pub actions: usize,
+    /// Probabilistic edges leaving actions
+    pub transitions: usize,
+}
+
+impl GraphSize {
+    pub fn of<T>(graph: &MDP<T>) -> Self {
+        let states = graph
+            .node_weights()
+            .filter(|n| matches!(n, State(_)))
+            .count();
+        let transitions = graph
+            .edge_indices()
+            .filter(|e| matches!(graph[graph.edge_endpoints(*e).unwrap().0], Action(_)))
+            .count();
+        GraphSize {
+            states,
+            actions: graph.node_count() - states,
+            transitions,
+        }
+    }
+}
+
+/// Time spent in the phases of a check
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Timings {
+    /// Building and validating the reachability graph
+    pub build: Duration,
+    /// Evaluating the formula, including the construction of automata and product MDPs
+    pub evaluation: Duration,
+}
+
+/// Outcome of checking a formula against a model
+#[derive(Debug)]
+pub struct CheckResult<K> {
+    /// Initial marking of the model
+    pub initial_marking: K,
+    /// Whether the initial marking satisfies the formula. LTL formulas are satisfied if they hold
+    /// with probability 1 under every scheduler. Always false for quantitative queries
+    pub satisfied: bool,
+    /// Result of a quantitative query like `Pmax=? [F goal]` or `R=? [C<=10]` for the initial
+    /// marking
+    pub value: Option<f64>,
+    /// Value of the query as a fraction if it was computed exactly
+    pub exact_value: Option<Rational>,
+    /// Interval the value of a P query is guaranteed to lie in. The value is its midpoint. For LTL
+    /// formulas the probability of the initial marking lies in it under every scheduler
+    pub value_bounds: Option<Interval>,
+    /// All reachable markings satisfying the formula
+    pub satisfying_markings: BTreeSet<K>,
+    /// Probabilities or expected rewards of the outermost P or R operator (or the LTL formula)
+    /// per marking. Empty if the formula does not start with such an operator
+    pub probabilities: BTreeMap<K, Probability>,
+    /// Size of the reachability graph
+    pub reach_graph: GraphSize,
+    /// Size of the product of the reachability graph and the automaton of an LTL formula
+    pub product: Option<GraphSize>,
+    /// Decisions of the optimal scheduler if it was requested and the formula has one
+    pub scheduler: Option<Vec<Decision<K>>>,
+    /// Runs explaining why the initial marking violates the formula if they were requested
+    pub counterexample: Option<Counterexample<K>>,
+    /// Requested automata of the translation of an LTL formula in HOA format
+    pub automata: Vec<(AutomatonStage, String)>,
+    pub timings: Timings,
+}
+
+/// Run of the model starting in the initial marking
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace<K> {
+    /// Markings visited by the run, one more than transitions
+    pub markings: Vec<K>,
+    /// States of the automaton of an LTL formula paired with the markings. Empty for PCTL
+    pub automaton_states: Vec<String>,
+    /// Names of the fired transitions, "(stay)" if the marking was kept without firing
+    pub transitions: Vec<String>,
+    /// Probability of the run under the scheduler of the counterexample
+    pub probability: f64,
+}
+
+/// Evidence that the initial marking violates the formula
+#[derive(Debug, Clone, PartialEq)]
+pub enum Counterexample<K> {
+    /// Most probable runs whose combined probability violates the bound of the outermost P
+    /// operator. Upper bounds are shown by runs satisfying its path formula, lower bounds by runs
+    /// which can no longer satisfy it. A single run for the bounds 0 and 1
+    Paths(Vec<Trace<K>>),
+    /// Run violating an LTL formula which repeats `cycle` forever after `prefix`
+    Lasso { prefix: Trace<K>, cycle: Trace<K> },
+}
+
+/// Choice of a scheduler in a marking
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision<K> {
+    pub marking: K,
+    /// State of the automaton of an LTL formula the scheduler remembers. None for PCTL, whose
+    /// schedulers are memoryless
+    pub memory: Option<String>,
+    /// Controllable transitions the scheduler disables
+    pub disabled: Vec<String>,
+}
+
+/// Checks whether the given model satisfies the formula.
+///
+/// Builds the reachability graph of the model, restricts the atomic propositions to reachable
+/// markings and evaluates the formula on the resulting MDP.
+pub fn check<T>(
+    model: &T,
+    formula: &Formula,
+    options: &CheckOptions,
+) -> Result<CheckResult<T::S>, McspError>
 where
-    <T as InputGraph>::S: Ord,
     T: InputGraph,
-    P: ParseImpl<T>,
+    T::S: Ord + Send + Sync,
 {
-    pub fn start(args: Args) {
-        info!("Parsing input petri net");
-        let content = read_file(&args.input_file);
-        let mut input_graph: Box<T> = P::parse(&content);
-        info!("Petri net parsed successfully");
-        info!("Validating petri net...");
-        let (reach_graph, initial_marking) = input_graph.to_mdp(args.precision_digits);
-        input_graph.validate_graph(&reach_graph);
-        info!("Petri net has been validated successfully");
-
-        // Show graph if user requests
-        if args.show_graph {
-            info!("Dot graph as requested...");
-            println!("{:?}", Dot::new(&reach_graph));
-        }
+    let start = Instant::now();
+    if options.check_boundedness {
+        info!("Checking boundedness...");
+        check_boundedness(&model.net_structure())?;
+    }
+    info!("Validating petri net...");
+    // Exact probabilities are computed from the rates of the continuous-time graph. Time bounds
+    // of CSL are always evaluated numerically
+    let exact = options.exact && !formula.is_continuous();
+    let (mut reach_graph, initial_marking, labels) = match formula.is_continuous() || exact {
+        true => model.to_ctmdp()?,
+        false => model.to_labelled_mdp(options.precision_digits)?,
+    };
+    let exact = match exact {
+        true => Some(exact_weights(
+            &mut reach_graph,
+            &labels,
+            &model.net_structure(),
+        )?),
+        false => None,
+    };
+    let ap_map = model.validate_graph(&reach_graph);
+    let transition_names = model
+        .net_structure()
+        .transitions
+        .into_iter()
+        .map(|t| t.name)
+        .collect();
+    let rewards = RewardMaps::of(model, &reach_graph, &labels);
+    let build = start.elapsed();
+    info!("Petri net has been validated successfully");
+    let mc: ModelCheckInfo<T::S> = ModelCheckInfo {
+        initial_marking,
+        reach_graph,
+        ap_map: &ap_map,
+        formula,
+        max_error: options.max_error,
+        rewards,
+        exact,
+        solver: options.solver,
+        automaton: options.automaton,
+        scheduler: options.scheduler,
+        labels,
+        transition_names,
+        counterexample: options.counterexample,
+        dump_automata: options.dump_automata.clone(),
+    };
+    let start = Instant::now();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.threads)
+        .stack_size(EVALUATION_STACK_SIZE)
+        .build()
+        .map_err(|e| McspError::ThreadPool(e.to_string()))?;
+    let mut result = pool.install(|| evaluate(mc))?;
+    result.timings = Timings {
+        build,
+        evaluation: start.elapsed(),
+    };
+    Ok(result)
+}
 
-        info!("Parsing formula...");
-        let formula = parse_formula(args.logic_type, &content);
-        info!("Formula parsed successfully");
-        let mc: ModelCheckInfo<T::S> = ModelCheckInfo {
-            initial_marking,
-            reach_graph,
-            ap_map: input_graph.get_ap_map(),
-            formula,
-            max_error: args.max_error,
+/// Fails with the unbounded places of the net if there are any
+pub fn check_boundedness(net: &NetStructure) -> Result<(), McspError> {
+    let result = analyze_boundedness(net);
+    let unbounded: Vec<&PlaceBound> = result.unbounded_places().collect();
+    if !unbounded.is_empty() {
+        let witnesses = || {
+            unbounded
+                .iter()
+                .filter_map(|p| p.witness.as_ref().map(|w| (&p.place, w)))
+        };
+        // A pump which needs the tokens of other pumps is reported without it
+        let (place, prefix, pump) = match witnesses().find(|(_, w)| w.repeatable) {
+            Some((place, witness)) => (place, &witness.prefix, Some(witness.pump.clone())),
+            None => match witnesses().next() {
+                Some((place, witness)) => (place, &witness.prefix, None),
+                None => (&unbounded[0].place, &Vec::new(), None),
+            },
         };
-        Self::evaluate_pctl(mc);
+        return Err(McspError::UnboundedPlaces {
+            places: unbounded.iter().map(|p| p.place.clone()).collect(),
+            place: place.clone(),
+            prefix: prefix.clone(),
+            pump,
+        });
     }
+    Ok(())
+}
 
-    pub fn evaluate_pctl<K>(mc_info: ModelCheckInfo<K>)
-    where
-        K: Debug + PartialEq + Clone + Ord,
-    {
-        let rename_map = rename_map(&mc_info.reach_graph);
-        let normalized_mdp = mc_info.reach_graph.map(
-            |_, node| match node {
-                State(_) => State(*rename_map.get(node).unwrap()),
-                Action(a) => Action(a.clone()),
-            },
-            |_, e| *e,
-        );
-        let initial_node = *rename_map
-            .get(&Node::State(mc_info.initial_marking))
-            .unwrap();
-        let normalized_ap_map = mc_info
-            .ap_map
-            .iter()
-            .map(|(ap, set)| {
-                let renamed_set = set
-                    .iter()
-                    .map(|k| *rename_map.get(&State(k.clone())).unwrap())
-                    .collect();
-                (ap.into(), renamed_set)
-            })
+/// Replaces the rates of the continuous-time graph by the probabilities of the transitions and
+/// returns these probabilities as exact fractions of the rates
+fn exact_weights<S>(
+    graph: &mut MDP<S>,
+    labels: &TransitionLabels,
+    net: &NetStructure,
+) -> Result<ExactWeights, McspError> {
+    let rates: Vec<Rational> = net
+        .transitions
+        .iter()
+        .map(|t| Rational::from_f64(t.fire_rate).ok_or(McspError::ExactOverflow))
+        .collect::<Result<_, _>>()?;
+    let actions: Vec<NodeIndex> = graph
+        .node_indices()
+        .filter(|n| matches!(graph[*n], Action(_)))
+        .collect();
+    let mut weights = ExactWeights::new();
+    for action in actions {
+        let edges: Vec<EdgeIndex> = graph
+            .edges_directed(action, Outgoing)
+            .map(|e| e.id())
             .collect();
-        let pctl_info: PctlInfo = PctlInfo {
-            initial_marking: initial_node,
-            reach_graph: normalized_mdp,
-            ap_map: normalized_ap_map,
-            max_error: mc_info.max_error,
-        };
+        // Only the loops of dead markings have no transition
+        let mut total = Rational::ZERO;
+        for edge in &edges {
+            if let Some(transition) = labels.get(edge) {
+                total = total
+                    .checked_add(rates[*transition])
+                    .ok_or(McspError::ExactOverflow)?;
+            }
+        }
+        for edge in edges {
+            let probability = match labels.get(&edge) {
+                Some(transition) => rates[*transition]
+                    .checked_div(total)
+                    .ok_or(McspError::ExactOverflow)?,
+                None => Rational::ONE,
+            };
+            graph[edge] = probability.to_f64();
+            weights.insert(edge, probability);
+        }
+    }
+    Ok(weights)
+}
 
-        info!("Evaluating formula...");
-        mc_info
-            .formula
-            .evaluate(&pctl_info, reverse_btree_map(rename_map));
+fn evaluate<K>(mc_info: ModelCheckInfo<K>) -> Result<CheckResult<K>, McspError>
+where
+    K: Debug + PartialEq + Clone + Ord,
+{
+    let rename_map = rename_map(&mc_info.reach_graph);
+    let normalized_mdp = mc_info.reach_graph.map(
+        |_, node| match node {
+            State(_) => State(*rename_map.get(node).unwrap()),
+            Action(a) => Action(a.clone()),
+        },
+        |_, e| *e,
+    );
+    let initial_node = *rename_map
+        .get(&Node::State(mc_info.initial_marking))
+        .unwrap();
+    let normalized_ap_map = mc_info
+        .ap_map
+        .iter()
+        .map(|(ap, set)| {
+            let renamed_set = set
+                .iter()
+                .map(|k| *rename_map.get(&State(k.clone())).unwrap())
+                .collect();
+            (ap.into(), renamed_set)
+        })
+        .collect();
+    let sparse = SparseMdp::from_graph(&normalized_mdp);
+    let mut pctl_info: PctlInfo = PctlInfo {
+        initial_marking: initial_node,
+        reach_graph: normalized_mdp,
+        ap_map: normalized_ap_map,
+        max_error: mc_info.max_error,
+        rewards: mc_info.rewards,
+        uniformisation_rate: None,
+        exact: mc_info.exact,
+        solver: mc_info.solver,
+        automaton: mc_info.automaton,
+        sparse,
+        scheduler: mc_info.scheduler,
+        labels: mc_info.labels,
+        transition_names: mc_info.transition_names,
+        counterexample: mc_info.counterexample,
+        dump_automata: mc_info.dump_automata,
+    };
+    if mc_info.formula.is_continuous() {
+        uniformise(&mut pctl_info);
     }
+
+    info!("Evaluating formula...");
+    mc_info
+        .formula
+        .evaluate(&pctl_info, reverse_btree_map(rename_map))
 }
@@ -1,11 +1,10 @@
+use crate::error::McspError;
 use crate::input_graph::pnet::{Marking, PetriNet, Place, Transition};
-use crate::input_graph::{ApMap, ParseImpl};
-use crate::utils::common::ParseOrQuit;
-use log::{error, warn};
+use crate::input_graph::{ApMap, ParseImpl, Rewards};
+use crate::utils::common::ParseOrError;
 use pest::iterators::{Pair, Pairs};
 use pest::Parser;
 use pest_derive::Parser;
-use std::process::exit;
 use std::str::FromStr;
 
 const PLACES_ID: &str = "P";
@@ -13,6 +12,9 @@ const GRAPH_ID: &str = "G";
 const INITIAL_MARKINGS_ID: &str = "M";
 const LAMBDAS_ID: &str = "L";
 const AP_MAP_ID: &str = "AP_MAP";
+const MARKING_REWARDS_ID: &str = "RM";
+const PLACE_REWARDS_ID: &str = "RP";
+const TRANSITION_REWARDS_ID: &str = "RT";
 
 #[derive(Parser)]
 #[grammar = "parser/petri_net.pest"]
@@ -21,15 +23,19 @@ pub struct InputParser;
 pub struct PetriNetParser;
 
 impl ParseImpl<PetriNet> for PetriNetParser {
-    fn parse(content: &str) -> Box<PetriNet> {
-        let pairs = InputParser::parse(Rule::Main, content).unwrap();
+    fn parse(content: &str) -> Result<Box<PetriNet>, McspError> {
+        let pairs = InputParser::parse(Rule::Main, content)?;
         let initial_marking = parse_list::<usize>(
             &pairs
                 .find_first_tagged(INITIAL_MARKINGS_ID)
                 .unwrap()
                 .into_inner(),
-        );
-        let lambdas = parse_list::<f64>(&pairs.find_first_tagged(LAMBDAS_ID).unwrap().into_inner());
+            "integer",
+        )?;
+        let lambdas = parse_list::<f64>(
+            &pairs.find_first_tagged(LAMBDAS_ID).unwrap().into_inner(),
+            "float",
+        )?;
         let all_places = pairs
             .find_first_tagged(PLACES_ID)
             .unwrap()
@@ -42,12 +48,10 @@ impl ParseImpl<PetriNet> for PetriNetParser {
         // Check whether there are the same no. of fire ratings as transitions
         let t_assigns = graph_rule.into_inner();
         if t_assigns.len() != lambdas.len() {
-            error!(
-                "{} fire rates were detected but there are {} transitions! Aborting...",
-                lambdas.len(),
-                t_assigns.len()
-            );
-            exit(0);
+            return Err(McspError::RateCountMismatch {
+                rates: lambdas.len(),
+                transitions: t_assigns.len(),
+            });
         }
 
         // Get all place names
@@ -80,18 +84,14 @@ impl ParseImpl<PetriNet> for PetriNetParser {
                             .unwrap()
                             .as_str();
                         if !all_places.contains(&place_name) {
-                            warn!(
-                                "Place \"{}\" was not found in set P. Skipping it...",
-                                place_name
-                            );
-                            continue;
+                            return Err(McspError::UnknownPlace(place_name.into()));
                         }
                         let token_result = place_rule
                             .clone()
                             .into_inner()
                             .find(|pair| pair.as_rule() == Rule::tokens);
                         let tokens: usize = match token_result {
-                            Some(tokens_rule) => tokens_rule.as_str().parse().unwrap(),
+                            Some(tokens_rule) => tokens_rule.as_str().parse_or_error("integer")?,
                             None => 1,
                         };
                         let place_index = all_places.iter().position(|s| *s == place_name).unwrap();
@@ -110,13 +110,10 @@ impl ParseImpl<PetriNet> for PetriNetParser {
 
         // If markings and detected places in transition assignments not equal, exit with error msg
         if all_places.len() != initial_marking.len() {
-            error!(
-                "{} places were detected but initial marking has {} places",
-                all_places.len(),
-                initial_marking.len()
-            );
-            error!("Detected places: {:?}", all_places);
-            exit(0);
+            return Err(McspError::MarkingLengthMismatch {
+                places: all_places.len(),
+                marking: initial_marking.len(),
+            });
         }
 
         // Transform place names to actual places
@@ -131,33 +128,76 @@ impl ParseImpl<PetriNet> for PetriNetParser {
             .collect::<Vec<Place>>();
 
         let ap_map_pairs = pairs.find_first_tagged(AP_MAP_ID).unwrap();
-        let ap_map = transform_ap_map(ap_map_pairs);
+        let ap_map = transform_ap_map(ap_map_pairs)?;
+        let rewards = parse_rewards(&pairs, places.len(), transitions.len())?;
         let petri_net = PetriNet {
             places,
             transitions,
             ap_map,
             initial_marking,
+            rewards,
         };
-        Box::new(petri_net)
+        Ok(Box::new(petri_net))
     }
 }
 
-fn parse_list<T>(list: &Pairs<Rule>) -> Vec<T>
+fn parse_list<T>(list: &Pairs<Rule>, type_name: &str) -> Result<Vec<T>, McspError>
 where
     T: FromStr,
 {
     let mut tmp_vec: Vec<T> = Vec::new();
     for rule in list.clone() {
-        let input_string = rule.as_str();
-        match input_string.parse::<T>() {
-            Ok(value) => tmp_vec.push(value),
-            Err(_) => panic!("{} is not a valid! Terminating...", input_string),
+        tmp_vec.push(rule.as_str().parse_or_error(type_name)?);
+    }
+    Ok(tmp_vec)
+}
+
+fn parse_rewards(
+    pairs: &Pairs<Rule>,
+    places: usize,
+    transitions: usize,
+) -> Result<Rewards, McspError> {
+    let mut rewards = Rewards::default();
+    if let Some(marking_rewards) = pairs.find_first_tagged(MARKING_REWARDS_ID) {
+        for marking_reward in marking_rewards.into_inner() {
+            let mut elements = marking_reward.into_inner();
+            let reward: f64 = elements.next().unwrap().as_str().parse_or_error("float")?;
+            for marking_rule in elements.next().unwrap().into_inner() {
+                let marking: Marking = marking_rule
+                    .into_inner()
+                    .map(|int_rule| int_rule.as_str().parse_or_error("integer"))
+                    .collect::<Result<_, _>>()?;
+                if marking.len() != places {
+                    return Err(McspError::MarkingLengthMismatch {
+                        places,
+                        marking: marking.len(),
+                    });
+                }
+                rewards.markings.insert(marking, reward);
+            }
+        }
+    }
+    for (section, expected, values) in [
+        (PLACE_REWARDS_ID, places, &mut rewards.places),
+        (TRANSITION_REWARDS_ID, transitions, &mut rewards.transitions),
+    ] {
+        if let Some(pair) = pairs.find_first_tagged(section) {
+            let tuple = pair.into_inner().next().unwrap();
+            let parsed = parse_list::<f64>(&tuple.into_inner(), "float")?;
+            if parsed.len() != expected {
+                return Err(McspError::RewardCountMismatch {
+                    section: section.into(),
+                    rewards: parsed.len(),
+                    expected,
+                });
+            }
+            *values = parsed;
         }
     }
-    tmp_vec
+    Ok(rewards)
 }
 
-pub fn transform_ap_map(pair: Pair<Rule>) -> ApMap<Marking> {
+pub fn transform_ap_map(pair: Pair<Rule>) -> Result<ApMap<Marking>, McspError> {
     assert_eq!(pair.as_rule(), Rule::AP);
     let mut ap_map = ApMap::new();
     for ap_assign in pair.clone().into_inner() {
@@ -170,14 +210,14 @@ pub fn transform_ap_map(pair: Pair<Rule>) -> ApMap<Marking> {
                     .map(|marking| {
                         marking
                             .into_inner()
-                            .map(|int_rule| int_rule.as_str().parse_or_quit("integer"))
+                            .map(|int_rule| int_rule.as_str().parse_or_error("integer"))
                             .collect()
                     })
-                    .collect();
+                    .collect::<Result<_, _>>()?;
                 ap_map.insert(ap.as_str().into(), markings);
             }
             (_, _) => panic!(),
         }
     }
-    ap_map
+    Ok(ap_map)
 }
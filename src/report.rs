@@ -0,0 +1,937 @@
+use crate::analysis::behaviour::{BehaviourResult, FiringPath};
+use crate::analysis::coverability::{BoundednessResult, Tokens};
+use crate::analysis::structural::{PlaceSet, StructuralResult};
+use crate::analysis::NetStructure;
+use crate::mcsp::{CheckResult, Counterexample, Decision, GraphSize, Trace};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Format the result of a check is written to stdout in
+#[derive(clap::ValueEnum, Clone, Copy, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
+/// Renders the result of a check in the given format
+pub fn format_result<K>(result: &CheckResult<K>, format: OutputFormat) -> String
+where
+    K: AsRef<[usize]> + Ord,
+{
+    match format {
+        OutputFormat::Text => format_text(result),
+        OutputFormat::Json => format_json(result),
+        OutputFormat::Csv => format_csv(result),
+    }
+}
+
+fn format_text<K>(result: &CheckResult<K>) -> String
+where
+    K: AsRef<[usize]>,
+{
+    if let Some(value) = result.value {
+        // Integral values look the same as fractions
+        let exact = match &result.exact_value {
+            Some(exact) if exact.to_string() != number(value) => format!(" ({})", exact),
+            _ => match &result.value_bounds {
+                Some(bounds) if bounds.lo != bounds.hi => {
+                    format!(" in [{}, {}]", number(bounds.lo), number(bounds.hi))
+                }
+                _ => String::new(),
+            },
+        };
+        return format!(
+            "Value of the initial marking {:?}: {}{}\n",
+            result.initial_marking.as_ref(),
+            number(value),
+            exact
+        );
+    }
+    match result.satisfied {
+        true => format!(
+            "Initial marking: {:?} satisfies the formula. So the petri net also satifies the formula.\n",
+            result.initial_marking.as_ref()
+        ),
+        false => match &result.counterexample {
+            Some(Counterexample::Paths(paths)) => {
+                let mut out = format!(
+                    "Initial marking: {:?} violates the formula. Most probable counterexample paths:\n",
+                    result.initial_marking.as_ref()
+                );
+                for path in paths {
+                    writeln!(out, "  {}: {}", number(path.probability), text_trace(path)).unwrap();
+                }
+                out
+            }
+            Some(Counterexample::Lasso { prefix, cycle }) => format!(
+                "Initial marking: {:?} violates the formula. Counterexample run:\n  prefix: {}\n  cycle: {}\n",
+                result.initial_marking.as_ref(),
+                text_trace(prefix),
+                text_trace(cycle)
+            ),
+            None => String::new(),
+        },
+    }
+}
+
+fn format_json<K>(result: &CheckResult<K>) -> String
+where
+    K: AsRef<[usize]> + Ord,
+{
+    let satisfying_markings: Vec<String> = result
+        .satisfying_markings
+        .iter()
+        .map(|m| json_marking(m.as_ref()))
+        .collect();
+    let probabilities: Vec<String> = result
+        .probabilities
+        .iter()
+        .map(|(m, p)| {
+            format!(
+                "{{\"marking\": {}, \"min\": {}, \"max\": {}}}",
+                json_marking(m.as_ref()),
+                json_number(p.min),
+                json_number(p.max)
+            )
+        })
+        .collect();
+    let product = match &result.product {
+        Some(size) => json_graph_size(size),
+        None => "null".into(),
+    };
+
+    let mut out = String::new();
+    out.push_str("{\n");
+    match result.value {
+        None => writeln!(out, "  \"satisfied\": {},", result.satisfied).unwrap(),
+        Some(value) => writeln!(out, "  \"value\": {},", json_number(value)).unwrap(),
+    }
+    if let Some(exact) = &result.exact_value {
+        writeln!(
+            out,
+            "  \"exact_value\": {},",
+            json_string(&exact.to_string())
+        )
+        .unwrap();
+    }
+    if let Some(bounds) = &result.value_bounds {
+        writeln!(
+            out,
+            "  \"value_bounds\": [{}, {}],",
+            json_number(bounds.lo),
+            json_number(bounds.hi)
+        )
+        .unwrap();
+    }
+    writeln!(
+        out,
+        "  \"initial_marking\": {},",
+        json_marking(result.initial_marking.as_ref())
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "  \"satisfying_markings\": [{}],",
+        satisfying_markings.join(", ")
+    )
+    .unwrap();
+    writeln!(out, "  \"probabilities\": [{}],", probabilities.join(", ")).unwrap();
+    writeln!(
+        out,
+        "  \"reachability_graph\": {},",
+        json_graph_size(&result.reach_graph)
+    )
+    .unwrap();
+    writeln!(out, "  \"product_mdp\": {},", product).unwrap();
+    match &result.counterexample {
+        Some(Counterexample::Paths(paths)) => {
+            let paths: Vec<String> = paths.iter().map(json_trace).collect();
+            writeln!(
+                out,
+                "  \"counterexample\": {{\"paths\": [{}]}},",
+                paths.join(", ")
+            )
+            .unwrap();
+        }
+        Some(Counterexample::Lasso { prefix, cycle }) => writeln!(
+            out,
+            "  \"counterexample\": {{\"prefix\": {}, \"cycle\": {}}},",
+            json_trace(prefix),
+            json_trace(cycle)
+        )
+        .unwrap(),
+        None => {}
+    }
+    writeln!(
+        out,
+        "  \"timings_ms\": {{\"build\": {}, \"evaluation\": {}}}",
+        millis(result.timings.build),
+        millis(result.timings.evaluation)
+    )
+    .unwrap();
+    out.push_str("}\n");
+    out
+}
+
+/// One row per marking which either satisfies the formula or has a probability. The summary of
+/// the check is written as '#' comment lines in front of the header
+fn format_csv<K>(result: &CheckResult<K>) -> String
+where
+    K: AsRef<[usize]> + Ord,
+{
+    let mut out = String::new();
+    match result.value {
+        None => writeln!(out, "# satisfied,{}", result.satisfied).unwrap(),
+        Some(value) => writeln!(out, "# value,{}", number(value)).unwrap(),
+    }
+    if let Some(exact) = &result.exact_value {
+        writeln!(out, "# exact_value,{}", exact).unwrap();
+    }
+    if let Some(bounds) = &result.value_bounds {
+        writeln!(
+            out,
+            "# value_bounds,{},{}",
+            number(bounds.lo),
+            number(bounds.hi)
+        )
+        .unwrap();
+    }
+    writeln!(
+        out,
+        "# reachability_graph,{}",
+        csv_graph_size(&result.reach_graph)
+    )
+    .unwrap();
+    if let Some(size) = &result.product {
+        writeln!(out, "# product_mdp,{}", csv_graph_size(size)).unwrap();
+    }
+    match &result.counterexample {
+        Some(Counterexample::Paths(paths)) => {
+            for path in paths {
+                writeln!(out, "# counterexample_path,{}", csv_trace(path)).unwrap();
+            }
+        }
+        Some(Counterexample::Lasso { prefix, cycle }) => {
+            writeln!(out, "# counterexample_prefix,{}", csv_trace(prefix)).unwrap();
+            writeln!(out, "# counterexample_cycle,{}", csv_trace(cycle)).unwrap();
+        }
+        None => {}
+    }
+    writeln!(
+        out,
+        "# timings_ms,build={},evaluation={}",
+        millis(result.timings.build),
+        millis(result.timings.evaluation)
+    )
+    .unwrap();
+    out.push_str("marking,initial,satisfied,min,max\n");
+
+    let markings: BTreeSet<&K> = result
+        .satisfying_markings
+        .iter()
+        .chain(result.probabilities.keys())
+        .collect();
+    for marking in markings {
+        let (min, max) = match result.probabilities.get(marking) {
+            Some(p) => (number(p.min), number(p.max)),
+            None => (String::new(), String::new()),
+        };
+        let tokens: Vec<String> = marking.as_ref().iter().map(|t| t.to_string()).collect();
+        writeln!(
+            out,
+            "\"({})\",{},{},{},{}",
+            tokens.join(","),
+            *marking == result.initial_marking,
+            result.satisfying_markings.contains(marking),
+            min,
+            max
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// Renders the bound of every place found by the coverability analysis
+pub fn format_boundedness(result: &BoundednessResult, format: OutputFormat) -> String {
+    let mut out = String::new();
+    match format {
+        OutputFormat::Text => {
+            for place in &result.places {
+                match &place.witness {
+                    None => writeln!(out, "Place {} is bounded by {}", place.place, place.bound),
+                    Some(witness) => writeln!(
+                        out,
+                        "Place {} is unbounded: fire [{}] and repeat [{}]{}",
+                        place.place,
+                        witness.prefix.join(", "),
+                        witness.pump.join(", "),
+                        match witness.repeatable {
+                            true => "",
+                            false => " after pumping the other unbounded places",
+                        }
+                    ),
+                }
+                .unwrap();
+            }
+            match result.is_bounded() {
+                true => out.push_str("The petri net is bounded.\n"),
+                false => out.push_str("The petri net is unbounded.\n"),
+            }
+        }
+        OutputFormat::Json => {
+            let places: Vec<String> = result
+                .places
+                .iter()
+                .map(|place| {
+                    let bound = match place.bound {
+                        Tokens::Finite(tokens) => tokens.to_string(),
+                        Tokens::Omega => "null".into(),
+                    };
+                    let witness = match &place.witness {
+                        None => "null".into(),
+                        Some(witness) => format!(
+                            "{{\"prefix\": {}, \"pump\": {}, \"repeatable\": {}}}",
+                            json_strings(&witness.prefix),
+                            json_strings(&witness.pump),
+                            witness.repeatable
+                        ),
+                    };
+                    format!(
+                        "{{\"place\": {}, \"bound\": {}, \"witness\": {}}}",
+                        json_string(&place.place),
+                        bound,
+                        witness
+                    )
+                })
+                .collect();
+            out.push_str("{\n");
+            writeln!(out, "  \"bounded\": {},", result.is_bounded()).unwrap();
+            writeln!(out, "  \"coverability_tree_size\": {},", result.tree_size).unwrap();
+            writeln!(out, "  \"places\": [{}]", places.join(", ")).unwrap();
+            out.push_str("}\n");
+        }
+        OutputFormat::Csv => {
+            writeln!(out, "# bounded,{}", result.is_bounded()).unwrap();
+            writeln!(out, "# coverability_tree_size,{}", result.tree_size).unwrap();
+            out.push_str("place,bounded,bound,prefix,pump,repeatable\n");
+            for place in &result.places {
+                let bound = match place.bound {
+                    Tokens::Finite(tokens) => tokens.to_string(),
+                    Tokens::Omega => String::new(),
+                };
+                let (prefix, pump, repeatable) = match &place.witness {
+                    None => (String::new(), String::new(), String::new()),
+                    Some(witness) => (
+                        witness.prefix.join(" "),
+                        witness.pump.join(" "),
+                        witness.repeatable.to_string(),
+                    ),
+                };
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{}",
+                    place.place,
+                    place.witness.is_none(),
+                    bound,
+                    prefix,
+                    pump,
+                    repeatable
+                )
+                .unwrap();
+            }
+        }
+    }
+    out
+}
+
+/// Renders invariants, siphons and traps using the names of the places and transitions
+pub fn format_structure(
+    net: &NetStructure,
+    result: &StructuralResult,
+    format: OutputFormat,
+) -> String {
+    let transitions: Vec<String> = net.transitions.iter().map(|t| t.name.clone()).collect();
+    let mut out = String::new();
+    match format {
+        OutputFormat::Text => {
+            out.push_str("P-invariants:\n");
+            for y in &result.p_invariants {
+                let tokens: u64 = y
+                    .iter()
+                    .zip(&net.initial_marking)
+                    .map(|(w, m)| w * *m as u64)
+                    .sum();
+                writeln!(out, "  {} = {}", weighted_sum(y, &net.places), tokens).unwrap();
+            }
+            out.push_str("T-invariants:\n");
+            for x in &result.t_invariants {
+                writeln!(out, "  {}", weighted_sum(x, &transitions)).unwrap();
+            }
+            out.push_str("Minimal siphons:\n");
+            for (siphon, marked) in result.siphons.iter().zip(&result.siphon_has_marked_trap) {
+                writeln!(
+                    out,
+                    "  {{{}}}{}",
+                    place_names(siphon, &net.places).join(", "),
+                    match marked {
+                        true => "",
+                        false => " (contains no initially marked trap)",
+                    }
+                )
+                .unwrap();
+            }
+            out.push_str("Minimal traps:\n");
+            for trap in &result.traps {
+                writeln!(out, "  {{{}}}", place_names(trap, &net.places).join(", ")).unwrap();
+            }
+            out.push_str("Place bounds from P-invariants:\n");
+            for (place, bound) in net.places.iter().zip(&result.place_bounds) {
+                match bound {
+                    Some(bound) => writeln!(out, "  {} <= {}", place, bound),
+                    None => writeln!(out, "  {} is not covered by a P-invariant", place),
+                }
+                .unwrap();
+            }
+        }
+        OutputFormat::Json => {
+            let p_invariants: Vec<String> = result
+                .p_invariants
+                .iter()
+                .map(|y| json_weights(y, &net.places))
+                .collect();
+            let t_invariants: Vec<String> = result
+                .t_invariants
+                .iter()
+                .map(|x| json_weights(x, &transitions))
+                .collect();
+            let siphons: Vec<String> = result
+                .siphons
+                .iter()
+                .zip(&result.siphon_has_marked_trap)
+                .map(|(siphon, marked)| {
+                    format!(
+                        "{{\"places\": {}, \"marked_trap\": {}}}",
+                        json_strings(&place_names(siphon, &net.places)),
+                        marked
+                    )
+                })
+                .collect();
+            let traps: Vec<String> = result
+                .traps
+                .iter()
+                .map(|trap| json_strings(&place_names(trap, &net.places)))
+                .collect();
+            let bounds: Vec<String> = net
+                .places
+                .iter()
+                .zip(&result.place_bounds)
+                .map(|(place, bound)| {
+                    let bound = match bound {
+                        Some(bound) => bound.to_string(),
+                        None => "null".into(),
+                    };
+                    format!("{}: {}", json_string(place), bound)
+                })
+                .collect();
+            out.push_str("{\n");
+            writeln!(
+                out,
+                "  \"structurally_bounded\": {},",
+                result.is_structurally_bounded()
+            )
+            .unwrap();
+            writeln!(out, "  \"p_invariants\": [{}],", p_invariants.join(", ")).unwrap();
+            writeln!(out, "  \"t_invariants\": [{}],", t_invariants.join(", ")).unwrap();
+            writeln!(out, "  \"siphons\": [{}],", siphons.join(", ")).unwrap();
+            writeln!(out, "  \"traps\": [{}],", traps.join(", ")).unwrap();
+            writeln!(out, "  \"place_bounds\": {{{}}}", bounds.join(", ")).unwrap();
+            out.push_str("}\n");
+        }
+        OutputFormat::Csv => {
+            writeln!(
+                out,
+                "# structurally_bounded,{}",
+                result.is_structurally_bounded()
+            )
+            .unwrap();
+            out.push_str("kind,elements,weights,marked_trap\n");
+            let mut write_row =
+                |kind: &str, elements: Vec<String>, weights: String, marked: String| {
+                    writeln!(
+                        out,
+                        "{},{},{},{}",
+                        kind,
+                        elements.join(" "),
+                        weights,
+                        marked
+                    )
+                    .unwrap()
+                };
+            for (kind, vectors, names) in [
+                ("p_invariant", &result.p_invariants, &net.places),
+                ("t_invariant", &result.t_invariants, &transitions),
+            ] {
+                for vector in vectors {
+                    let support: Vec<usize> =
+                        (0..vector.len()).filter(|i| vector[*i] > 0).collect();
+                    let weights: Vec<String> =
+                        support.iter().map(|i| vector[*i].to_string()).collect();
+                    let elements = support.iter().map(|i| names[*i].clone()).collect();
+                    write_row(kind, elements, weights.join(" "), String::new());
+                }
+            }
+            for (siphon, marked) in result.siphons.iter().zip(&result.siphon_has_marked_trap) {
+                write_row(
+                    "siphon",
+                    place_names(siphon, &net.places),
+                    String::new(),
+                    marked.to_string(),
+                );
+            }
+            for trap in &result.traps {
+                write_row(
+                    "trap",
+                    place_names(trap, &net.places),
+                    String::new(),
+                    String::new(),
+                );
+            }
+        }
+    }
+    out
+}
+
+/// Renders the controllable transitions a scheduler disables in every marking
+pub fn format_scheduler<K>(decisions: &[Decision<K>], format: OutputFormat) -> String
+where
+    K: AsRef<[usize]>,
+{
+    let mut out = String::new();
+    match format {
+        OutputFormat::Text => {
+            for decision in decisions {
+                let memory = match &decision.memory {
+                    Some(memory) => format!(" in automaton state {}", memory),
+                    None => String::new(),
+                };
+                let disabled = match decision.disabled.is_empty() {
+                    true => "nothing".into(),
+                    false => decision.disabled.join(", "),
+                };
+                writeln!(
+                    out,
+                    "Marking {:?}{}: disable {}",
+                    decision.marking.as_ref(),
+                    memory,
+                    disabled
+                )
+                .unwrap();
+            }
+        }
+        OutputFormat::Json => {
+            let decisions: Vec<String> = decisions
+                .iter()
+                .map(|decision| {
+                    let memory = match &decision.memory {
+                        Some(memory) => json_string(memory),
+                        None => "null".into(),
+                    };
+                    format!(
+                        "{{\"marking\": {}, \"memory\": {}, \"disabled\": {}}}",
+                        json_marking(decision.marking.as_ref()),
+                        memory,
+                        json_strings(&decision.disabled)
+                    )
+                })
+                .collect();
+            out.push_str("{\n");
+            writeln!(out, "  \"scheduler\": [{}]", decisions.join(", ")).unwrap();
+            out.push_str("}\n");
+        }
+        OutputFormat::Csv => {
+            out.push_str("marking,memory,disabled\n");
+            for decision in decisions {
+                let tokens: Vec<String> = decision
+                    .marking
+                    .as_ref()
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect();
+                writeln!(
+                    out,
+                    "\"({})\",{},{}",
+                    tokens.join(","),
+                    decision.memory.as_deref().unwrap_or(""),
+                    decision.disabled.join(" ")
+                )
+                .unwrap();
+            }
+        }
+    }
+    out
+}
+
+/// Renders deadlocks, liveness levels, home markings and reversibility with their witnesses
+pub fn format_behaviour<K>(result: &BehaviourResult<K>, format: OutputFormat) -> String
+where
+    K: AsRef<[usize]>,
+{
+    let mut out = String::new();
+    match format {
+        OutputFormat::Text => {
+            match result.is_deadlock_free() {
+                true => out.push_str("The petri net is deadlock free.\n"),
+                false => out.push_str("Dead markings:\n"),
+            }
+            for path in &result.dead_markings {
+                writeln!(out, "  {}", text_path(path)).unwrap();
+            }
+            out.push_str("Liveness:\n");
+            for transition in &result.liveness {
+                write!(out, "  {}: {:?}", transition.transition, transition.level).unwrap();
+                if let Some(path) = &transition.dead {
+                    write!(out, ", never fires again after {}", text_path(path)).unwrap();
+                }
+                out.push('\n');
+            }
+            match result.home_markings.is_empty() {
+                true => out.push_str(
+                    "There is no home marking. The following markings cannot reach each other:\n",
+                ),
+                false => out.push_str("Home markings:\n"),
+            }
+            for marking in &result.home_markings {
+                writeln!(out, "  {:?}", marking.as_ref()).unwrap();
+            }
+            for path in &result.home_witnesses {
+                writeln!(out, "  {}", text_path(path)).unwrap();
+            }
+            match &result.irreversible {
+                None => out.push_str("The petri net is reversible.\n"),
+                Some(path) => writeln!(
+                    out,
+                    "The petri net is not reversible: the initial marking cannot be reached again after {}",
+                    text_path(path)
+                )
+                .unwrap(),
+            }
+        }
+        OutputFormat::Json => {
+            let json_paths = |paths: &[FiringPath<K>]| -> String {
+                let paths: Vec<String> = paths.iter().map(json_path).collect();
+                format!("[{}]", paths.join(", "))
+            };
+            let json_option = |path: &Option<FiringPath<K>>| match path {
+                Some(path) => json_path(path),
+                None => "null".into(),
+            };
+            let liveness: Vec<String> = result
+                .liveness
+                .iter()
+                .map(|t| {
+                    format!(
+                        "{{\"transition\": {}, \"level\": \"{:?}\", \"enabled\": {}, \"dead\": {}}}",
+                        json_string(&t.transition),
+                        t.level,
+                        json_option(&t.enabled),
+                        json_option(&t.dead)
+                    )
+                })
+                .collect();
+            let home_markings: Vec<String> = result
+                .home_markings
+                .iter()
+                .map(|m| json_marking(m.as_ref()))
+                .collect();
+            out.push_str("{\n");
+            writeln!(
+                out,
+                "  \"dead_markings\": {},",
+                json_paths(&result.dead_markings)
+            )
+            .unwrap();
+            writeln!(out, "  \"liveness\": [{}],", liveness.join(", ")).unwrap();
+            writeln!(out, "  \"home_markings\": [{}],", home_markings.join(", ")).unwrap();
+            writeln!(
+                out,
+                "  \"home_witnesses\": {},",
+                json_paths(&result.home_witnesses)
+            )
+            .unwrap();
+            writeln!(out, "  \"reversible\": {},", result.is_reversible()).unwrap();
+            writeln!(
+                out,
+                "  \"irreversible\": {}",
+                json_option(&result.irreversible)
+            )
+            .unwrap();
+            out.push_str("}\n");
+        }
+        OutputFormat::Csv => {
+            writeln!(out, "# deadlock_free,{}", result.is_deadlock_free()).unwrap();
+            writeln!(out, "# reversible,{}", result.is_reversible()).unwrap();
+            out.push_str("property,subject,value,marking,path\n");
+            let mut write_row =
+                |property: &str, subject: &str, value: String, path: Option<&FiringPath<K>>| {
+                    let (marking, transitions) = match path {
+                        Some(path) => (
+                            csv_marking(path.marking.as_ref()),
+                            path.transitions.join(" "),
+                        ),
+                        None => (String::new(), String::new()),
+                    };
+                    writeln!(
+                        out,
+                        "{},{},{},{},{}",
+                        property, subject, value, marking, transitions
+                    )
+                    .unwrap()
+                };
+            for path in &result.dead_markings {
+                write_row("dead_marking", "", String::new(), Some(path));
+            }
+            for t in &result.liveness {
+                write_row(
+                    "liveness",
+                    &t.transition,
+                    format!("{:?}", t.level),
+                    t.dead.as_ref(),
+                );
+            }
+            for path in &result.home_witnesses {
+                write_row("home_witness", "", String::new(), Some(path));
+            }
+            if let Some(path) = &result.irreversible {
+                write_row("irreversible", "", String::new(), Some(path));
+            }
+            for marking in &result.home_markings {
+                writeln!(out, "home_marking,,,{},", csv_marking(marking.as_ref())).unwrap();
+            }
+        }
+    }
+    out
+}
+
+fn text_path<K: AsRef<[usize]>>(path: &FiringPath<K>) -> String {
+    format!(
+        "[{}] reaching {:?}",
+        path.transitions.join(", "),
+        path.marking.as_ref()
+    )
+}
+
+fn json_path<K: AsRef<[usize]>>(path: &FiringPath<K>) -> String {
+    format!(
+        "{{\"transitions\": {}, \"marking\": {}}}",
+        json_strings(&path.transitions),
+        json_marking(path.marking.as_ref())
+    )
+}
+
+/// Markings and the transitions between them like [1, 0] -t1-> [0, 1], each marking followed by
+/// the state of the automaton if there is one
+fn text_trace<K: AsRef<[usize]>>(trace: &Trace<K>) -> String {
+    let mut out = String::new();
+    for (i, marking) in trace.markings.iter().enumerate() {
+        if i > 0 {
+            write!(out, " -{}-> ", trace.transitions[i - 1]).unwrap();
+        }
+        write!(out, "{:?}", marking.as_ref()).unwrap();
+        if let Some(state) = trace.automaton_states.get(i) {
+            write!(out, " in {}", state).unwrap();
+        }
+    }
+    out
+}
+
+fn json_trace<K: AsRef<[usize]>>(trace: &Trace<K>) -> String {
+    let markings: Vec<String> = trace
+        .markings
+        .iter()
+        .map(|m| json_marking(m.as_ref()))
+        .collect();
+    format!(
+        "{{\"probability\": {}, \"markings\": [{}], \"automaton_states\": {}, \"transitions\": {}}}",
+        json_number(trace.probability),
+        markings.join(", "),
+        json_strings(&trace.automaton_states),
+        json_strings(&trace.transitions)
+    )
+}
+
+/// Probability followed by the quoted markings and transitions separated by spaces
+fn csv_trace<K: AsRef<[usize]>>(trace: &Trace<K>) -> String {
+    let mut steps = Vec::new();
+    for (i, marking) in trace.markings.iter().enumerate() {
+        if i > 0 {
+            steps.push(trace.transitions[i - 1].clone());
+        }
+        let tokens: Vec<String> = marking.as_ref().iter().map(|t| t.to_string()).collect();
+        let mut step = format!("({})", tokens.join(","));
+        if let Some(state) = trace.automaton_states.get(i) {
+            write!(step, "/{}", state).unwrap();
+        }
+        steps.push(step);
+    }
+    format!("{},\"{}\"", number(trace.probability), steps.join(" "))
+}
+
+fn csv_marking(marking: &[usize]) -> String {
+    let tokens: Vec<String> = marking.iter().map(|t| t.to_string()).collect();
+    format!("\"({})\"", tokens.join(","))
+}
+
+fn place_names(set: &PlaceSet, places: &[String]) -> Vec<String> {
+    set.iter().map(|p| places[*p].clone()).collect()
+}
+
+/// Renders a vector like 2*P1 + P3 leaving out zero entries
+fn weighted_sum(weights: &[u64], names: &[String]) -> String {
+    let terms: Vec<String> = weights
+        .iter()
+        .zip(names)
+        .filter(|(w, _)| **w > 0)
+        .map(|(w, name)| match w {
+            1 => name.clone(),
+            _ => format!("{}*{}", w, name),
+        })
+        .collect();
+    terms.join(" + ")
+}
+
+fn json_weights(weights: &[u64], names: &[String]) -> String {
+    let entries: Vec<String> = weights
+        .iter()
+        .zip(names)
+        .filter(|(w, _)| **w > 0)
+        .map(|(w, name)| format!("{}: {}", json_string(name), w))
+        .collect();
+    format!("{{{}}}", entries.join(", "))
+}
+
+/// Expected rewards are infinite if the goal may be missed
+fn number(value: f64) -> String {
+    match value.is_infinite() {
+        true if value > 0.0 => "Infinity".into(),
+        true => "-Infinity".into(),
+        false => value.to_string(),
+    }
+}
+
+/// JSON has no infinite numbers and no NaN, so they are written as the strings "Infinity",
+/// "-Infinity" and "NaN"
+fn json_number(value: f64) -> String {
+    match value.is_finite() {
+        true => number(value),
+        false => json_string(&number(value)),
+    }
+}
+
+fn json_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn json_strings(values: &[String]) -> String {
+    let strings: Vec<String> = values.iter().map(|v| json_string(v)).collect();
+    format!("[{}]", strings.join(", "))
+}
+
+fn json_marking(marking: &[usize]) -> String {
+    let tokens: Vec<String> = marking.iter().map(|t| t.to_string()).collect();
+    format!("[{}]", tokens.join(", "))
+}
+
+fn json_graph_size(size: &GraphSize) -> String {
+    format!(
+        "{{\"states\": {}, \"actions\": {}, \"transitions\": {}}}",
+        size.states, size.actions, size.transitions
+    )
+}
+
+fn csv_graph_size(size: &GraphSize) -> String {
+    format!(
+        "states={},actions={},transitions={}",
+        size.states, size.actions, size.transitions
+    )
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_result, OutputFormat};
+    use crate::logic::LogicType;
+    use crate::mcsp::{CheckOptions, CheckResult};
+    use crate::testing::{check_net, CHOICE_NET};
+
+    fn exact_result() -> CheckResult<Vec<usize>> {
+        let options = CheckOptions {
+            exact: true,
+            ..Default::default()
+        };
+        check_net(CHOICE_NET, LogicType::Pctl, "Pmax=? [F c]", &options)
+    }
+
+    #[test]
+    fn json_lists_the_value_and_the_probabilities() {
+        let json = format_result(&exact_result(), OutputFormat::Json);
+        let lines: Vec<&str> = json.lines().collect();
+        assert_eq!(
+            lines[..4],
+            [
+                "{",
+                "  \"value\": 0.6,",
+                "  \"exact_value\": \"3/5\",",
+                "  \"initial_marking\": [1, 0, 0, 0],"
+            ]
+        );
+        assert!(
+            json.contains("{\"marking\": [1, 0, 0, 0], \"min\": 0.3333333333333333, \"max\": 0.6}")
+        );
+        assert!(json.contains("\"product_mdp\": null,"));
+        assert!(json.ends_with("}\n"));
+    }
+
+    #[test]
+    fn json_writes_non_finite_numbers_as_strings() {
+        let mut result = exact_result();
+        result.exact_value = None;
+        for (value, expected) in [
+            (f64::NAN, "\"NaN\""),
+            (f64::INFINITY, "\"Infinity\""),
+            (f64::NEG_INFINITY, "\"-Infinity\""),
+        ] {
+            result.value = Some(value);
+            let json = format_result(&result, OutputFormat::Json);
+            assert_eq!(
+                json.lines().nth(1),
+                Some(format!("  \"value\": {},", expected).as_str())
+            );
+        }
+    }
+
+    #[test]
+    fn csv_has_a_row_for_every_marking() {
+        let csv = format_result(&exact_result(), OutputFormat::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[..2], ["# value,0.6", "# exact_value,3/5"]);
+        let header = lines
+            .iter()
+            .position(|l| *l == "marking,initial,satisfied,min,max")
+            .unwrap();
+        assert_eq!(
+            lines[header + 1..],
+            [
+                "\"(0,0,0,1)\",false,false,0,0",
+                "\"(0,0,1,0)\",false,false,1,1",
+                "\"(0,1,0,0)\",false,false,0.3333333333333333,0.6",
+                "\"(1,0,0,0)\",true,false,0.3333333333333333,0.6",
+            ]
+        );
+    }
+}
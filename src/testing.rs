@@ -0,0 +1,61 @@
+use crate::input_graph::dpnet::Marking;
+use crate::input_graph::ParseImpl;
+use crate::logic::{parse_formula, LogicType};
+use crate::mcsp::{check, CheckOptions, CheckResult};
+use crate::parser::dpn_parser::DPetriNetParser;
+use crate::parser::petri_net_parser::PetriNetParser;
+
+/// From B the net reaches C with probability 1/2 and the absorbing D with 1/3 if t6 is enabled,
+/// otherwise with 1/4 and 1/2. A and C return to B, so C is reached with probability 3/5 at most
+/// and 1/3 at least
+pub const CHOICE_NET: &str = "
+P = {A, B, C, D}
+G = {
+        {A} -> t1 -> {A},
+        {A} -> t2 -> {B},
+        {B} -> t3 -> {A},
+        {B} -> t4 -> {C},
+        {B} -> t5 -> {D},
+        {B} -> t6 -> {C},
+        {C} -> t7 -> {A},
+        {D} -> t8 -> {D}
+}
+C = {t6}
+M = (1, 0, 0, 0)
+L = (1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 1.0, 1.0)
+AP = {
+    (c, {(0,0,1,0)}),
+    (d, {(0,0,0,1)})
+}
+";
+
+/// Maximal probability of reaching C in `CHOICE_NET`
+pub const MAX_REACH_C: f64 = 0.6;
+/// Minimal probability of reaching C in `CHOICE_NET`
+pub const MIN_REACH_C: f64 = 1.0 / 3.0;
+
+/// Checks the formula against the decision net
+pub fn check_net(
+    net: &str,
+    logic_type: LogicType,
+    phi: &str,
+    options: &CheckOptions,
+) -> CheckResult<Marking> {
+    let content = format!("{}PHI = {}", net, phi);
+    let model = DPetriNetParser::parse(&content).unwrap();
+    let formula = parse_formula(logic_type, &content).unwrap();
+    check(model.as_ref(), &formula, options).unwrap()
+}
+
+/// Checks the formula against the Petri net without controllable transitions
+pub fn check_petri_net(
+    net: &str,
+    logic_type: LogicType,
+    phi: &str,
+    options: &CheckOptions,
+) -> CheckResult<Marking> {
+    let content = format!("{}PHI = {}", net, phi);
+    let model = PetriNetParser::parse(&content).unwrap();
+    let formula = parse_formula(logic_type, &content).unwrap();
+    check(model.as_ref(), &formula, options).unwrap()
+}
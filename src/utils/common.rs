@@ -1,8 +1,7 @@
-use log::error;
+use crate::error::McspError;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Display;
 use std::hash::Hash;
-use std::process::exit;
 use std::str::FromStr;
 
 #[derive(PartialEq)]
@@ -34,6 +33,16 @@ impl Comp {
         }
     }
 
+    /// Comparison holding for 1 - x and 1 - y iff this one holds for x and y
+    pub fn flipped(&self) -> Comp {
+        match self {
+            Comp::Less => Comp::Greater,
+            Comp::Leq => Comp::Geq,
+            Comp::Greater => Comp::Less,
+            Comp::Geq => Comp::Leq,
+        }
+    }
+
     pub fn is_upper_bound(&self) -> bool {
         match self {
             Comp::Less => true,
@@ -44,19 +53,15 @@ impl Comp {
     }
 }
 
-pub trait ParseOrQuit {
-    fn parse_or_quit<T: FromStr>(&self, type_name: &str) -> T;
+pub trait ParseOrError {
+    fn parse_or_error<T: FromStr>(&self, type_name: &str) -> Result<T, McspError>;
 }
-impl ParseOrQuit for &str {
-    fn parse_or_quit<T: FromStr>(&self, type_name: &str) -> T {
-        let test = self.parse::<T>();
-        match test {
-            Ok(value) => value,
-            Err(_) => {
-                error!("{} is not valid {}! Terminating", self, type_name);
-                exit(0);
-            }
-        }
+impl ParseOrError for &str {
+    fn parse_or_error<T: FromStr>(&self, type_name: &str) -> Result<T, McspError> {
+        self.parse::<T>().map_err(|_| McspError::InvalidValue {
+            value: self.to_string(),
+            expected: type_name.into(),
+        })
     }
 }
 
@@ -88,7 +93,7 @@ where
     let mut reversed_map = HashMap::new();
     for (k, set) in map {
         for v in set {
-            if reversed_map.get(v).is_none() {
+            if !reversed_map.contains_key(v) {
                 reversed_map.insert(v, HashSet::new());
             }
             reversed_map.get_mut(v).unwrap().insert(k);
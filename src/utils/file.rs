@@ -1,17 +1,16 @@
-use log::error;
-use std::{fs, io::ErrorKind, process::exit};
+use crate::error::McspError;
+use std::fs;
 
-pub fn read_file(path: &str) -> String {
-    let file_content = fs::read_to_string(path);
-    match file_content {
-        Ok(value) => value,
-        Err(err_type) => {
-            let error_msg: String = match err_type.kind() {
-                ErrorKind::NotFound => format!("File \"{}\" not found", path),
-                _ => format!("Error occurred while attempting to read {}", path),
-            };
-            error!("{}", error_msg);
-            exit(0);
-        }
-    }
+pub fn read_file(path: &str) -> Result<String, McspError> {
+    fs::read_to_string(path).map_err(|err| McspError::Io {
+        path: path.into(),
+        kind: err.kind(),
+    })
+}
+
+pub fn write_file(path: &str, content: &str) -> Result<(), McspError> {
+    fs::write(path, content).map_err(|err| McspError::Io {
+        path: path.into(),
+        kind: err.kind(),
+    })
 }
@@ -0,0 +1,95 @@
+use crate::utils::rational::Rational;
+use std::collections::BTreeMap;
+
+/// Numbers linear equation systems are solved over. The arithmetic is checked and returns None
+/// if it overflows or divides by zero
+pub trait Scalar: Copy + PartialEq {
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn checked_div(self, other: Self) -> Option<Self>;
+}
+
+impl Scalar for f64 {
+    const ZERO: f64 = 0.0;
+    const ONE: f64 = 1.0;
+
+    fn checked_add(self, other: f64) -> Option<f64> {
+        Some(self + other)
+    }
+
+    fn checked_sub(self, other: f64) -> Option<f64> {
+        Some(self - other)
+    }
+
+    fn checked_mul(self, other: f64) -> Option<f64> {
+        Some(self * other)
+    }
+
+    fn checked_div(self, other: f64) -> Option<f64> {
+        (other != 0.0).then(|| self / other)
+    }
+}
+
+impl Scalar for Rational {
+    const ZERO: Rational = Rational::ZERO;
+    const ONE: Rational = Rational::ONE;
+
+    fn checked_add(self, other: Rational) -> Option<Rational> {
+        Rational::checked_add(self, other)
+    }
+
+    fn checked_sub(self, other: Rational) -> Option<Rational> {
+        Rational::checked_sub(self, other)
+    }
+
+    fn checked_mul(self, other: Rational) -> Option<Rational> {
+        Rational::checked_mul(self, other)
+    }
+
+    fn checked_div(self, other: Rational) -> Option<Rational> {
+        Rational::checked_div(self, other)
+    }
+}
+
+/// Solves the linear equation system `rows` x = `rhs`, where every row maps its columns to the
+/// non-zero coefficients, by Gaussian elimination on the sparse rows. Rows are eliminated in
+/// their order without pivoting, which keeps the fill-in low and is stable for the nonsingular
+/// M-matrices of Markov chains. None if a pivot becomes 0 or the arithmetic fails
+pub fn solve<T: Scalar>(rows: Vec<BTreeMap<usize, T>>, rhs: Vec<T>) -> Option<Vec<T>> {
+    let n = rhs.len();
+    // Row i of the upper triangular matrix only has entries in the columns from i on
+    let mut upper: Vec<BTreeMap<usize, T>> = Vec::with_capacity(n);
+    let mut reduced_rhs: Vec<T> = Vec::with_capacity(n);
+    for (i, (mut row, mut value)) in rows.into_iter().zip(rhs).enumerate() {
+        // Eliminating a column only adds entries to the right of it, so the columns left of
+        // the diagonal are visited in ascending order
+        while let Some((&column, &entry)) = row.range(..i).next() {
+            row.remove(&column);
+            let pivot_row = &upper[column];
+            let factor = entry.checked_div(pivot_row[&column])?;
+            for (k, pivot_entry) in pivot_row.range(column + 1..) {
+                let target = row.entry(*k).or_insert(T::ZERO);
+                *target = target.checked_sub(factor.checked_mul(*pivot_entry)?)?;
+            }
+            value = value.checked_sub(factor.checked_mul(reduced_rhs[column])?)?;
+        }
+        if row.get(&i).is_none_or(|pivot| *pivot == T::ZERO) {
+            return None;
+        }
+        upper.push(row);
+        reduced_rhs.push(value);
+    }
+    let mut solution = vec![T::ZERO; n];
+    for i in (0..n).rev() {
+        let mut value = reduced_rhs[i];
+        for (k, entry) in upper[i].range(i + 1..) {
+            value = value.checked_sub(entry.checked_mul(solution[*k])?)?;
+        }
+        solution[i] = value.checked_div(upper[i][&i])?;
+    }
+    Some(solution)
+}
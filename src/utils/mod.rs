@@ -1,2 +1,5 @@
 pub mod file;
 pub mod common;
+pub mod linear;
+pub mod rational;
+pub mod simplex;
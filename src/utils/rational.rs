@@ -0,0 +1,177 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+/// Exact fraction in lowest terms with a positive denominator. Arithmetic is checked and returns
+/// None if a numerator or denominator does not fit into an i128
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numer: i128,
+    denom: i128,
+}
+
+impl Rational {
+    pub const ZERO: Rational = Rational { numer: 0, denom: 1 };
+    pub const ONE: Rational = Rational { numer: 1, denom: 1 };
+
+    pub fn new(numer: i128, denom: i128) -> Option<Rational> {
+        if denom == 0 {
+            return None;
+        }
+        let divisor = gcd(numer, denom);
+        let sign = if denom < 0 { -1 } else { 1 };
+        Some(Rational {
+            numer: sign * (numer / divisor),
+            denom: sign * (denom / divisor),
+        })
+    }
+
+    /// Parses a decimal number like "12", "0.25" or "-1.5" without any rounding
+    pub fn parse_decimal(value: &str) -> Option<Rational> {
+        let (negative, digits) = match value.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, value),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return None;
+        }
+        let mut numer: i128 = 0;
+        let mut denom: i128 = 1;
+        for (i, c) in integer.chars().chain(fraction.chars()).enumerate() {
+            numer = numer
+                .checked_mul(10)?
+                .checked_add(c.to_digit(10)? as i128)?;
+            if i >= integer.len() {
+                denom = denom.checked_mul(10)?;
+            }
+        }
+        Rational::new(if negative { -numer } else { numer }, denom)
+    }
+
+    /// The decimal number the float was written as, e.g. 0.1 is 1/10 instead of the binary
+    /// fraction closest to it
+    pub fn from_f64(value: f64) -> Option<Rational> {
+        Rational::parse_decimal(&value.to_string())
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.numer as f64 / self.denom as f64
+    }
+
+    pub fn checked_add(self, other: Rational) -> Option<Rational> {
+        let divisor = gcd(self.denom, other.denom);
+        let left = self.numer.checked_mul(other.denom / divisor)?;
+        let right = other.numer.checked_mul(self.denom / divisor)?;
+        Rational::new(
+            left.checked_add(right)?,
+            self.denom.checked_mul(other.denom / divisor)?,
+        )
+    }
+
+    pub fn checked_sub(self, other: Rational) -> Option<Rational> {
+        self.checked_add(Rational {
+            numer: -other.numer,
+            denom: other.denom,
+        })
+    }
+
+    pub fn checked_mul(self, other: Rational) -> Option<Rational> {
+        // Cancel first to keep the intermediate products small
+        let g1 = gcd(self.numer, other.denom);
+        let g2 = gcd(other.numer, self.denom);
+        Rational::new(
+            (self.numer / g1).checked_mul(other.numer / g2)?,
+            (self.denom / g2).checked_mul(other.denom / g1)?,
+        )
+    }
+
+    pub fn checked_div(self, other: Rational) -> Option<Rational> {
+        if other.numer == 0 {
+            return None;
+        }
+        self.checked_mul(Rational {
+            numer: other.denom,
+            denom: other.numer,
+        })
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numer == 0
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (
+            self.numer.checked_mul(other.denom),
+            other.numer.checked_mul(self.denom),
+        ) {
+            (Some(left), Some(right)) => left.cmp(&right),
+            // The difference of two distinct fractions this large is far above the f64 precision
+            _ => self.to_f64().total_cmp(&other.to_f64()),
+        }
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.denom {
+            1 => write!(f, "{}", self.numer),
+            _ => write!(f, "{}/{}", self.numer, self.denom),
+        }
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    match a {
+        0 => 1,
+        _ => a,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rational;
+
+    fn fraction(numer: i128, denom: i128) -> Rational {
+        Rational::new(numer, denom).unwrap()
+    }
+
+    #[test]
+    fn keeps_fractions_in_lowest_terms() {
+        assert_eq!(fraction(2, -4), fraction(-1, 2));
+        assert_eq!(Rational::parse_decimal("0.25"), Some(fraction(1, 4)));
+        assert_eq!(Rational::parse_decimal("-1.5"), Some(fraction(-3, 2)));
+        assert_eq!(Rational::from_f64(0.1), Some(fraction(1, 10)));
+        let sum = fraction(1, 3).checked_add(fraction(1, 6)).unwrap();
+        assert_eq!(sum, fraction(1, 2));
+        assert_eq!(sum.to_string(), "1/2");
+        assert_eq!(fraction(4, 2).to_string(), "2");
+        assert!(fraction(1, 3) < fraction(1, 2));
+    }
+
+    #[test]
+    fn overflowing_arithmetic_fails() {
+        let large = fraction(i128::MAX, 1);
+        assert_eq!(large.checked_add(Rational::ONE), None);
+        assert_eq!(large.checked_mul(fraction(2, 1)), None);
+        assert_eq!(Rational::ONE.checked_div(Rational::ZERO), None);
+        assert_eq!(Rational::new(1, 0), None);
+        // Cancelling before multiplying keeps the result representable
+        assert_eq!(
+            large.checked_mul(fraction(1, i128::MAX)),
+            Some(Rational::ONE)
+        );
+        assert!(fraction(1, i128::MAX) < fraction(1, i128::MAX - 1));
+    }
+}
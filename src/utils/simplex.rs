@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+/// Values below this are treated as 0 to absorb floating point errors
+const EPSILON: f64 = 1e-12;
+
+/// Optimal solution of a linear program
+pub struct LpSolution {
+    /// Values of the variables
+    pub values: Vec<f64>,
+    /// Values of the variables of the dual program, one per constraint
+    pub duals: Vec<f64>,
+}
+
+/// Maximizes `objective` x subject to `constraints` x <= `bounds` and x >= 0 with the simplex
+/// algorithm. Every constraint maps its columns to the non-zero coefficients, and the tableau
+/// keeps its rows sparse as well. The bounds must be non-negative, so the origin is a feasible
+/// start. Bland's rule keeps the algorithm from cycling. None if the program is unbounded
+pub fn maximize(
+    objective: &[f64],
+    constraints: &[BTreeMap<usize, f64>],
+    bounds: &[f64],
+) -> Option<LpSolution> {
+    let n = objective.len();
+    let m = bounds.len();
+    // Every row holds the coefficients of the variables and of the slack variables, sorted by
+    // their columns
+    let mut tableau: Vec<Vec<(usize, f64)>> = constraints
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut full_row: Vec<(usize, f64)> = row.iter().map(|(j, a)| (*j, *a)).collect();
+            full_row.push((n + i, 1.0));
+            full_row
+        })
+        .collect();
+    let mut rhs = bounds.to_vec();
+    // Reduced costs of all variables, the optimum is reached once none is negative
+    let mut costs: Vec<f64> = objective.iter().map(|c| -c).collect();
+    costs.extend(vec![0.0; m]);
+    let mut basis: Vec<usize> = (n..n + m).collect();
+
+    while let Some(entering) = (0..n + m).find(|j| costs[*j] < -EPSILON) {
+        let (leaving, _) = (0..m)
+            .filter_map(|i| {
+                let entry = coefficient(&tableau[i], entering)?;
+                (entry > EPSILON).then_some((i, rhs[i] / entry))
+            })
+            .min_by(|(i, ratio_i), (k, ratio_k)| {
+                ratio_i.total_cmp(ratio_k).then(basis[*i].cmp(&basis[*k]))
+            })?;
+        pivot(&mut tableau, &mut rhs, &mut costs, leaving, entering);
+        basis[leaving] = entering;
+    }
+
+    let mut values = vec![0.0; n];
+    for (row, variable) in basis.iter().enumerate() {
+        if *variable < n {
+            values[*variable] = rhs[row];
+        }
+    }
+    Some(LpSolution {
+        values,
+        duals: costs[n..n + m].to_vec(),
+    })
+}
+
+fn coefficient(row: &[(usize, f64)], column: usize) -> Option<f64> {
+    row.binary_search_by_key(&column, |(j, _)| *j)
+        .ok()
+        .map(|i| row[i].1)
+}
+
+fn pivot(
+    tableau: &mut [Vec<(usize, f64)>],
+    rhs: &mut [f64],
+    costs: &mut [f64],
+    row: usize,
+    column: usize,
+) {
+    let factor = coefficient(&tableau[row], column).unwrap();
+    tableau[row].iter_mut().for_each(|(_, a)| *a /= factor);
+    rhs[row] /= factor;
+    let pivot_row = tableau[row].clone();
+    let pivot_rhs = rhs[row];
+    for (i, other) in tableau.iter_mut().enumerate() {
+        let factor = match coefficient(other, column) {
+            Some(factor) if i != row && factor != 0.0 => factor,
+            _ => continue,
+        };
+        *other = subtract(other, &pivot_row, factor, column);
+        rhs[i] -= factor * pivot_rhs;
+    }
+    let factor = costs[column];
+    if factor != 0.0 {
+        for (k, p) in &pivot_row {
+            costs[*k] -= factor * p;
+        }
+    }
+}
+
+/// `row` - `factor` `pivot_row` by merging both rows. The entering column is eliminated exactly,
+/// dropping it keeps the row sparse
+fn subtract(
+    row: &[(usize, f64)],
+    pivot_row: &[(usize, f64)],
+    factor: f64,
+    column: usize,
+) -> Vec<(usize, f64)> {
+    let mut result = Vec::with_capacity(row.len() + pivot_row.len());
+    let (mut i, mut k) = (0, 0);
+    while i < row.len() || k < pivot_row.len() {
+        let (j, a) = match (row.get(i), pivot_row.get(k)) {
+            (Some((j, a)), Some((l, _))) if j < l => {
+                i += 1;
+                (*j, *a)
+            }
+            (Some((j, a)), Some((l, p))) if j == l => {
+                i += 1;
+                k += 1;
+                (*j, a - factor * p)
+            }
+            (_, Some((l, p))) => {
+                k += 1;
+                (*l, -factor * p)
+            }
+            (Some((j, a)), None) => {
+                i += 1;
+                (*j, *a)
+            }
+            (None, None) => unreachable!(),
+        };
+        if j != column {
+            result.push((j, a));
+        }
+    }
+    result
+}
@@ -0,0 +1,56 @@
+use mcsp::{
+    check, parse_formula, CheckOptions, DPetriNetParser, LogicType, McspError, ParseImpl,
+    PetriNetParser,
+};
+
+/// B is left for the absorbing C with probability 1/4 and for A otherwise
+const NET: &str = "
+P = {A, B, C}
+G = {
+        {A} -> t1 -> {B},
+        {B} -> t2 -> {A},
+        {B} -> t3 -> {C}
+}
+M = (1, 0, 0)
+L = (1.0, 3.0, 1.0)
+AP = {
+    (c, {(0,0,1)})
+}
+";
+
+fn content(net: &str, phi: &str) -> String {
+    format!("{}PHI = {}", net, phi)
+}
+
+#[test]
+fn checks_petri_nets_through_the_library() {
+    let content = content(NET, "P(F(c), >= 1.0)");
+    let model = PetriNetParser::parse(&content).unwrap();
+    let formula = parse_formula(LogicType::Pctl, &content).unwrap();
+    let result = check(model.as_ref(), &formula, &CheckOptions::default()).unwrap();
+    assert!(result.satisfied);
+    assert_eq!(result.initial_marking, vec![1, 0, 0]);
+    assert!(result.satisfying_markings.contains(&vec![1, 0, 0]));
+    assert_eq!(result.reach_graph.states, 3);
+}
+
+#[test]
+fn checks_decision_petri_nets_through_the_library() {
+    let net = NET.replace("M = ", "C = {t3}\nM = ");
+    let content = content(&net, "Pmin=? [F c]");
+    let model = DPetriNetParser::parse(&content).unwrap();
+    let formula = parse_formula(LogicType::Pctl, &content).unwrap();
+    let result = check(model.as_ref(), &formula, &CheckOptions::default()).unwrap();
+    // Disabling t3 forever never reaches C
+    assert_eq!(result.value, Some(0.0));
+}
+
+#[test]
+fn returns_errors_instead_of_exiting() {
+    let content = content(NET, "P(F(d), >= 1.0)");
+    let model = PetriNetParser::parse(&content).unwrap();
+    let formula = parse_formula(LogicType::Pctl, &content).unwrap();
+    let error = check(model.as_ref(), &formula, &CheckOptions::default()).unwrap_err();
+    assert_eq!(error, McspError::UnknownAp("d".into()));
+    assert_eq!(error.exit_code(), 5);
+}